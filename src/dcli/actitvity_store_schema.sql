@@ -1,5 +1,10 @@
 BEGIN TRANSACTION;
 
+DROP VIEW IF EXISTS "linked_member";
+
+DROP TABLE IF EXISTS "skill_rating_activity";
+DROP TABLE IF EXISTS "skill_rating";
+DROP TABLE IF EXISTS "combat_rating";
 DROP TABLE IF EXISTS "modes";
 DROP TABLE IF EXISTS "team_result";
 DROP TABLE IF EXISTS "weapon_result";
@@ -8,6 +13,11 @@ DROP TABLE IF EXISTS "activity_queue";
 DROP TABLE IF EXISTS "character_activity_stats";
 DROP TABLE IF EXISTS "activity";
 DROP TABLE IF EXISTS "character";
+DROP TABLE IF EXISTS "group_member";
+DROP TABLE IF EXISTS "group_subscription";
+DROP TABLE IF EXISTS "member_alias";
+DROP TABLE IF EXISTS "member_name";
+DROP TABLE IF EXISTS "member_link";
 DROP TABLE IF EXISTS "sync";
 DROP TABLE IF EXISTS "member";
 DROP TABLE IF EXISTS "version";
@@ -15,19 +25,26 @@ DROP TABLE IF EXISTS "version";
 DROP INDEX IF EXISTS "modes_activity_index";
 DROP INDEX IF EXISTS "character_activity_stats_char_index";
 DROP INDEX IF EXISTS "activity_period_index";
+DROP INDEX IF EXISTS "member_link_identity_index";
+DROP INDEX IF EXISTS "combat_rating_character_index";
+DROP INDEX IF EXISTS "skill_rating_member_index";
+DROP INDEX IF EXISTS "skill_rating_activity_index";
 
 
 CREATE TABLE IF NOT EXISTS "main"."version" (
     "version"   INTEGER NOT NULL UNIQUE
 );
 
-INSERT INTO "main"."version"("version") VALUES (10);
+INSERT INTO "main"."version"("version") VALUES (20);
 
 CREATE TABLE IF NOT EXISTS "main"."activity_queue" (
     "id"	INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
     "synced" INTEGER NOT NULL DEFAULT 0,
     "activity_id" INTEGER NOT NULL,
     "character"	INTEGER NOT NULL,
+    "attempts" INTEGER NOT NULL DEFAULT 0,
+    "last_error" TEXT,
+    "last_attempt" TEXT,
     UNIQUE("activity_id", "character"),
     FOREIGN KEY ("character")
         REFERENCES character ("character_id")
@@ -38,11 +55,35 @@ CREATE TABLE IF NOT EXISTS "sync" (
     "id"            INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
     "member"	    INTEGER NOT NULL,
     "last_sync"   TEXT NOT NULL,
+    "sync_since"    TEXT,
+    "max_activities"    INTEGER,
+    "history_since" TEXT,
+    "history_max_activities"    INTEGER,
+    "links_checked" TEXT,
     UNIQUE("member"),
     FOREIGN KEY ("member")
         REFERENCES "member" ("member_id")
 );
 
+CREATE TABLE IF NOT EXISTS "group_subscription" (
+    "group_id"          INTEGER PRIMARY KEY UNIQUE NOT NULL,
+    "remove_departed"   INTEGER NOT NULL DEFAULT 0,
+    "last_checked"      TEXT
+);
+
+CREATE TABLE IF NOT EXISTS "group_member" (
+    "id"        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+    "group_id"  INTEGER NOT NULL,
+    "member"    INTEGER NOT NULL,
+    UNIQUE("group_id", "member"),
+    FOREIGN KEY ("group_id")
+        REFERENCES "group_subscription" ("group_id")
+        ON DELETE CASCADE,
+    FOREIGN KEY ("member")
+        REFERENCES "member" ("member_id")
+        ON DELETE CASCADE
+);
+
 CREATE TABLE IF NOT EXISTS  "member" (
     "member_id"	    INTEGER PRIMARY KEY UNIQUE NOT NULL,
     "platform_id"	INTEGER NOT NULL,
@@ -51,6 +92,50 @@ CREATE TABLE IF NOT EXISTS  "member" (
     "bungie_display_name_code"  TEXT
 );
 
+--memberships (other platforms / cross save) which belong to the same player.
+--identity is the member_id of the players primary membership. has_profile is
+--1 if the membership has a Destiny profile which can be synced
+CREATE TABLE IF NOT EXISTS "member_link" (
+    "member"    INTEGER PRIMARY KEY UNIQUE NOT NULL,
+    "identity"  INTEGER NOT NULL,
+    "has_profile"   INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY ("member")
+        REFERENCES "member" ("member_id")
+        ON DELETE CASCADE
+);
+
+--each distinct bungie name seen for a member, with the periods of the first
+--and last activities it was seen in
+CREATE TABLE IF NOT EXISTS "member_name" (
+    "id"                        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+    "member"                    INTEGER NOT NULL,
+    "bungie_display_name"       TEXT NOT NULL,
+    "bungie_display_name_code"  TEXT NOT NULL,
+    "first_seen"                TEXT NOT NULL,
+    "last_seen"                 TEXT NOT NULL,
+    UNIQUE("member", "bungie_display_name", "bungie_display_name_code"),
+    FOREIGN KEY ("member")
+        REFERENCES "member" ("member_id")
+        ON DELETE CASCADE
+);
+
+--local aliases which can be used in place of a player's bungie name
+CREATE TABLE IF NOT EXISTS "member_alias" (
+    "alias"     TEXT PRIMARY KEY UNIQUE NOT NULL COLLATE NOCASE,
+    "member"    INTEGER NOT NULL,
+    FOREIGN KEY ("member")
+        REFERENCES "member" ("member_id")
+        ON DELETE CASCADE
+);
+
+--maps each member to all of the memberships linked to it (including itself)
+CREATE VIEW IF NOT EXISTS "linked_member" AS
+    SELECT member_id AS member, member_id AS linked FROM member
+    UNION
+    SELECT a.member, b.member
+    FROM member_link AS a
+    INNER JOIN member_link AS b ON a.identity = b.identity;
+
 CREATE TABLE IF NOT EXISTS  "character" (
     "character_id"	INTEGER PRIMARY KEY UNIQUE NOT NULL,
     "member"	    INTEGER NOT NULL,
@@ -61,13 +146,27 @@ CREATE TABLE IF NOT EXISTS  "character" (
         ON DELETE CASCADE
 );
 
+--combat ratings retrieved from the API. A row is kept each time a rating is
+--retrieved, so activities can display the rating as of when they were played
+CREATE TABLE IF NOT EXISTS "combat_rating" (
+    "id"        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+    "character" INTEGER NOT NULL,
+    "mode"      INTEGER NOT NULL,
+    "rating"    REAL NOT NULL,
+    "fetched"   TEXT NOT NULL,
+    FOREIGN KEY ("character")
+        REFERENCES "character" ("character_id")
+        ON DELETE CASCADE
+);
+
 CREATE TABLE IF NOT EXISTS "main"."activity" (
     "activity_id"	INTEGER PRIMARY KEY UNIQUE NOT NULL,
     "period"        TEXT NOT NULL,
     "mode"          INTEGER NOT NULL,
     "platform"      INTEGER NOT NULL,
     "director_activity_hash" INTEGER NOT NULL,
-    "reference_id"  INTEGER NOT NULL
+    "reference_id"  INTEGER NOT NULL,
+    "detail_pending" INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE TABLE IF NOT EXISTS "main"."modes" (
@@ -124,6 +223,37 @@ CREATE TABLE IF NOT EXISTS "main"."medal_result" (
         ON DELETE CASCADE
 );
 
+--skill ratings calculated by replaying team activities in order, per mode
+--family. A row is stored for each player in each rated activity, with their
+--rating before and after the activity
+CREATE TABLE IF NOT EXISTS "skill_rating" (
+    "id"                INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+    "member"            INTEGER NOT NULL,
+    "family"            INTEGER NOT NULL,
+    "activity"          INTEGER NOT NULL,
+    "period"            TEXT NOT NULL,
+    "pre_rating"        REAL NOT NULL,
+    "pre_deviation"     REAL NOT NULL,
+    "rating"            REAL NOT NULL,
+    "deviation"         REAL NOT NULL,
+    "volatility"        REAL NOT NULL,
+    UNIQUE("member", "activity"),
+    FOREIGN KEY ("member")
+        REFERENCES "member" ("member_id")
+        ON DELETE CASCADE,
+    FOREIGN KEY ("activity")
+        REFERENCES "activity" ("activity_id")
+        ON DELETE CASCADE
+);
+
+--activities which have been replayed when calculating skill ratings
+CREATE TABLE IF NOT EXISTS "skill_rating_activity" (
+    "activity"  INTEGER PRIMARY KEY UNIQUE NOT NULL,
+    FOREIGN KEY ("activity")
+        REFERENCES "activity" ("activity_id")
+        ON DELETE CASCADE
+);
+
 CREATE TABLE IF NOT EXISTS "main"."character_activity_stats" (
     "id"	INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
     "character"                 INTEGER NOT NULL,
@@ -168,6 +298,10 @@ CREATE TABLE IF NOT EXISTS "main"."character_activity_stats" (
 CREATE INDEX modes_activity_index ON modes (activity);
 CREATE INDEX character_activity_stats_char_index ON character_activity_stats (character);
 CREATE INDEX activity_period_index ON activity (period);
+CREATE INDEX member_link_identity_index ON member_link (identity);
+CREATE INDEX combat_rating_character_index ON combat_rating (character, mode, fetched);
+CREATE INDEX skill_rating_member_index ON skill_rating (member, family, period);
+CREATE INDEX skill_rating_activity_index ON skill_rating (activity);
 
 CREATE INDEX idx_character_activity_stats_activity ON character_activity_stats(activity);
 
@@ -0,0 +1,139 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use std::fmt;
+
+use crate::crucible::PLAYER_START_BUFFER;
+use structopt::StructOpt;
+
+/// Policy for which incomplete games (late joins, early exits and mercies)
+/// are included when retrieving and aggregating activities.
+///
+/// By default all games are included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityFilter {
+    pub include_late_joins: bool,
+    pub include_left_early: bool,
+    pub include_mercy: bool,
+
+    /// Number of seconds after the activity started that a player must have
+    /// joined after for the game to be considered a late join.
+    pub late_join_threshold_seconds: u32,
+}
+
+impl Default for ActivityFilter {
+    fn default() -> Self {
+        ActivityFilter {
+            include_late_joins: true,
+            include_left_early: true,
+            include_mercy: true,
+            late_join_threshold_seconds: PLAYER_START_BUFFER,
+        }
+    }
+}
+
+/// Count based window of the most recent games to include, applied after the
+/// time period and filter. Games are counted back from the most recent.
+///
+/// By default all games are included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ActivityWindow {
+    /// Number of most recent games to skip
+    pub skip: u32,
+
+    /// Maximum number of games to include. None includes all games.
+    pub count: Option<u32>,
+}
+
+impl ActivityWindow {
+    /// Returns the window with the same number of games immediately before
+    /// this one. Returns None if the window has no count.
+    pub fn previous(&self) -> Option<ActivityWindow> {
+        let count = self.count?;
+
+        Some(ActivityWindow {
+            skip: self.skip.saturating_add(count),
+            count: Some(count),
+        })
+    }
+
+    /// Value for an SQLite LIMIT clause. -1 is no limit
+    pub fn sql_limit(&self) -> i64 {
+        match self.count {
+            Some(e) => e as i64,
+            None => -1,
+        }
+    }
+}
+
+impl fmt::Display for ActivityWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.count {
+            None if self.skip == 0 => write!(f, "all games"),
+            None => write!(f, "all games before the last {}", self.skip),
+            Some(count) if self.skip == 0 => {
+                write!(f, "the last {} games", count)
+            }
+            Some(count) => write!(
+                f,
+                "games {} to {} back",
+                self.skip,
+                self.skip.saturating_add(count)
+            ),
+        }
+    }
+}
+
+/// Command line options for filtering games, shared by the tools which
+/// aggregate activities. Include via #[structopt(flatten)].
+#[derive(StructOpt, Debug)]
+pub struct ActivityFilterOpt {
+    /// Exclude games the player joined after they had started
+    ///
+    /// A game is considered a late join if the player joined more than
+    /// --late-join-seconds after the activity started.
+    #[structopt(long = "exclude-late-joins")]
+    pub exclude_late_joins: bool,
+
+    /// Number of seconds after an activity starts that joining is considered a late join
+    #[structopt(long = "late-join-seconds", default_value = "30")]
+    pub late_join_seconds: u32,
+
+    /// Exclude games the player left before they completed
+    #[structopt(long = "exclude-left-early")]
+    pub exclude_left_early: bool,
+
+    /// Exclude games that ended in a mercy
+    #[structopt(long = "exclude-mercy")]
+    pub exclude_mercy: bool,
+}
+
+impl ActivityFilterOpt {
+    pub fn filter(&self) -> ActivityFilter {
+        ActivityFilter {
+            include_late_joins: !self.exclude_late_joins,
+            include_left_early: !self.exclude_left_early,
+            include_mercy: !self.exclude_mercy,
+            late_join_threshold_seconds: self.late_join_seconds,
+        }
+    }
+}
@@ -27,7 +27,13 @@ use tell::{Tell, TellLevel};
 use chrono::{DateTime, Utc};
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 
+use crate::activityfilter::{ActivityFilter, ActivityWindow};
+use crate::metrics;
 use crate::playeractivitiessummary::PlayerActivitiesSummary;
+use crate::skillrating::{self, SkillRating};
+use crate::storearchive::{
+    ARCHIVE_FORMAT, ARCHIVE_HEADER_TYPE, ARCHIVE_TABLES, ARCHIVE_VERSION,
+};
 use crate::utils::{
     format_error, CHECKMATE_CLASH_ACTIVITY_HASH,
     CHECKMATE_CONTROL_ACTIVITY_HASHES, CHECKMATE_COUNTDOWN_ACTIVITY_HASH,
@@ -36,20 +42,26 @@ use crate::utils::{
     IRON_BANNER_FORTRESS_ACTIVITY_HASH, IRON_BANNER_TRIBUTE_ACTIVITY_HASH,
 };
 use crate::{
-    crucible::{CrucibleActivity, Member, PlayerName, Team},
+    crucible::{
+        CrucibleActivity, HistoryLimit, Member, PlayerName, PlayerSelector,
+        Team,
+    },
     enums::{
         completionreason::CompletionReason,
         itemtype::{ItemSubType, ItemType},
+        modefamily::ModeFamily,
         moment::DateTimePeriod,
         standing::Standing,
     },
-    response::pgcr::DestinyPostGameCarnageReportEntry,
+    response::{activities::Activity, pgcr::DestinyPostGameCarnageReportEntry},
 };
 use futures::TryStreamExt;
-use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
-use sqlx::Row;
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteConnectOptions, SqliteJournalMode};
+use sqlx::{Column, Row, Sqlite, TypeInfo, ValueRef};
 use sqlx::{ConnectOptions, SqliteConnection};
 
+use crate::character::PlayerInfo;
 use crate::crucible::{
     ActivityDetail, CruciblePlayerActivityPerformance,
     CruciblePlayerPerformance, CrucibleStats, ExtendedCrucibleStats, Item,
@@ -59,11 +71,13 @@ use crate::enums::character::{CharacterClass, CharacterClassSelection};
 use crate::enums::medaltier::MedalTier;
 use crate::enums::mode::Mode;
 use crate::enums::platform::Platform;
-use crate::{apiinterface::ApiInterface, manifestinterface::ManifestInterface};
+use crate::manifestinterface::ManifestInterface;
+use crate::{apiclient::RequestBudget, apiinterface::ApiInterface};
 use crate::{
     error::Error,
     response::pgcr::{
         DestinyHistoricalStatsValue, DestinyPostGameCarnageReportData,
+        UserInfoCard,
     },
     utils::{
         calculate_efficiency, calculate_kills_deaths_assists,
@@ -72,6 +86,12 @@ use crate::{
 };
 
 use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 
 use log::info;
 
@@ -80,17 +100,270 @@ const STORE_DB_SCHEMA: &str = include_str!("../actitvity_store_schema.sql");
 
 const DCLI_FIX_DATA: &str = "DCLI_FIX_DATA";
 
+//number of activities to rate above which a message is displayed, as
+//replaying them can take a while
+const SKILL_RATING_PROGRESS_COUNT: usize = 1000;
+
 //number of simultaneous requests we make to server when retrieving activity history
 const PGCR_REQUEST_CHUNK_AMOUNT: usize = 50;
 
-const DB_SCHEMA_VERSION: i32 = 10;
+const DB_SCHEMA_VERSION: i32 = 20;
+
+//oldest schema version which can be updated in place, so the store doesnt
+//have to be synced again
+const MIN_MIGRATABLE_DB_SCHEMA_VERSION: i32 = 10;
+
+//migrations which update the store in place, in order starting from
+//MIN_MIGRATABLE_DB_SCHEMA_VERSION. Each updates the store by one version.
+const STORE_DB_MIGRATIONS: &[&str] = &[
+    r#"
+    BEGIN TRANSACTION;
+    ALTER TABLE "activity_queue"
+        ADD COLUMN "attempts" INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE "activity_queue" ADD COLUMN "last_error" TEXT;
+    ALTER TABLE "activity_queue" ADD COLUMN "last_attempt" TEXT;
+    INSERT INTO "main"."version"("version") VALUES (11);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "group_subscription" (
+        "group_id"          INTEGER PRIMARY KEY UNIQUE NOT NULL,
+        "remove_departed"   INTEGER NOT NULL DEFAULT 0,
+        "last_checked"      TEXT
+    );
+    CREATE TABLE IF NOT EXISTS "group_member" (
+        "id"        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+        "group_id"  INTEGER NOT NULL,
+        "member"    INTEGER NOT NULL,
+        UNIQUE("group_id", "member"),
+        FOREIGN KEY ("group_id")
+            REFERENCES "group_subscription" ("group_id")
+            ON DELETE CASCADE,
+        FOREIGN KEY ("member")
+            REFERENCES "member" ("member_id")
+            ON DELETE CASCADE
+    );
+    INSERT INTO "main"."version"("version") VALUES (12);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "member_link" (
+        "member"    INTEGER PRIMARY KEY UNIQUE NOT NULL,
+        "identity"  INTEGER NOT NULL,
+        FOREIGN KEY ("member")
+            REFERENCES "member" ("member_id")
+            ON DELETE CASCADE
+    );
+    CREATE VIEW IF NOT EXISTS "linked_member" AS
+        SELECT member_id AS member, member_id AS linked FROM member
+        UNION
+        SELECT a.member, b.member
+        FROM member_link AS a
+        INNER JOIN member_link AS b ON a.identity = b.identity;
+    CREATE INDEX member_link_identity_index ON member_link (identity);
+    INSERT INTO "main"."version"("version") VALUES (13);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "member_name" (
+        "id"                        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+        "member"                    INTEGER NOT NULL,
+        "bungie_display_name"       TEXT NOT NULL,
+        "bungie_display_name_code"  TEXT NOT NULL,
+        "first_seen"                TEXT NOT NULL,
+        "last_seen"                 TEXT NOT NULL,
+        UNIQUE("member", "bungie_display_name", "bungie_display_name_code"),
+        FOREIGN KEY ("member")
+            REFERENCES "member" ("member_id")
+            ON DELETE CASCADE
+    );
+    INSERT OR IGNORE INTO "member_name"
+        ("member", "bungie_display_name", "bungie_display_name_code",
+        "first_seen", "last_seen")
+    SELECT
+        member.member_id, member.bungie_display_name,
+        member.bungie_display_name_code,
+        MIN(activity.period), MAX(activity.period)
+    FROM
+        member
+    INNER JOIN
+        character ON character.member = member.member_id
+    INNER JOIN
+        character_activity_stats
+            ON character_activity_stats.character = character.character_id
+    INNER JOIN
+        activity ON activity.activity_id = character_activity_stats.activity
+    WHERE
+        member.bungie_display_name IS NOT NULL AND
+        member.bungie_display_name_code IS NOT NULL
+    GROUP BY
+        member.member_id;
+    INSERT INTO "main"."version"("version") VALUES (14);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "member_alias" (
+        "alias"     TEXT PRIMARY KEY UNIQUE NOT NULL COLLATE NOCASE,
+        "member"    INTEGER NOT NULL,
+        FOREIGN KEY ("member")
+            REFERENCES "member" ("member_id")
+            ON DELETE CASCADE
+    );
+    INSERT INTO "main"."version"("version") VALUES (15);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    ALTER TABLE "sync" ADD COLUMN "sync_since" TEXT;
+    ALTER TABLE "sync" ADD COLUMN "max_activities" INTEGER;
+    ALTER TABLE "sync" ADD COLUMN "history_since" TEXT;
+    ALTER TABLE "sync" ADD COLUMN "history_max_activities" INTEGER;
+    INSERT INTO "main"."version"("version") VALUES (16);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    ALTER TABLE "activity"
+        ADD COLUMN "detail_pending" INTEGER NOT NULL DEFAULT 0;
+    INSERT INTO "main"."version"("version") VALUES (17);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "combat_rating" (
+        "id"        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+        "character" INTEGER NOT NULL,
+        "mode"      INTEGER NOT NULL,
+        "rating"    REAL NOT NULL,
+        "fetched"   TEXT NOT NULL,
+        FOREIGN KEY ("character")
+            REFERENCES "character" ("character_id")
+            ON DELETE CASCADE
+    );
+    CREATE INDEX combat_rating_character_index
+        ON combat_rating (character, mode, fetched);
+    INSERT INTO "main"."version"("version") VALUES (18);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    CREATE TABLE IF NOT EXISTS "skill_rating" (
+        "id"                INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT UNIQUE,
+        "member"            INTEGER NOT NULL,
+        "family"            INTEGER NOT NULL,
+        "activity"          INTEGER NOT NULL,
+        "period"            TEXT NOT NULL,
+        "pre_rating"        REAL NOT NULL,
+        "pre_deviation"     REAL NOT NULL,
+        "rating"            REAL NOT NULL,
+        "deviation"         REAL NOT NULL,
+        "volatility"        REAL NOT NULL,
+        UNIQUE("member", "activity"),
+        FOREIGN KEY ("member")
+            REFERENCES "member" ("member_id")
+            ON DELETE CASCADE,
+        FOREIGN KEY ("activity")
+            REFERENCES "activity" ("activity_id")
+            ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS "skill_rating_activity" (
+        "activity"  INTEGER PRIMARY KEY UNIQUE NOT NULL,
+        FOREIGN KEY ("activity")
+            REFERENCES "activity" ("activity_id")
+            ON DELETE CASCADE
+    );
+    CREATE INDEX skill_rating_member_index
+        ON skill_rating (member, family, period);
+    CREATE INDEX skill_rating_activity_index ON skill_rating (activity);
+    INSERT INTO "main"."version"("version") VALUES (19);
+    COMMIT;
+"#,
+    r#"
+    BEGIN TRANSACTION;
+    ALTER TABLE "sync" ADD COLUMN "links_checked" TEXT;
+    ALTER TABLE "member_link"
+        ADD COLUMN "has_profile" INTEGER NOT NULL DEFAULT 0;
+    INSERT INTO "main"."version"("version") VALUES (20);
+    COMMIT;
+"#,
+];
+
+static_assertions::const_assert!(
+    STORE_DB_MIGRATIONS.len() as i32
+        == DB_SCHEMA_VERSION - MIN_MIGRATABLE_DB_SCHEMA_VERSION
+);
+
+//activity_queue synced values
+const QUEUE_PENDING: i32 = 0;
+const QUEUE_FAILED: i32 = 2;
+
+//default number of times retrieving an activity can fail before it is moved
+//to the failed list, and no longer retried
+pub const DEFAULT_MAX_ACTIVITY_ATTEMPTS: u32 = 5;
 const NO_TEAMS_INDEX: i32 = 253;
 
+//number of example rows returned for each integrity issue
+const INTEGRITY_EXAMPLE_COUNT: usize = 5;
+
+//maximum number of matching players listed when a player name is ambiguous
+const MAX_PLAYER_CANDIDATES: usize = 10;
+
+//days after which unsynced activity queue entries are considered stuck
+const STUCK_QUEUE_DAYS: i64 = 14;
+
+//DestinyActivityDefinition manifest hashes for gambit private matches, which
+//are returned with the private match history, and are ignored
+const GAMBIT_PRIVATE_MATCH_HASHES: [u32; 2] = [2526740498, 248695599];
+
+//default number of members which are synced at the same time
+pub const DEFAULT_MAX_CONCURRENT_SYNCS: u32 = 4;
+
+//how long to wait for another connection to finish writing to the store
+const STORE_BUSY_TIMEOUT_SECONDS: u64 = 60;
+
+//hours after which a member's linked memberships are retrieved again when
+//syncing. They are also retrieved when the member is added to sync, or a new
+//character or platform is found
+const LINK_REFRESH_HOURS: i64 = 24;
+
+//queues activities pending their details which synced players played in, so
+//their details are retrieved on the next sync
+const QUEUE_PENDING_ACTIVITIES_SQL: &str = r#"
+    INSERT OR IGNORE INTO activity_queue ("activity_id", "character")
+    SELECT
+        s.activity, s.character
+    FROM
+        character_activity_stats as s
+    INNER JOIN
+        activity as a ON s.activity = a.activity_id
+    INNER JOIN
+        character as c ON s.character = c.character_id
+    WHERE
+        a.detail_pending = 1 AND
+        c.member IN (
+            SELECT linked FROM linked_member
+            WHERE member IN (SELECT member FROM sync)
+        )
+"#;
+
 pub struct ActivityStoreInterface {
     db: SqliteConnection,
     path: String,
     api_interface: ApiInterface,
     fix_corrupt_data: bool,
+    max_activity_attempts: u32,
+    max_concurrent_syncs: u32,
+
+    //ttl for combat ratings prefetched for synced activities. None if
+    //ratings are not prefetched
+    combat_rating_prefetch: Option<chrono::Duration>,
+
+    //whether to display sync progress. Disabled when syncing concurrently
+    show_progress: bool,
 }
 
 impl ActivityStoreInterface {
@@ -98,6 +371,33 @@ impl ActivityStoreInterface {
         self.path.clone()
     }
 
+    /// Sets the number of times retrieving an activity can fail before it is
+    /// moved to the failed list, and no longer retried when syncing.
+    pub fn set_max_activity_attempts(&mut self, attempts: u32) {
+        self.max_activity_attempts = attempts;
+    }
+
+    /// Sets the maximum number of members which are synced at the same time
+    /// when syncing multiple members.
+    pub fn set_max_concurrent_syncs(&mut self, count: u32) {
+        self.max_concurrent_syncs = count.max(1);
+    }
+
+    /// Sets the budget which limits the rate of requests made to the Bungie
+    /// API when syncing. The budget is shared by all members being synced.
+    pub fn set_request_budget(&mut self, budget: RequestBudget) {
+        self.api_interface.set_request_budget(budget);
+    }
+
+    /// Sets whether combat ratings are prefetched for the players in synced
+    /// activities, and how long prefetched ratings are current for.
+    pub fn set_combat_rating_prefetch(
+        &mut self,
+        ttl: Option<chrono::Duration>,
+    ) {
+        self.combat_rating_prefetch = ttl;
+    }
+
     pub async fn init_with_path(
         store_dir: &Path,
         key: Option<String>,
@@ -119,19 +419,10 @@ impl ActivityStoreInterface {
             fix_corrupt_data
         );
 
-        let read_only = false;
-        let connection_string: &str = &path;
-
-        //TODO: Is this still the correct / best journal mode for us?
-        let mut db = SqliteConnectOptions::from_str(connection_string)?
-            .journal_mode(SqliteJournalMode::Wal)
-            .create_if_missing(true)
-            .read_only(read_only)
-            .connect()
-            .await?;
+        let mut db = connect(&path).await?;
 
         //is this an existing db, or a completely new one / first time?
-        let should_update_schema = match sqlx::query(
+        let version = match sqlx::query(
             r#"
             SELECT max(version) as max_version FROM version
         "#,
@@ -139,14 +430,19 @@ impl ActivityStoreInterface {
         .fetch_one(&mut db)
         .await
         {
-            Ok(e) => {
-                let version: i32 = e.try_get("max_version").unwrap_or(-1);
-                version != DB_SCHEMA_VERSION
-            }
-            Err(_e) => true,
+            Ok(e) => e.try_get("max_version").unwrap_or(-1),
+            Err(_e) => -1,
         };
 
-        if should_update_schema {
+        if (MIN_MIGRATABLE_DB_SCHEMA_VERSION..DB_SCHEMA_VERSION)
+            .contains(&version)
+        {
+            tell::update!("Updating data store.");
+            let skip = (version - MIN_MIGRATABLE_DB_SCHEMA_VERSION) as usize;
+            for migration in STORE_DB_MIGRATIONS.iter().skip(skip) {
+                sqlx::query(migration).execute(&mut db).await?;
+            }
+        } else if version != DB_SCHEMA_VERSION {
             tell::update!("Data store needs to be updated.");
             sqlx::query(STORE_DB_SCHEMA).execute(&mut db).await?;
         }
@@ -161,11 +457,32 @@ impl ActivityStoreInterface {
             path: path.to_string(),
             api_interface,
             fix_corrupt_data,
+            max_activity_attempts: DEFAULT_MAX_ACTIVITY_ATTEMPTS,
+            max_concurrent_syncs: DEFAULT_MAX_CONCURRENT_SYNCS,
+            combat_rating_prefetch: None,
+            show_progress: true,
+        })
+    }
+
+    //opens another connection to the store, which shares the api interface
+    //(and its request budget), so members can be synced concurrently
+    async fn open_sync_worker(&self) -> Result<ActivityStoreInterface, Error> {
+        let db = connect(&self.path).await?;
+
+        Ok(ActivityStoreInterface {
+            db,
+            path: self.path.clone(),
+            api_interface: self.api_interface.clone(),
+            fix_corrupt_data: self.fix_corrupt_data,
+            max_activity_attempts: self.max_activity_attempts,
+            max_concurrent_syncs: 1,
+            combat_rating_prefetch: self.combat_rating_prefetch,
+            show_progress: false,
         })
     }
 
     //todo: this should take a PlayerName
-    async fn retrieve_member_by_name(
+    pub async fn retrieve_member_by_name(
         &mut self,
         player_name: &PlayerName,
     ) -> Result<Option<Member>, Error> {
@@ -173,7 +490,10 @@ impl ActivityStoreInterface {
 
         let row_option = sqlx::query(
             r#"
-            SELECT "member_id", "platform_id", "display_name", "bungie_display_name", "bungie_display_name_code" from "member" where bungie_display_name= ? and bungie_display_name_code = ?
+            SELECT "member_id", "platform_id", "display_name", "bungie_display_name", "bungie_display_name_code" from "member"
+            LEFT JOIN member_link on member_link.member = member.member_id
+            where bungie_display_name= ? COLLATE NOCASE and bungie_display_name_code = ?
+            ORDER BY member_link.identity = member.member_id DESC
         "#,
         )
         .bind(player_name.bungie_display_name.as_ref().unwrap())
@@ -182,6 +502,46 @@ impl ActivityStoreInterface {
         .await?;
 
         //TODO: may want to use this in other areas
+        let row = match row_option {
+            Some(e) => e,
+            None => {
+                return self.retrieve_member_by_previous_name(player_name).await
+            }
+        };
+
+        let member = self.parse_member_row(&row)?;
+
+        Ok(Some(member))
+    }
+
+    //finds the member who most recently used the name, if they have
+    //since changed it
+    async fn retrieve_member_by_previous_name(
+        &mut self,
+        player_name: &PlayerName,
+    ) -> Result<Option<Member>, Error> {
+        let row_option = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name",
+                member.bungie_display_name, member.bungie_display_name_code
+            FROM
+                member_name
+            INNER JOIN
+                member ON member_name.member = member.member_id
+            WHERE
+                member_name.bungie_display_name = ? COLLATE NOCASE AND
+                member_name.bungie_display_name_code = ?
+            ORDER BY
+                member_name.last_seen DESC
+            LIMIT 1
+        "#,
+        )
+        .bind(player_name.bungie_display_name.as_ref().unwrap())
+        .bind(player_name.bungie_display_name_code.as_ref().unwrap())
+        .fetch_optional(&mut self.db)
+        .await?;
+
         let row = match row_option {
             Some(e) => e,
             None => return Ok(None),
@@ -189,9 +549,61 @@ impl ActivityStoreInterface {
 
         let member = self.parse_member_row(&row)?;
 
+        info!(
+            "{} found in name history. Current name : {}",
+            player_name.get_bungie_name(),
+            member.name.get_bungie_name()
+        );
+
         Ok(Some(member))
     }
 
+    /// Returns each Bungie name the member (and their linked memberships)
+    /// has been seen with in synced activities, oldest first.
+    pub async fn retrieve_name_history(
+        &mut self,
+        member: &Member,
+    ) -> Result<Vec<NameHistoryEntry>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                bungie_display_name, bungie_display_name_code,
+                MIN(first_seen) AS first_seen, MAX(last_seen) AS last_seen
+            FROM
+                member_name
+            WHERE
+                member IN (SELECT linked FROM linked_member WHERE member = ?)
+            GROUP BY
+                bungie_display_name, bungie_display_name_code
+            ORDER BY
+                first_seen
+        "#,
+        )
+        .bind(member.id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let parse_time = |e: String| -> Result<DateTime<Utc>, Error> {
+            Ok(DateTime::parse_from_rfc3339(&e)?.with_timezone(&Utc))
+        };
+
+        let mut out: Vec<NameHistoryEntry> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            out.push(NameHistoryEntry {
+                name: PlayerName {
+                    display_name: None,
+                    bungie_display_name: row.try_get("bungie_display_name")?,
+                    bungie_display_name_code: row
+                        .try_get("bungie_display_name_code")?,
+                },
+                first_seen: parse_time(row.try_get("first_seen")?)?,
+                last_seen: parse_time(row.try_get("last_seen")?)?,
+            });
+        }
+
+        Ok(out)
+    }
+
     fn parse_member_row(
         &mut self,
         member_row: &sqlx::sqlite::SqliteRow,
@@ -242,6 +654,96 @@ impl ActivityStoreInterface {
         Ok(())
     }
 
+    /// Sets how far back the activity history for a member being synced is
+    /// synced. If the limit includes older activities than have already been
+    /// synced, they are synced the next time the member is synced.
+    pub async fn set_history_limit(
+        &mut self,
+        member_id: &i64,
+        limit: &HistoryLimit,
+    ) -> Result<(), Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sync SET sync_since = ?, max_activities = ?
+            WHERE member = ?
+        "#,
+        )
+        .bind(limit.since.map(|e| e.to_rfc3339()))
+        .bind(limit.max_activities)
+        .bind(member_id)
+        .execute(&mut self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::PlayerNotFound {
+                description: format!(
+                    "Member id {} is not being synced.",
+                    member_id
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    //returns the history limit for the member, and the limit the member's
+    //history was last synced with. Members who are not in the sync table
+    //are not limited
+    async fn retrieve_history_limits(
+        &mut self,
+        member_id: &i64,
+    ) -> Result<(HistoryLimit, HistoryLimit), Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                sync_since, max_activities,
+                history_since, history_max_activities
+            FROM sync
+            WHERE member = ?
+        "#,
+        )
+        .bind(member_id)
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        let row = match row {
+            Some(e) => e,
+            None => {
+                return Ok((HistoryLimit::default(), HistoryLimit::default()))
+            }
+        };
+
+        let limit = parse_history_limit(&row, "sync_since", "max_activities")?;
+        let history = parse_history_limit(
+            &row,
+            "history_since",
+            "history_max_activities",
+        )?;
+
+        Ok((limit, history))
+    }
+
+    //records that the member's history has been synced with their current
+    //history limit
+    async fn update_history_limit(
+        &mut self,
+        member_id: &i64,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            UPDATE sync SET
+                history_since = sync_since,
+                history_max_activities = max_activities
+            WHERE member = ?
+        "#,
+        )
+        .bind(member_id)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn find_member(
         &mut self,
         name: &PlayerName,
@@ -268,106 +770,737 @@ impl ActivityStoreInterface {
         Ok(out)
     }
 
-    pub async fn sync_players(
+    /// Finds the member for a player specified on the command line.
+    ///
+    /// Bungie names are looked up in the store, and then via the API.
+    /// Membership ids are looked up in the store, and then via the API.
+    /// Queries match aliases, and then the start of the names of players in
+    /// the store (case insensitive), preferring synced players. Queries never
+    /// call the API.
+    pub async fn resolve_member(
         &mut self,
-        players: &[PlayerName],
-    ) -> Result<(), Error> {
-        for player in players.iter() {
-            match self.sync_player(player).await {
-                Ok(_) => {}
-                Err(e) => {
-                    tell::error!(
-                        "{}",
-                        format_error(
-                            &format!(
-                                "Error Syncing. Aborting syncing player: {}",
-                                player.get_bungie_name()
-                            ),
-                            e
-                        )
-                    );
-                }
-            };
+        selector: &PlayerSelector,
+        store: bool,
+    ) -> Result<Member, Error> {
+        match selector {
+            PlayerSelector::BungieName(e) => self.find_member(e, store).await,
+            PlayerSelector::MembershipId(e) => {
+                self.find_member_by_id(*e, store).await
+            }
+            PlayerSelector::Query(e) => self.find_member_by_query(e).await,
         }
-
-        Ok(())
     }
 
-    //todo: rename sync to sync member
-    pub async fn sync_player(
+    async fn find_member_by_id(
         &mut self,
-        player: &PlayerName,
-    ) -> Result<SyncResult, Error> {
-        let member = self.find_member(player, false).await?;
+        member_id: i64,
+        store: bool,
+    ) -> Result<Member, Error> {
+        let row_option = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                member
+            WHERE
+                member_id = ?
+        "#,
+        )
+        .bind(member_id)
+        .fetch_optional(&mut self.db)
+        .await?;
 
-        let out = self.sync_member(&member).await?;
-        Ok(out)
+        if let Some(row) = row_option {
+            return self.parse_member_row(&row);
+        }
+
+        let memberships = self
+            .api_interface
+            .retrieve_player_info_by_id(&member_id)
+            .await?;
+
+        let member = match memberships
+            .destiny_memberships
+            .iter()
+            .find(|e| e.membership_id == member_id)
+        {
+            Some(e) => e.to_member(),
+            None => {
+                return Err(Error::PlayerNotFound {
+                    description: format!(
+                        "No Destiny membership found with id {}.",
+                        member_id
+                    ),
+                })
+            }
+        };
+
+        if store {
+            self.insert_member(&member).await?;
+        }
+
+        Ok(member)
     }
 
-    //select all members where sync member = memberid
-    pub async fn sync_all(&mut self) -> Result<(), Error> {
-        let members: Vec<Member> = self.get_sync_members().await?;
+    async fn find_member_by_query(
+        &mut self,
+        query: &str,
+    ) -> Result<Member, Error> {
+        if let Some(e) = self.retrieve_member_by_alias(query).await? {
+            return Ok(e);
+        }
 
-        for member in members.iter() {
-            match self.sync_member(member).await {
-                Ok(_) => {}
-                Err(e) => tell::error!(
-                    "{}",
-                    format_error(
-                        &format!(
-                            "Error Syncing. Aborting syncing player: {}",
-                            member.name.get_bungie_name()
-                        ),
-                        e
-                    )
+        //synced players first, so they are not made ambiguous by the
+        //opponents they have played against
+        let mut candidates = self.find_members_by_prefix(query, true).await?;
+        if candidates.is_empty() {
+            candidates = self.find_members_by_prefix(query, false).await?;
+        }
+
+        //an exact match on the name (without the code) wins
+        let exact: Vec<&Member> = candidates
+            .iter()
+            .filter(|e| match e.name.bungie_display_name.as_deref() {
+                Some(e) => e.eq_ignore_ascii_case(query),
+                None => false,
+            })
+            .collect();
+
+        if exact.len() == 1 {
+            return Ok(exact[0].clone());
+        }
+
+        match candidates.len() {
+            0 => Err(Error::PlayerNotFound {
+                description: format!(
+                    "No players in the data store match {}. Specify the full Bungie name (NAME#CODE) to find players who have not been synced.",
+                    query
                 ),
+            }),
+            1 => Ok(candidates.remove(0)),
+            _ => {
+                let mut names: Vec<String> = candidates
+                    .iter()
+                    .take(MAX_PLAYER_CANDIDATES)
+                    .map(|e| e.name.get_bungie_name())
+                    .collect();
+
+                if candidates.len() > MAX_PLAYER_CANDIDATES {
+                    names.push("...".to_string());
+                }
+
+                Err(Error::AmbiguousPlayerName {
+                    description: format!(
+                        "{} matches : {}. Specify more of the name, or the full Bungie name (NAME#CODE).",
+                        query,
+                        names.join(", ")
+                    ),
+                })
             }
         }
-
-        Ok(())
     }
 
-    pub async fn get_sync_members(&mut self) -> Result<Vec<Member>, Error> {
+    //finds members whose bungie name starts with prefix (case insensitive).
+    //Only includes the primary membership for linked memberships
+    async fn find_members_by_prefix(
+        &mut self,
+        prefix: &str,
+        synced_only: bool,
+    ) -> Result<Vec<Member>, Error> {
         let rows = sqlx::query(
             r#"
             SELECT
-                "member_id", "platform_id", "display_name", 
+                "member_id", "platform_id", "display_name",
                 "bungie_display_name", "bungie_display_name_code"
             FROM
-                "member"
-            INNER JOIN
-                sync on member.member_id = sync.member
+                member
+            WHERE
+                bungie_display_name IS NOT NULL AND
+                bungie_display_name_code IS NOT NULL AND
+                (bungie_display_name || '#' || bungie_display_name_code)
+                    LIKE ? ESCAPE '\' AND
+                member_id NOT IN
+                    (SELECT member FROM member_link WHERE identity != member) AND
+                (NOT ? OR member_id IN (
+                    SELECT linked FROM linked_member
+                    WHERE member IN (SELECT member FROM sync)
+                ))
+            ORDER BY
+                bungie_display_name COLLATE NOCASE, bungie_display_name_code
+            LIMIT ?
         "#,
         )
+        .bind(like_prefix_pattern(prefix))
+        .bind(synced_only)
+        .bind((MAX_PLAYER_CANDIDATES + 1) as u32)
         .fetch_all(&mut self.db)
         .await?;
 
-        let mut out: Vec<Member> = Vec::new();
+        let mut out: Vec<Member> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            out.push(self.parse_member_row(row)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Searches the store for players whose Bungie name starts with prefix
+    /// (case insensitive), returning up to limit players. Players with synced
+    /// data are listed first.
+    pub async fn search_stored_members(
+        &mut self,
+        prefix: &str,
+        limit: u32,
+    ) -> Result<Vec<StoredMember>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code",
+                member_id IN (
+                    SELECT linked FROM linked_member
+                    WHERE member IN (SELECT member FROM sync)
+                ) AS synced,
+                (
+                    SELECT MAX(activity.period)
+                    FROM activity
+                    INNER JOIN character_activity_stats ON
+                        character_activity_stats.activity = activity.activity_id
+                    INNER JOIN character ON
+                        character_activity_stats.character = character.character_id
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                ) AS last_activity
+            FROM
+                member
+            WHERE
+                bungie_display_name IS NOT NULL AND
+                bungie_display_name_code IS NOT NULL AND
+                (bungie_display_name || '#' || bungie_display_name_code)
+                    LIKE ? ESCAPE '\' AND
+                member_id NOT IN
+                    (SELECT member FROM member_link WHERE identity != member)
+            ORDER BY
+                synced DESC,
+                bungie_display_name COLLATE NOCASE, bungie_display_name_code
+            LIMIT ?
+        "#,
+        )
+        .bind(like_prefix_pattern(prefix))
+        .bind(limit)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<StoredMember> = Vec::with_capacity(rows.len());
         for row in rows.iter() {
             let member = self.parse_member_row(row)?;
-            out.push(member);
+            let last_activity: Option<String> = row.try_get("last_activity")?;
+
+            out.push(StoredMember {
+                member,
+                synced: row.try_get("synced")?,
+                last_activity: last_activity.and_then(|e| {
+                    DateTime::parse_from_rfc3339(&e)
+                        .ok()
+                        .map(|e| e.with_timezone(&Utc))
+                }),
+            });
         }
 
         Ok(out)
     }
 
-    pub async fn remove_player_from_sync(
+    async fn retrieve_member_by_alias(
         &mut self,
-        player: &PlayerName,
-    ) -> Result<(), Error> {
+        alias: &str,
+    ) -> Result<Option<Member>, Error> {
         let row_option = sqlx::query(
             r#"
-            SELECT "member_id" from "member" 
-            where bungie_display_name = ? and bungie_display_name_code = ?
+            SELECT
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                member_alias
+            INNER JOIN
+                member ON member_alias.member = member.member_id
+            WHERE
+                alias = ?
         "#,
         )
-        .bind(player.bungie_display_name.as_ref().unwrap())
-        .bind(player.bungie_display_name_code.as_ref().unwrap())
+        .bind(alias)
         .fetch_optional(&mut self.db)
         .await?;
 
-        let id: i64 = match row_option {
-            Some(e) => {
+        match row_option {
+            Some(e) => Ok(Some(self.parse_member_row(&e)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets a local alias which can be used in place of the member's Bungie
+    /// name. Replaces any existing alias with the same name.
+    pub async fn set_alias(
+        &mut self,
+        alias: &str,
+        member: &Member,
+    ) -> Result<(), Error> {
+        let alias = alias.trim();
+
+        //aliases cant look like bungie names or membership ids, since those
+        //are resolved before aliases
+        if alias.is_empty()
+            || alias.contains('#')
+            || alias.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(Error::InvalidArgument {
+                description: format!(
+                    "Invalid alias : {}. Aliases cannot be empty, contain #, or be a number.",
+                    alias
+                ),
+            });
+        }
+
+        self.insert_member(member).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "member_alias" ("alias", "member") VALUES (?, ?)
+            ON CONFLICT(alias) DO UPDATE
+            SET alias = excluded.alias, member = excluded.member
+        "#,
+        )
+        .bind(alias)
+        .bind(member.id)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a local alias. Returns false if the alias did not exist.
+    pub async fn remove_alias(&mut self, alias: &str) -> Result<bool, Error> {
+        let count = sqlx::query(
+            r#"
+            DELETE FROM "member_alias" WHERE alias = ?
+        "#,
+        )
+        .bind(alias.trim())
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        Ok(count > 0)
+    }
+
+    /// Returns all local aliases, and the members they refer to.
+    pub async fn retrieve_aliases(
+        &mut self,
+    ) -> Result<Vec<MemberAlias>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                alias, "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                member_alias
+            INNER JOIN
+                member ON member_alias.member = member.member_id
+            ORDER BY
+                alias
+        "#,
+        )
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<MemberAlias> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            out.push(MemberAlias {
+                alias: row.try_get("alias")?,
+                member: self.parse_member_row(row)?,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Syncs the specified players concurrently. Players who cannot be found
+    /// are logged and skipped.
+    pub async fn sync_players(
+        &mut self,
+        players: &[PlayerName],
+    ) -> Result<Vec<MemberSyncResult>, Error> {
+        let mut members: Vec<Member> = Vec::with_capacity(players.len());
+        for player in players.iter() {
+            match self.find_member(player, false).await {
+                Ok(e) => members.push(e),
+                Err(e) => {
+                    metrics::record_error(&e);
+                    tell::error!(
+                        "{}",
+                        format_error(
+                            &format!(
+                                "Error Syncing. Aborting syncing player: {}",
+                                player.get_bungie_name()
+                            ),
+                            e
+                        )
+                    );
+                }
+            };
+        }
+
+        Ok(self.sync_members(&members).await)
+    }
+
+    //todo: rename sync to sync member
+    pub async fn sync_player(
+        &mut self,
+        player: &PlayerName,
+    ) -> Result<SyncResult, Error> {
+        let member = self.find_member(player, false).await?;
+
+        let out = self.sync_member(&member).await?;
+        Ok(out)
+    }
+
+    /// Syncs all of the members flagged to be synced concurrently.
+    pub async fn sync_all(&mut self) -> Result<Vec<MemberSyncResult>, Error> {
+        let members: Vec<Member> = self.get_sync_members().await?;
+
+        Ok(self.sync_members(&members).await)
+    }
+
+    /// Syncs the members, syncing up to the max concurrent syncs members at
+    /// the same time. All members share the api request budget.
+    ///
+    /// The activity history for all of the members is synced first, so their
+    /// activities can be queried while the activity details are synced. Skill
+    /// ratings are updated once all of the members have been synced.
+    ///
+    /// Errors syncing a member are logged and returned in the member's
+    /// result, and do not stop the other members from being synced. Results
+    /// are returned in the same order as the members.
+    pub async fn sync_members(
+        &mut self,
+        members: &[Member],
+    ) -> Vec<MemberSyncResult> {
+        let worker_count =
+            (self.max_concurrent_syncs as usize).min(members.len());
+
+        //each worker has its own connection to the store
+        let mut workers: Vec<ActivityStoreInterface> = Vec::new();
+        if worker_count > 1 {
+            for _ in 0..worker_count {
+                match self.open_sync_worker().await {
+                    Ok(e) => workers.push(e),
+                    Err(e) => {
+                        tell::error!(
+                            "{}",
+                            format_error(
+                                "Could not open store connection for concurrent sync.",
+                                e
+                            )
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        if workers.len() < 2 {
+            workers.clear();
+        } else {
+            tell::progress!(
+                "Syncing {} players, {} at a time.",
+                members.len(),
+                workers.len()
+            );
+        }
+
+        let history = self
+            .run_sync_phase(&mut workers, members, SyncPhase::History)
+            .await;
+
+        //members whose history couldnt be synced arent synced any further
+        let synced: Vec<Member> = members
+            .iter()
+            .zip(history.iter())
+            .filter(|(_, e)| e.0.is_ok())
+            .map(|(m, _)| m.clone())
+            .collect();
+
+        let mut details = self
+            .run_sync_phase(&mut workers, &synced, SyncPhase::Details)
+            .await
+            .into_iter();
+
+        if !synced.is_empty() {
+            self.update_skill_ratings_after_sync().await;
+        }
+
+        members
+            .iter()
+            .zip(history)
+            .map(|(member, (result, duration))| {
+                let (result, duration) = match result {
+                    Ok(_) => {
+                        let (r, d) = details.next().unwrap();
+                        (r, duration + d)
+                    }
+                    Err(e) => (Err(e), duration),
+                };
+
+                MemberSyncResult {
+                    member: member.clone(),
+                    result,
+                    duration,
+                }
+            })
+            .collect()
+    }
+
+    //updates skill ratings with newly synced activities. The activities have
+    //been synced, so errors dont fail the sync
+    async fn update_skill_ratings_after_sync(&mut self) {
+        if let Err(e) = self.update_skill_ratings().await {
+            metrics::record_error(&e);
+            tell::error!(
+                "{}",
+                format_error("Could not update skill ratings.", e)
+            );
+        }
+    }
+
+    //runs the sync phase for the members, with the workers taking the next
+    //member until they have all been synced. If there are no workers, the
+    //members are synced one at a time. Results are returned in the same
+    //order as the members
+    async fn run_sync_phase(
+        &mut self,
+        workers: &mut [ActivityStoreInterface],
+        members: &[Member],
+        phase: SyncPhase,
+    ) -> Vec<(Result<SyncResult, Error>, std::time::Duration)> {
+        if workers.is_empty() {
+            let mut out = Vec::with_capacity(members.len());
+            for member in members.iter() {
+                out.push(self.sync_member_phase(member, phase).await);
+            }
+            return out;
+        }
+
+        //workers take the next member from the queue until it is empty
+        let queue = std::sync::Mutex::new(members.iter().enumerate());
+        let f = workers.iter_mut().map(|worker| {
+            let queue = &queue;
+            async move {
+                let mut out = Vec::new();
+                loop {
+                    let next = queue.lock().unwrap().next();
+                    let (index, member) = match next {
+                        Some(e) => e,
+                        None => break,
+                    };
+
+                    out.push((
+                        index,
+                        worker.sync_member_phase(member, phase).await,
+                    ));
+                }
+                out
+            }
+        });
+
+        let mut results: Vec<(usize, _)> = futures::future::join_all(f)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        results.sort_by_key(|e| e.0);
+        results.into_iter().map(|e| e.1).collect()
+    }
+
+    //syncs the phase for the member, logging any errors, and recording how
+    //long it took
+    async fn sync_member_phase(
+        &mut self,
+        member: &Member,
+        phase: SyncPhase,
+    ) -> (Result<SyncResult, Error>, std::time::Duration) {
+        let start = std::time::Instant::now();
+        let result = match phase {
+            SyncPhase::History => {
+                self.sync_member_history(member).await.map(|e| SyncResult {
+                    total_available: e,
+                    total_synced: 0,
+                })
+            }
+            SyncPhase::Details => self.sync_member_details(member).await,
+        };
+
+        if let Err(e) = &result {
+            metrics::record_error(e);
+            tell::error!(
+                "Error Syncing. Aborting syncing player: {} : {}",
+                member.name.get_bungie_name(),
+                e
+            );
+        }
+
+        (result, start.elapsed())
+    }
+
+    pub async fn get_sync_members(&mut self) -> Result<Vec<Member>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name", 
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                "member"
+            INNER JOIN
+                sync on member.member_id = sync.member
+        "#,
+        )
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<Member> = Vec::new();
+        for row in rows.iter() {
+            let member = self.parse_member_row(row)?;
+            out.push(member);
+        }
+
+        Ok(out)
+    }
+
+    /// Returns the sync status for each member flagged to be synced,
+    /// including counts of stored, queued and failed activities.
+    pub async fn retrieve_sync_status(
+        &mut self,
+    ) -> Result<Vec<SyncStatus>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code",
+                sync.last_sync, sync.sync_since, sync.max_activities,
+                (
+                    SELECT COUNT(*) FROM character
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                ) AS characters,
+                (
+                    SELECT COUNT(DISTINCT character_activity_stats.activity)
+                    FROM character_activity_stats
+                    INNER JOIN character ON
+                        character_activity_stats.character = character.character_id
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                ) AS activities,
+                (
+                    SELECT MAX(activity.period)
+                    FROM activity
+                    INNER JOIN character_activity_stats ON
+                        character_activity_stats.activity = activity.activity_id
+                    INNER JOIN character ON
+                        character_activity_stats.character = character.character_id
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                ) AS last_activity,
+                (
+                    SELECT COUNT(*) FROM activity_queue
+                    INNER JOIN character ON
+                        activity_queue.character = character.character_id
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                        AND activity_queue.synced = ?
+                ) AS queued,
+                (
+                    SELECT COUNT(*) FROM activity_queue
+                    INNER JOIN character ON
+                        activity_queue.character = character.character_id
+                    WHERE character.member IN (
+                        SELECT linked FROM linked_member
+                        WHERE linked_member.member = member.member_id
+                    )
+                        AND activity_queue.synced = ?
+                ) AS failed
+            FROM
+                member
+            INNER JOIN
+                sync ON member.member_id = sync.member
+            ORDER BY
+                bungie_display_name COLLATE NOCASE, bungie_display_name_code
+        "#,
+        )
+        .bind(QUEUE_PENDING)
+        .bind(QUEUE_FAILED)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let parse_time = |e: Option<String>| {
+            e.and_then(|e| {
+                DateTime::parse_from_rfc3339(&e)
+                    .ok()
+                    .map(|e| e.with_timezone(&Utc))
+            })
+        };
+
+        let mut out: Vec<SyncStatus> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let member = self.parse_member_row(row)?;
+            let last_sync: Option<String> = row.try_get("last_sync")?;
+            let last_activity: Option<String> = row.try_get("last_activity")?;
+
+            out.push(SyncStatus {
+                member,
+                history_limit: parse_history_limit(
+                    row,
+                    "sync_since",
+                    "max_activities",
+                )?,
+                characters: row.try_get("characters")?,
+                activities: row.try_get("activities")?,
+                last_activity: parse_time(last_activity),
+                last_sync: parse_time(last_sync),
+                queued: row.try_get("queued")?,
+                failed: row.try_get("failed")?,
+            });
+        }
+
+        Ok(out)
+    }
+
+    pub async fn remove_player_from_sync(
+        &mut self,
+        player: &PlayerName,
+    ) -> Result<(), Error> {
+        let row_option = sqlx::query(
+            r#"
+            SELECT "member_id" from "member" 
+            where bungie_display_name = ? and bungie_display_name_code = ?
+        "#,
+        )
+        .bind(player.bungie_display_name.as_ref().unwrap())
+        .bind(player.bungie_display_name_code.as_ref().unwrap())
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        let id: i64 = match row_option {
+            Some(e) => {
                 let id: i64 = e.try_get("member_id")?;
                 id
             }
@@ -409,15 +1542,25 @@ impl ActivityStoreInterface {
             }
         };
 
+        //retrieve linked memberships on the next sync
+        match self.clear_links_checked(&member.id).await {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+
         self.commit_transaction().await?;
 
         Ok(())
     }
 
+    /// Adds the player to sync, returning their member
     pub async fn add_player_to_sync(
         &mut self,
         player: &PlayerName,
-    ) -> Result<(), Error> {
+    ) -> Result<Member, Error> {
         let member = match self.find_member(player, false).await {
             Ok(e) => e,
             Err(e) => {
@@ -427,249 +1570,2999 @@ impl ActivityStoreInterface {
 
         self.add_member_to_sync(&member).await?;
 
-        Ok(())
+        Ok(member)
     }
 
-    /// TODO currently no way to sync old / delete characters. would be easy to
-    /// add by just moving the character sync into its own api sync_character(id, class_type)
-    /// but not going to worry about it unless someone requests it
-    /// retrieves and stores activity details for ids in activity queue
-    pub async fn sync_member(
+    /// Subscribes to a Destiny 2 group / clan, so its roster is reconciled
+    /// with the players being synced. If remove_departed is true, players who
+    /// leave the group are no longer synced.
+    pub async fn subscribe_group(
         &mut self,
-        member: &Member,
-    ) -> Result<SyncResult, Error> {
-        //Note, we need this call in case the user deletes and creates a new character
-        //https://www.bungie.net/Platform/Destiny2/1/Profile/4611686018429783292/?components=100,200
-        let player_info = self
-            .api_interface
-            .get_player_info(&member.id, &member.platform)
-            .await?;
+        group_id: u32,
+        remove_departed: bool,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO "group_subscription" ("group_id", "remove_departed")
+            VALUES (?, ?)
+            ON CONFLICT(group_id) DO UPDATE
+            SET remove_departed = excluded.remove_departed
+        "#,
+        )
+        .bind(group_id)
+        .bind(remove_departed)
+        .execute(&mut self.db)
+        .await?;
 
-        //TODO: Check is core issue has been fixed
-        //Note, if this profile call fails, then we dont sync user. However, we could
-        //capture and then just sync the characters we already have.
-        //This issue is causing the sync to fail for some users:
-        //https://twitter.com/BungieHelp/status/1595515590160306176?s=20&t=QR7Rs7weMwzTfO2YtTArSg
-        //https://github.com/Bungie-net/api/issues/1738
+        Ok(())
+    }
 
-        let characters = player_info.characters;
+    /// Removes the subscription to the group. Players from the group are
+    /// still synced. Returns false if there was no subscription.
+    pub async fn unsubscribe_group(
+        &mut self,
+        group_id: u32,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM "group_subscription" WHERE group_id = ?
+        "#,
+        )
+        .bind(group_id)
+        .execute(&mut self.db)
+        .await?;
 
-        self.insert_member(&player_info.user_info.to_member())
-            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        let mut total_synced = 0;
-        let mut total_in_queue = 0;
+    pub async fn retrieve_group_subscriptions(
+        &mut self,
+    ) -> Result<Vec<GroupSubscription>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                group_id, remove_departed, last_checked,
+                (
+                    SELECT COUNT(*) FROM group_member
+                    WHERE group_member.group_id = group_subscription.group_id
+                ) AS members
+            FROM
+                group_subscription
+            ORDER BY
+                group_id
+        "#,
+        )
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<GroupSubscription> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let last_checked: Option<String> = row.try_get("last_checked")?;
+
+            out.push(GroupSubscription {
+                group_id: row.try_get("group_id")?,
+                remove_departed: row.try_get("remove_departed")?,
+                last_checked: last_checked.and_then(|e| {
+                    DateTime::parse_from_rfc3339(&e)
+                        .ok()
+                        .map(|e| e.with_timezone(&Utc))
+                }),
+                members: row.try_get("members")?,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Retrieves the current roster for a subscribed group, and reconciles it
+    /// with the roster from the last check. Players who joined the group are
+    /// added to sync, and if the subscription removes departed players,
+    /// players who left are removed from sync (unless they are in another
+    /// subscribed group).
+    pub async fn reconcile_group(
+        &mut self,
+        group_id: u32,
+    ) -> Result<RosterChanges, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT remove_departed FROM group_subscription WHERE group_id = ?
+        "#,
+        )
+        .bind(group_id)
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        let remove_departed: bool = match row {
+            Some(e) => e.try_get("remove_departed")?,
+            None => {
+                return Err(Error::InvalidArgument {
+                    description: format!(
+                        "Not subscribed to group : {}",
+                        group_id
+                    ),
+                });
+            }
+        };
+
+        //players without a valid bungie name cant be synced, so are ignored
+        let roster: Vec<Member> = self
+            .api_interface
+            .retrieve_group_members(group_id)
+            .await?
+            .into_iter()
+            .filter(|e| e.name.is_valid_bungie_name())
+            .collect();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                group_member
+            INNER JOIN
+                member ON group_member.member = member.member_id
+            WHERE
+                group_member.group_id = ?
+        "#,
+        )
+        .bind(group_id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut previous: Vec<Member> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            previous.push(self.parse_member_row(row)?);
+        }
+
+        let mut changes = RosterChanges::default();
+
+        for member in roster.iter() {
+            if previous.iter().any(|e| e.id == member.id) {
+                continue;
+            }
+
+            //dont reset the last sync time for players already being synced
+            if !self.is_member_synced(member).await? {
+                self.add_member_to_sync(member).await?;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO "group_member" ("group_id", "member")
+                VALUES (?, ?)
+            "#,
+            )
+            .bind(group_id)
+            .bind(member.id)
+            .execute(&mut self.db)
+            .await?;
+
+            changes.added.push(member.clone());
+        }
+
+        for member in previous.into_iter() {
+            if roster.iter().any(|e| e.id == member.id) {
+                continue;
+            }
+
+            sqlx::query(
+                r#"
+                DELETE FROM "group_member" WHERE group_id = ? AND member = ?
+            "#,
+            )
+            .bind(group_id)
+            .bind(member.id)
+            .execute(&mut self.db)
+            .await?;
+
+            if remove_departed && !self.in_subscribed_group(&member).await? {
+                self.remove_player_from_sync(&member.name).await?;
+                changes.removed.push(member);
+            } else {
+                changes.departed.push(member);
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE "group_subscription" SET last_checked = ? WHERE group_id = ?
+        "#,
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(group_id)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(changes)
+    }
+
+    async fn is_member_synced(
+        &mut self,
+        member: &Member,
+    ) -> Result<bool, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS count FROM sync WHERE member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_one(&mut self.db)
+        .await?;
+
+        let count: i64 = row.try_get("count")?;
+        Ok(count > 0)
+    }
+
+    async fn in_subscribed_group(
+        &mut self,
+        member: &Member,
+    ) -> Result<bool, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS count FROM group_member WHERE member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_one(&mut self.db)
+        .await?;
+
+        let count: i64 = row.try_get("count")?;
+        Ok(count > 0)
+    }
+
+    /// TODO currently no way to sync old / delete characters. would be easy to
+    /// add by just moving the character sync into its own api sync_character(id, class_type)
+    /// but not going to worry about it unless someone requests it
+    /// retrieves and stores activity details for ids in activity queue
+    ///
+    /// Syncs the member's activity history, and then the details for its
+    /// activities (see sync_member_history and sync_member_details), and
+    /// updates skill ratings with the synced activities.
+    ///
+    /// Characters from all of the Destiny memberships linked to the member
+    /// (other platforms and cross save) are synced.
+    pub async fn sync_member(
+        &mut self,
+        member: &Member,
+    ) -> Result<SyncResult, Error> {
+        self.sync_member_history(member).await?;
+        let result = self.sync_member_details(member).await?;
+
+        self.update_skill_ratings_after_sync().await;
+
+        Ok(result)
+    }
+
+    /// Syncs the member's activity history, returning the number of new
+    /// activities found.
+    ///
+    /// New activities are stored with the data from the history (period,
+    /// mode, map, standing and the member's stats), so they can be queried
+    /// right away, and are queued to have their details synced. Until then
+    /// they are flagged as pending.
+    pub async fn sync_member_history(
+        &mut self,
+        member: &Member,
+    ) -> Result<u32, Error> {
+        //if we cant retrieve the linked memberships, we still sync the member
+        let members = match self.retrieve_member_profiles(member).await {
+            Ok(e) => e,
+            Err(e) => {
+                metrics::record_error(&e);
+                tell::error!(
+                    "{}",
+                    format_error(
+                        "Could not retrieve linked memberships. Only syncing the specified membership.",
+                        e
+                    )
+                );
+                vec![member.clone()]
+            }
+        };
+
+        let (limit, history_limit) =
+            self.retrieve_history_limits(&member.id).await?;
+
+        //if the limit has been extended since the history was last synced,
+        //we walk the entire history again to find the older activities
+        let backfill = limit.extends(&history_limit);
 
         tell::update!(
             "CHECKING FOR NEW ACTIVITIES FOR {} (PUBLIC AND PRIVATE)",
             member.name.get_bungie_name()
         );
-        tell::progress!("This may take a few minutes depending on the number of activities.");
+
+        if backfill {
+            tell::update!("Extending synced activity history.");
+        }
+        if self.show_progress {
+            tell::progress!("This may take a few minutes depending on the number of activities.");
+        }
+
+        let mut total_found = 0;
+        let mut complete = true;
+        let mut relink = false;
+
+        for m in members.iter() {
+            if members.len() > 1 && self.show_progress {
+                tell::progress!(
+                    "{}",
+                    format!("[{}]", m.platform).to_uppercase()
+                );
+            }
+
+            let (found, new_profile_data) = match self
+                .sync_member_characters(m, &limit, backfill)
+                .await
+            {
+                Ok(e) => e,
+                //errors syncing the specified membership abort the sync,
+                //but we still sync the others if a linked membership fails
+                Err(e) if m.id != member.id => {
+                    metrics::record_error(&e);
+                    tell::error!(
+                        "{}",
+                        format_error(
+                            &format!(
+                                "Error syncing linked {} membership {}. Skipping.",
+                                m.platform, m.id
+                            ),
+                            e
+                        )
+                    );
+                    complete = false;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            total_found += found;
+            relink = relink || new_profile_data;
+        }
+
+        self.update_sync_entry(&member.id).await?;
+
+        //memberships are linked again on the next sync, so any new ones are
+        //synced
+        if relink {
+            self.clear_links_checked(&member.id).await?;
+        }
+
+        if complete {
+            self.update_history_limit(&member.id).await?;
+        }
+
+        Ok(total_found)
+    }
+
+    /// Syncs the details (all players, weapons and medals) for the member's
+    /// queued activities, newest first. Returns the number of activities
+    /// synced, and the number still in the queue.
+    pub async fn sync_member_details(
+        &mut self,
+        member: &Member,
+    ) -> Result<SyncResult, Error> {
+        let result = self.sync_activities(member).await?;
+
+        metrics::record_member_sync(
+            &member.name.get_bungie_name(),
+            result.total_synced,
+        );
+
+        Ok(SyncResult {
+            total_synced: result.total_synced,
+            total_available: result.total_available - result.total_synced,
+        })
+    }
+
+    //syncs the characters and activity history for a single membership.
+    //Returns the number of new activities found, and whether a new character
+    //or platform was found for a membership which had already been synced
+    async fn sync_member_characters(
+        &mut self,
+        member: &Member,
+        limit: &HistoryLimit,
+        backfill: bool,
+    ) -> Result<(u32, bool), Error> {
+        //Note, we need this call in case the user deletes and creates a new character
+        //https://www.bungie.net/Platform/Destiny2/1/Profile/4611686018429783292/?components=100,200
+        let player_info = self
+            .api_interface
+            .get_player_info(&member.id, &member.platform)
+            .await?;
+
+        //TODO: Check is core issue has been fixed
+        //Note, if this profile call fails, then we dont sync user. However, we could
+        //capture and then just sync the characters we already have.
+        //This issue is causing the sync to fail for some users:
+        //https://twitter.com/BungieHelp/status/1595515590160306176?s=20&t=QR7Rs7weMwzTfO2YtTArSg
+        //https://github.com/Bungie-net/api/issues/1738
+
+        let new_profile_data =
+            self.has_new_profile_data(member, &player_info).await?;
+
+        let characters = player_info.characters;
+
+        self.insert_member(&player_info.user_info.to_member())
+            .await?;
+
+        let mut total_found = 0;
 
         for c in characters.characters {
             let character_id = &c.id;
             self.insert_character(&c.id, &c.class_type, &member).await?;
-            tell::progress!("{}", format!("[{}]", c.class_type).to_uppercase());
+            if self.show_progress {
+                tell::progress!(
+                    "{}",
+                    format!("[{}]", c.class_type).to_uppercase()
+                );
+            }
 
             //these calls could be a little more general purpose by taking api ids and not db ids.
             //however, passing the db ids, lets us optimize a lot of the sql, and avoid
             //some extra calls to the DB
-
-            let a = self.sync_activities(character_id).await?;
-
-            let _b = self
+            let result = self
                 .update_activity_queue(
                     &member.id,
                     character_id,
                     &member.platform,
+                    limit,
+                    backfill,
                 )
                 .await?;
 
-            let c = self.sync_activities(character_id).await?;
+            total_found += result.total_available;
+        }
+
+        Ok((total_found, new_profile_data))
+    }
+
+    //whether the profile has characters, or is applicable to platforms,
+    //which are not stored for the member. Always false the first time the
+    //member is synced
+    async fn has_new_profile_data(
+        &mut self,
+        member: &Member,
+        player_info: &PlayerInfo,
+    ) -> Result<bool, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT character_id FROM "character" WHERE member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        if rows.is_empty() {
+            return Ok(false);
+        }
+
+        let mut character_ids: Vec<i64> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            character_ids.push(row.try_get("character_id")?);
+        }
+
+        if player_info
+            .characters
+            .characters
+            .iter()
+            .any(|e| !character_ids.contains(&e.id))
+        {
+            return Ok(true);
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                member.platform_id
+            FROM
+                linked_member
+            INNER JOIN
+                member ON member.member_id = linked_member.linked
+            WHERE
+                linked_member.member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut platforms: Vec<Platform> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let platform_id: u32 = row.try_get("platform_id")?;
+            platforms.push(Platform::from_id(platform_id));
+        }
+
+        let new_platform =
+            match &player_info.user_info.applicable_membership_types {
+                Some(e) => e.iter().any(|e| !platforms.contains(e)),
+                None => false,
+            };
+
+        Ok(new_platform)
+    }
+
+    /// Returns the memberships linked to the member which have Destiny
+    /// profiles that can be synced.
+    ///
+    /// The stored links are used, unless the member was just added to sync, a
+    /// new character or platform was found on the last sync, or they were last
+    /// retrieved more than LINK_REFRESH_HOURS ago, in which case they are
+    /// retrieved and stored again (see link_member_profiles).
+    pub async fn retrieve_member_profiles(
+        &mut self,
+        member: &Member,
+    ) -> Result<Vec<Member>, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT links_checked FROM sync WHERE member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        let links_checked: Option<String> = match row {
+            Some(e) => e.try_get("links_checked")?,
+            None => None,
+        };
+
+        let is_current = links_checked
+            .and_then(|e| DateTime::parse_from_rfc3339(&e).ok())
+            .map(|e| {
+                Utc::now() - e.with_timezone(&Utc)
+                    < chrono::Duration::hours(LINK_REFRESH_HOURS)
+            })
+            .unwrap_or(false);
+
+        if !is_current {
+            return self.link_member_profiles(member).await;
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                member.member_id, member.platform_id, member.display_name,
+                member.bungie_display_name, member.bungie_display_name_code
+            FROM
+                member_link AS a
+            INNER JOIN
+                member_link AS b ON a.identity = b.identity
+            INNER JOIN
+                member ON member.member_id = b.member
+            WHERE
+                a.member = ? AND b.has_profile = 1
+        "#,
+        )
+        .bind(member.id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut profiles: Vec<Member> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            profiles.push(self.parse_member_row(row)?);
+        }
+
+        if profiles.is_empty() {
+            profiles.push(member.clone());
+        }
+
+        Ok(profiles)
+    }
+
+    async fn clear_links_checked(
+        &mut self,
+        member_id: &i64,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            UPDATE "sync" SET links_checked = NULL WHERE member = ?
+        "#,
+        )
+        .bind(member_id)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves all of the Destiny memberships linked to the member (other
+    /// platforms and cross save), and stores them under a single identity so
+    /// queries for the member return activities from all of them.
+    ///
+    /// Returns the memberships which have Destiny profiles that can be synced.
+    pub async fn link_member_profiles(
+        &mut self,
+        member: &Member,
+    ) -> Result<Vec<Member>, Error> {
+        let linked = self
+            .api_interface
+            .retrieve_linked_profiles(&member.id, &member.platform)
+            .await?;
+
+        let memberships = self
+            .api_interface
+            .retrieve_player_info_by_id(&member.id)
+            .await?;
+
+        //profiles can include memberships which are not returned in the
+        //memberships (and vice versa), so we combine them
+        let mut cards: Vec<UserInfoCard> = memberships.destiny_memberships;
+        for profile in linked.profiles.iter() {
+            let id = profile.membership_id;
+            if !cards.iter().any(|e| e.membership_id == id) {
+                cards.push(profile.to_user_info_card());
+            }
+        }
+
+        let profiles: Vec<Member> = if linked.profiles.is_empty() {
+            vec![member.clone()]
+        } else {
+            linked
+                .profiles
+                .iter()
+                .map(|e| e.to_user_info_card().to_member())
+                .collect()
+        };
+
+        //only link if there are other memberships
+        if cards.len() >= 2 {
+            //the cross save primary membership if there is one. Otherwise, the
+            //oldest membership, so the identity doesnt change between syncs
+            let identity = cards
+                .iter()
+                .find(|e| {
+                    e.cross_save_override != Platform::Unknown
+                        && e.cross_save_override == e.membership_type
+                })
+                .or_else(|| cards.iter().min_by_key(|e| e.membership_id))
+                .map(|e| e.membership_id)
+                .unwrap_or(member.id);
+
+            self.begin_transaction().await?;
+            match self.insert_member_links(&cards, &profiles, identity).await {
+                Ok(e) => e,
+                Err(e) => {
+                    self.rollback_transaction().await?;
+                    return Err(e);
+                }
+            };
+            self.commit_transaction().await?;
+
+            tell::verbose!(
+                "Linked {} memberships for {}",
+                cards.len(),
+                member.name.get_bungie_name()
+            );
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE "sync" SET links_checked = ? WHERE member = ?
+        "#,
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(member.id)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(profiles)
+    }
+
+    async fn insert_member_links(
+        &mut self,
+        cards: &[UserInfoCard],
+        profiles: &[Member],
+        identity: i64,
+    ) -> Result<(), Error> {
+        for card in cards.iter() {
+            self.insert_member(&card.to_member()).await?;
+
+            let has_profile =
+                profiles.iter().any(|e| e.id == card.membership_id);
+
+            sqlx::query(
+                r#"
+                INSERT INTO "member_link" ("member", "identity", "has_profile")
+                VALUES (?, ?, ?)
+                ON CONFLICT(member) DO UPDATE
+                SET
+                    identity = excluded.identity,
+                    has_profile = excluded.has_profile
+            "#,
+            )
+            .bind(card.membership_id)
+            .bind(identity)
+            .bind(has_profile)
+            .execute(&mut self.db)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// download results from ids in queue for all of the member's characters
+    /// (including linked memberships), newest first, and return number of
+    /// items synced
+    async fn sync_activities(
+        &mut self,
+        member: &Member,
+    ) -> Result<SyncResult, Error> {
+        //activity and character ids
+        let mut ids: Vec<(i64, i64)> = Vec::new();
+
+        //This is to scope rows, so the mutable borrow of self goes out of scope
+        {
+            //the activities are stored from the history when queued, so we
+            //can sync the most recent first
+            let mut rows = sqlx::query(
+                r#"
+                    SELECT
+                        activity_queue.activity_id, activity_queue.character
+                    FROM
+                        "activity_queue"
+                    LEFT JOIN
+                        activity ON activity.activity_id = activity_queue.activity_id
+                    WHERE
+                        activity_queue.synced = 0 AND
+                        activity_queue.character IN (
+                            SELECT character_id FROM character
+                            WHERE member IN (
+                                SELECT linked FROM linked_member WHERE member = ?
+                            )
+                        )
+                    ORDER BY
+                        activity.period DESC, activity_queue.activity_id DESC
+                "#,
+            )
+            .bind(member.id)
+            .fetch(&mut self.db);
+
+            //todo: could probably get this without looping through entire set
+            while let Some(row) = rows.try_next().await? {
+                let activity_id: i64 = row.try_get("activity_id")?;
+                let character_id: i64 = row.try_get("character")?;
+                ids.push((activity_id, character_id));
+            }
+        };
+
+        if ids.is_empty() {
+            return Ok(SyncResult {
+                total_available: 0,
+                total_synced: 0,
+            });
+        }
+
+        let mut filtered_ids = Vec::new();
+
+        //remove ids to sync for activities we have already synced
+        for (id, character_id) in ids {
+            if self.has_activity_detail(&id).await {
+                self.remove_from_activity_queue(&character_id, &id).await?;
+                continue;
+            } else {
+                filtered_ids.push((id, character_id))
+            }
+        }
+        ids = filtered_ids;
+
+        let total_available = ids.len() as u32;
+        let mut total_synced = 0;
+        let mut synced_ids: Vec<i64> = Vec::new();
+
+        use std::fmt::Write;
+
+        let pb = if self.show_progress && Tell::is_active(TellLevel::Progress) {
+            ProgressBar::new(ids.len() as u64)
+        } else {
+            ProgressBar::hidden()
+        };
+
+        pb.set_style(ProgressStyle::with_template(
+                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ETA:({eta_precise})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+
+        let mut count: u64 = 0;
+        for id_chunks in ids.chunks(PGCR_REQUEST_CHUNK_AMOUNT) {
+            let mut f = Vec::new();
+
+            for (c, _) in id_chunks {
+                //this is saving the future, call hasnt been made yet
+                f.push(
+                    self.api_interface.retrieve_post_game_carnage_report(*c),
+                );
+            }
+
+            count += id_chunks.len() as u64;
+            pb.set_position(count);
+
+            //tell::progress!(".");
+
+            //TODO: look into using threading for this
+            let results = futures::future::join_all(f).await;
+
+            //loop through. if we get results. grab those, otherwise, we record
+            //the error, which will keep the IDs in the queue to try next time,
+            //until they have failed max_activity_attempts times
+            for (r, (activity_id, character_id)) in
+                results.into_iter().zip(id_chunks)
+            {
+                let error = match r {
+                    Ok(Some(mut e)) => {
+                        match self.insert_activity(&mut e, character_id).await {
+                            Ok(_e) => {
+                                total_synced += 1;
+                                synced_ids.push(*activity_id);
+                                continue;
+                            }
+                            Err(e) => {
+                                metrics::record_error(&e);
+                                format!(
+                                    "Error inserting data into character activity stats table. : {}",
+                                    e
+                                )
+                            }
+                        }
+                    }
+                    //None means either an API error occured or there is no
+                    //data associated with the ID (which is an api data error)
+                    Ok(None) => {
+                        metrics::record_error(&Error::ApiResponseMissing);
+                        "PGCR returned empty response.".to_string()
+                    }
+                    //network and API availability errors are temporary, so
+                    //dont count them as failed attempts
+                    Err(
+                        e @ Error::ApiNotAvailableException
+                        | e @ Error::RequestTimedOut
+                        | e @ Error::Request,
+                    ) => {
+                        metrics::record_error(&e);
+                        tell::error!(
+                            "Error retrieving activity details from api. Skipping. : {}",
+                            e
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        metrics::record_error(&e);
+                        format!(
+                            "Error retrieving activity details from api. : {}",
+                            e
+                        )
+                    }
+                };
+
+                tell::error!("{} Skipping. ({})", error, activity_id);
+                self.record_activity_failure(character_id, activity_id, &error)
+                    .await?;
+            }
+        }
+
+        pb.finish_and_clear();
+
+        if !ids.is_empty() {
+            sqlx::query("PRAGMA OPTIMIZE;")
+                .execute(&mut self.db)
+                .await?;
+        }
+
+        //the activities have been synced, so dont fail the sync if the
+        //ratings cant be retrieved
+        if let Some(ttl) = self.combat_rating_prefetch {
+            if let Err(e) =
+                self.prefetch_combat_ratings(&synced_ids, &ttl).await
+            {
+                metrics::record_error(&e);
+                tell::error!(
+                    "{}",
+                    format_error("Could not prefetch combat ratings.", e)
+                );
+            }
+        }
+
+        Ok(SyncResult {
+            total_available,
+            total_synced,
+        })
+    }
+
+    async fn update_activity_queue(
+        &mut self,
+        member_id: &i64,
+        character_id: &i64,
+        platform: &Platform,
+        limit: &HistoryLimit,
+        backfill: bool,
+    ) -> Result<SyncResult, Error> {
+        //activities found by walking the history, which max activities
+        //applies to, and new activities since the last sync, which are
+        //always queued
+        let mut walked: Vec<Activity> = Vec::new();
+        let mut activities: Vec<Activity> = Vec::new();
+
+        //TODO catch errors so we can continue?
+        for mode in [Mode::PrivateMatchesAll, Mode::AllPvP].iter() {
+            let (found, is_walk) = self
+                .retrieve_new_activities(
+                    member_id,
+                    character_id,
+                    platform,
+                    mode,
+                    limit,
+                    backfill,
+                )
+                .await?;
+
+            if is_walk {
+                walked.extend(found);
+            } else {
+                activities.extend(found);
+            }
+        }
+
+        //max activities applies across both modes, so we keep the most
+        //recent from either
+        if let Some(e) = limit.max_activities {
+            walked.sort_by_key(|e| std::cmp::Reverse(e.period));
+            walked.truncate(e as usize);
+        }
+
+        activities.extend(walked);
+
+        self.queue_activities(character_id, activities).await
+    }
+
+    /// Compares the member's activity history from the API within the period
+    /// against the stored activities, and queues any which are missing, so
+    /// their details are synced the next time the member is synced.
+    ///
+    /// Syncing only retrieves activities newer than the most recent stored
+    /// activity, so activities missed by an interrupted sync (i.e. an API
+    /// outage part way through the history) are otherwise never retrieved.
+    ///
+    /// Characters from all of the Destiny memberships linked to the member
+    /// which have been synced are verified. Errors verifying a character are
+    /// logged, and the character skipped.
+    pub async fn verify_member_history(
+        &mut self,
+        member: &Member,
+        period: &DateTimePeriod,
+    ) -> Result<HistoryVerification, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                character.character_id, member.member_id, member.platform_id
+            FROM
+                character
+            INNER JOIN
+                member ON character.member = member.member_id
+            WHERE
+                member.member_id IN
+                    (SELECT linked FROM linked_member WHERE member = ?)
+        "#,
+        )
+        .bind(member.id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out = HistoryVerification::default();
+
+        for row in rows.iter() {
+            let character_id: i64 = row.try_get("character_id")?;
+            let member_id: i64 = row.try_get("member_id")?;
+            let platform_id: u32 = row.try_get("platform_id")?;
+            let platform = Platform::from_id(platform_id);
+
+            for mode in [Mode::PrivateMatchesAll, Mode::AllPvP].iter() {
+                match self
+                    .verify_character_history(
+                        &member_id,
+                        &character_id,
+                        &platform,
+                        mode,
+                        period,
+                    )
+                    .await
+                {
+                    Ok((checked, missing)) => {
+                        out.checked += checked;
+                        out.missing += missing;
+                    }
+                    Err(e) => {
+                        metrics::record_error(&e);
+                        tell::error!(
+                            "{}",
+                            format_error(
+                                &format!(
+                                    "Could not verify {} history for character {}. Skipping.",
+                                    mode, character_id
+                                ),
+                                e
+                            )
+                        );
+                        out.failed += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    //returns the number of activities in the character's history within the
+    //period, and the number of those which were missing and queued
+    async fn verify_character_history(
+        &mut self,
+        member_id: &i64,
+        character_id: &i64,
+        platform: &Platform,
+        mode: &Mode,
+        period: &DateTimePeriod,
+    ) -> Result<(u32, u32), Error> {
+        let limit = HistoryLimit {
+            since: Some(period.get_start()),
+            max_activities: None,
+        };
+
+        //start from the most recent activity
+        let activities = match self
+            .api_interface
+            .retrieve_activities_since_id(
+                member_id,
+                character_id,
+                platform,
+                mode,
+                0,
+                &limit,
+            )
+            .await?
+        {
+            Some(e) => e,
+            None => return Ok((0, 0)),
+        };
+
+        let end = period.get_end();
+        let activities: Vec<Activity> = activities
+            .into_iter()
+            .filter(|e| {
+                e.period <= end
+                    && !GAMBIT_PRIVATE_MATCH_HASHES
+                        .contains(&e.details.director_activity_hash)
+            })
+            .collect();
+
+        let checked = activities.len() as u32;
+
+        self.begin_transaction().await?;
+        let missing = match self
+            .queue_missing_activities(activities, character_id)
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+        self.commit_transaction().await?;
+
+        if missing > 0 {
+            tell::verbose!(
+                "Queued {} missing {} activities for character {}",
+                missing,
+                mode,
+                character_id
+            );
+        }
+
+        Ok((checked, missing))
+    }
+
+    //queues and stores the activities from the history which arent stored or
+    //queued for the character. Should be called within a transaction
+    async fn queue_missing_activities(
+        &mut self,
+        activities: Vec<Activity>,
+        character_id: &i64,
+    ) -> Result<u32, Error> {
+        let mut missing = 0;
+        for activity in activities {
+            let instance_id = activity.details.instance_id;
+
+            //activities on the failed list are known, so arent missing
+            let found = sqlx::query(
+                r#"
+                SELECT 1 FROM character_activity_stats
+                WHERE activity = ? AND character = ?
+                UNION ALL
+                SELECT 1 FROM activity_queue
+                WHERE activity_id = ? AND character = ? AND synced IN (?, ?)
+            "#,
+            )
+            .bind(instance_id)
+            .bind(character_id)
+            .bind(instance_id)
+            .bind(character_id)
+            .bind(QUEUE_PENDING)
+            .bind(QUEUE_FAILED)
+            .fetch_optional(&mut self.db)
+            .await?;
+
+            if found.is_some() {
+                continue;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO activity_queue ("activity_id", "character", "synced")
+                VALUES (?, ?, ?)
+                ON CONFLICT(activity_id, character) DO UPDATE
+                SET synced = excluded.synced, attempts = 0
+            "#,
+            )
+            .bind(instance_id)
+            .bind(character_id)
+            .bind(QUEUE_PENDING)
+            .execute(&mut self.db)
+            .await?;
+
+            self._insert_activity_index(activity, character_id).await?;
+            missing += 1;
+        }
+
+        Ok(missing)
+    }
+
+    /// Returns the combat ratings for the players in the activity, keyed by
+    /// character id.
+    ///
+    /// Ratings are cached in the store. The first rating retrieved after the
+    /// activity was played is used, so older activities display the rating as
+    /// of when it was retrieved. Otherwise, the most recent rating is used if
+    /// it was retrieved within the ttl, or the rating is retrieved from the
+    /// API. If it cannot be retrieved, the most recent rating is used.
+    pub async fn retrieve_combat_ratings(
+        &mut self,
+        activity: &CrucibleActivity,
+        ttl: &chrono::Duration,
+    ) -> Result<HashMap<i64, CombatRating>, Error> {
+        let mode = &activity.details.mode;
+
+        let mut out: HashMap<i64, CombatRating> = HashMap::new();
+        let mut players: Vec<(i64, i64, Platform)> = Vec::new();
+        for t in activity.teams.values() {
+            for p in &t.player_performances {
+                let player = &p.player;
+                let (rating, expired) = self
+                    .retrieve_cached_combat_rating(
+                        &player.character_id,
+                        mode,
+                        &activity.details.period,
+                        ttl,
+                    )
+                    .await?;
+
+                if let Some(e) = rating {
+                    out.insert(player.character_id, e);
+                }
+
+                if expired {
+                    players.push((
+                        player.member_id,
+                        player.character_id,
+                        player.platform,
+                    ));
+                }
+            }
+        }
+
+        let fetched = self.fetch_combat_ratings(&players, mode).await?;
+        out.extend(fetched);
+
+        Ok(out)
+    }
+
+    /// Retrieves and caches the combat ratings for the players in the
+    /// activities which were played within the ttl, unless they have been
+    /// retrieved within the ttl. Returns the number of ratings retrieved.
+    ///
+    /// Ratings for older activities are not retrieved, as the current rating
+    /// would not reflect the rating when the activity was played.
+    pub async fn prefetch_combat_ratings(
+        &mut self,
+        activity_ids: &[i64],
+        ttl: &chrono::Duration,
+    ) -> Result<u32, Error> {
+        let since = Utc::now() - *ttl;
+
+        //players to retrieve ratings for, by mode
+        let mut modes: HashMap<u32, Vec<(i64, i64, Platform)>> = HashMap::new();
+        for activity_id in activity_ids {
+            let rows = sqlx::query(
+                r#"
+                SELECT
+                    character.character_id, character.member,
+                    member.platform_id, activity.mode, activity.period
+                FROM
+                    character_activity_stats
+                INNER JOIN
+                    activity ON activity.activity_id = character_activity_stats.activity
+                INNER JOIN
+                    character ON character.character_id = character_activity_stats.character
+                INNER JOIN
+                    member ON member.member_id = character.member
+                WHERE
+                    character_activity_stats.activity = ? AND activity.period >= ?
+            "#,
+            )
+            .bind(activity_id)
+            .bind(since.to_rfc3339())
+            .fetch_all(&mut self.db)
+            .await?;
+
+            for row in rows {
+                let character_id: i64 = row.try_get("character_id")?;
+                let member_id: i64 = row.try_get("member")?;
+                let platform_id: u32 = row.try_get_unchecked("platform_id")?;
+                let mode_id: u32 = row.try_get_unchecked("mode")?;
+                let period: String = row.try_get("period")?;
+                let period =
+                    DateTime::parse_from_rfc3339(&period)?.with_timezone(&Utc);
+
+                let players = modes.entry(mode_id).or_default();
+                if players.iter().any(|e| e.1 == character_id) {
+                    continue;
+                }
+
+                let mode = Mode::from_id(mode_id)?;
+                let (_, expired) = self
+                    .retrieve_cached_combat_rating(
+                        &character_id,
+                        &mode,
+                        &period,
+                        ttl,
+                    )
+                    .await?;
+
+                if expired {
+                    players.push((
+                        member_id,
+                        character_id,
+                        Platform::from_id(platform_id),
+                    ));
+                }
+            }
+        }
+
+        let mut count = 0;
+        for (mode_id, players) in modes {
+            let mode = Mode::from_id(mode_id)?;
+            count += self.fetch_combat_ratings(&players, &mode).await?.len();
+        }
+
+        if count > 0 {
+            tell::verbose!("Prefetched {} combat ratings", count);
+        }
+
+        Ok(count as u32)
+    }
+
+    //returns the cached combat rating for the character as of the period,
+    //and whether the rating needs to be retrieved from the API
+    async fn retrieve_cached_combat_rating(
+        &mut self,
+        character_id: &i64,
+        mode: &Mode,
+        period: &DateTime<Utc>,
+        ttl: &chrono::Duration,
+    ) -> Result<(Option<CombatRating>, bool), Error> {
+        //first rating retrieved after the activity
+        let row = sqlx::query(
+            r#"
+            SELECT rating, fetched FROM combat_rating
+            WHERE character = ? AND mode = ? AND fetched >= ?
+            ORDER BY fetched ASC
+            LIMIT 1
+        "#,
+        )
+        .bind(character_id)
+        .bind(mode.as_id())
+        .bind(period.to_rfc3339())
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        if let Some(row) = row {
+            return Ok((Some(parse_combat_rating_row(&row)?), false));
+        }
+
+        //otherwise the most recent rating, if it is still current
+        let row = sqlx::query(
+            r#"
+            SELECT rating, fetched FROM combat_rating
+            WHERE character = ? AND mode = ?
+            ORDER BY fetched DESC
+            LIMIT 1
+        "#,
+        )
+        .bind(character_id)
+        .bind(mode.as_id())
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        match row {
+            Some(row) => {
+                let rating = parse_combat_rating_row(&row)?;
+                let expired = Utc::now() - rating.fetched >= *ttl;
+                Ok((Some(rating), expired))
+            }
+            None => Ok((None, true)),
+        }
+    }
+
+    //retrieves the combat ratings for the players (member id, character id,
+    //platform) from the API, and stores them. Players whose ratings cannot be
+    //retrieved are not included in the results
+    async fn fetch_combat_ratings(
+        &mut self,
+        players: &[(i64, i64, Platform)],
+        mode: &Mode,
+    ) -> Result<HashMap<i64, CombatRating>, Error> {
+        let mut out: HashMap<i64, CombatRating> = HashMap::new();
+
+        for chunk in players.chunks(PGCR_REQUEST_CHUNK_AMOUNT) {
+            let fetched = Utc::now();
+
+            let api = &self.api_interface;
+            let f = chunk.iter().map(|(member_id, character_id, platform)| {
+                api.retrieve_alltime_crucible_stats(
+                    member_id,
+                    character_id,
+                    platform,
+                    mode,
+                )
+            });
+            let results = futures::future::join_all(f).await;
+
+            for (r, (_, character_id, _)) in results.into_iter().zip(chunk) {
+                let rating = match r {
+                    Ok(Some(e)) => e.combat_rating,
+                    //no stats for the mode
+                    Ok(None) => continue,
+                    Err(e) => {
+                        metrics::record_error(&e);
+                        tell::verbose!(
+                            "Could not retrieve combat rating for character {} : {}",
+                            character_id,
+                            e
+                        );
+                        continue;
+                    }
+                };
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO combat_rating
+                        ("character", "mode", "rating", "fetched")
+                    VALUES (?, ?, ?, ?)
+                "#,
+                )
+                .bind(character_id)
+                .bind(mode.as_id())
+                .bind(rating)
+                .bind(fetched.to_rfc3339())
+                .execute(&mut self.db)
+                .await?;
+
+                out.insert(*character_id, CombatRating { rating, fetched });
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Updates players' skill ratings with the activities which have been
+    /// synced since they were last updated. Returns the number of activities
+    /// which were rated.
+    ///
+    /// Ratings are calculated by replaying activities in the order they were
+    /// played, separately for each mode family. If an activity is synced
+    /// which was played before activities which have already been rated (such
+    /// as when backfilling history), the ratings for its family are
+    /// recalculated from that activity.
+    ///
+    /// Details are synced newest first, so only activities played before the
+    /// oldest activity whose details are still queued for a synced player are
+    /// rated. The rest are rated once their details have been synced, so the
+    /// ratings dont have to be recalculated each time.
+    pub async fn update_skill_ratings(&mut self) -> Result<u32, Error> {
+        let cutoff = self.retrieve_skill_rating_cutoff().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                activity.activity_id, activity.period, activity.mode,
+                group_concat(modes.mode) AS modes
+            FROM
+                activity
+            LEFT JOIN
+                modes ON modes.activity = activity.activity_id
+            WHERE
+                activity.detail_pending = 0 AND
+                activity.period < ? AND
+                activity.activity_id NOT IN
+                    (SELECT activity FROM skill_rating_activity)
+            GROUP BY
+                activity.activity_id
+        "#,
+        )
+        .bind(&cutoff)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        //earliest activity which hasnt been rated for each family
+        let mut starts: HashMap<ModeFamily, String> = HashMap::new();
+        let mut activity_ids: Vec<i64> = Vec::with_capacity(rows.len());
+        for row in &rows {
+            activity_ids.push(row.try_get("activity_id")?);
+
+            let family = match parse_mode_family(row)? {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let period: String = row.try_get("period")?;
+            match starts.get(&family) {
+                Some(e) if *e <= period => (),
+                _ => {
+                    starts.insert(family, period);
+                }
+            }
+        }
+
+        self.begin_transaction().await?;
+        let count = match self.replay_skill_ratings(&starts, &cutoff).await {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+
+        //activities which cant be rated (such as private matches) are
+        //marked so they arent checked again
+        for activity_id in activity_ids {
+            if let Err(e) = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO skill_rating_activity ("activity")
+                VALUES (?)
+            "#,
+            )
+            .bind(activity_id)
+            .execute(&mut self.db)
+            .await
+            {
+                self.rollback_transaction().await?;
+                return Err(Error::from(e));
+            }
+        }
+
+        self.commit_transaction().await?;
+
+        tell::verbose!("Updated skill ratings for {} activities", count);
+
+        Ok(count)
+    }
+
+    //returns the period of the oldest activity whose details are still
+    //queued to be synced for a synced player. Activities after it are not
+    //rated yet
+    async fn retrieve_skill_rating_cutoff(&mut self) -> Result<String, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                MIN(activity.period) AS period
+            FROM
+                activity_queue
+            INNER JOIN
+                activity ON activity.activity_id = activity_queue.activity_id
+            INNER JOIN
+                character ON character.character_id = activity_queue.character
+            WHERE
+                activity_queue.synced = ? AND
+                activity.detail_pending = 1 AND
+                character.member IN (
+                    SELECT linked FROM linked_member
+                    WHERE member IN (SELECT member FROM sync)
+                )
+        "#,
+        )
+        .bind(QUEUE_PENDING)
+        .fetch_one(&mut self.db)
+        .await?;
+
+        let period: Option<String> = row.try_get("period")?;
+
+        //periods are compared as strings, so this is after any period
+        Ok(period.unwrap_or_else(|| "9999".to_string()))
+    }
+
+    //recalculates the skill ratings for each family from the period it is
+    //mapped to, up to the cutoff. Should be called within a transaction
+    async fn replay_skill_ratings(
+        &mut self,
+        starts: &HashMap<ModeFamily, String>,
+        cutoff: &str,
+    ) -> Result<u32, Error> {
+        let start = match starts.values().min() {
+            Some(e) => e.clone(),
+            None => return Ok(0),
+        };
+
+        //each player's most recent rating before the replay, and the period
+        //of the activity it is from, keyed by their identity
+        let mut ratings: HashMap<(ModeFamily, i64), (SkillRating, String)> =
+            HashMap::new();
+
+        for (family, period) in starts {
+            //activities after the cutoff which have already been rated (i.e.
+            //when older activities are backfilled) are rated again once the
+            //cutoff has passed them
+            sqlx::query(
+                r#"
+                DELETE FROM skill_rating_activity WHERE activity IN (
+                    SELECT activity FROM skill_rating
+                    WHERE family = ? AND period >= ?
+                )
+            "#,
+            )
+            .bind(family.as_id())
+            .bind(cutoff)
+            .execute(&mut self.db)
+            .await?;
+
+            sqlx::query(
+                r#"
+                DELETE FROM skill_rating WHERE family = ? AND period >= ?
+            "#,
+            )
+            .bind(family.as_id())
+            .bind(period)
+            .execute(&mut self.db)
+            .await?;
+
+            let rows = sqlx::query(
+                r#"
+                SELECT identity, period, rating, deviation, volatility FROM (
+                    SELECT
+                        skill_rating.*,
+                        COALESCE(member_link.identity, skill_rating.member)
+                            AS identity,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY COALESCE(
+                                member_link.identity, skill_rating.member)
+                            ORDER BY period DESC, activity DESC
+                        ) AS n
+                    FROM
+                        skill_rating
+                    LEFT JOIN
+                        member_link ON member_link.member = skill_rating.member
+                    WHERE
+                        family = ?
+                )
+                WHERE n = 1
+            "#,
+            )
+            .bind(family.as_id())
+            .fetch_all(&mut self.db)
+            .await?;
+
+            for row in rows {
+                let identity: i64 = row.try_get("identity")?;
+                ratings.insert(
+                    (*family, identity),
+                    (
+                        SkillRating {
+                            rating: row.try_get("rating")?,
+                            deviation: row.try_get("deviation")?,
+                            volatility: row.try_get("volatility")?,
+                        },
+                        row.try_get("period")?,
+                    ),
+                );
+            }
+        }
+
+        let activities = sqlx::query(
+            r#"
+            SELECT
+                activity.activity_id, activity.period, activity.mode,
+                group_concat(modes.mode) AS modes
+            FROM
+                activity
+            LEFT JOIN
+                modes ON modes.activity = activity.activity_id
+            WHERE
+                activity.detail_pending = 0 AND
+                activity.period >= ? AND
+                activity.period < ?
+            GROUP BY
+                activity.activity_id
+            ORDER BY
+                activity.period ASC, activity.activity_id ASC
+        "#,
+        )
+        .bind(&start)
+        .bind(cutoff)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        //players in each activity, with their team's standing and score
+        let mut players: HashMap<i64, Vec<RatedPlayer>> = HashMap::new();
+        {
+            let mut rows = sqlx::query(
+                r#"
+                SELECT
+                    character_activity_stats.activity, character.member,
+                    COALESCE(member_link.identity, character.member)
+                        AS identity,
+                    team_result.team_id, team_result.standing, team_result.score
+                FROM
+                    character_activity_stats
+                INNER JOIN
+                    activity ON activity.activity_id = character_activity_stats.activity
+                INNER JOIN
+                    character ON character.character_id = character_activity_stats.character
+                INNER JOIN
+                    team_result ON
+                        team_result.activity = character_activity_stats.activity AND
+                        team_result.team_id = character_activity_stats.team
+                LEFT JOIN
+                    member_link ON member_link.member = character.member
+                WHERE
+                    activity.detail_pending = 0 AND
+                    activity.period >= ? AND
+                    activity.period < ?
+            "#,
+            )
+            .bind(&start)
+            .bind(cutoff)
+            .fetch(&mut self.db);
+
+            while let Some(row) = rows.try_next().await? {
+                players.entry(row.try_get("activity")?).or_default().push(
+                    RatedPlayer {
+                        member_id: row.try_get("member")?,
+                        identity: row.try_get("identity")?,
+                        team_id: row.try_get("team_id")?,
+                        standing: row.try_get("standing")?,
+                        score: row.try_get("score")?,
+                    },
+                );
+            }
+        }
+
+        if activities.len() > SKILL_RATING_PROGRESS_COUNT {
+            tell::progress!(
+                "Calculating skill ratings for {} activities.",
+                activities.len()
+            );
+        }
+
+        let mut count = 0;
+        for row in &activities {
+            let family = match parse_mode_family(row)? {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let period: String = row.try_get("period")?;
+            match starts.get(&family) {
+                Some(e) if *e <= period => (),
+                _ => continue,
+            }
+
+            let activity_id: i64 = row.try_get("activity_id")?;
+            let activity_players = match players.get(&activity_id) {
+                Some(e) => e,
+                None => continue,
+            };
+
+            if self
+                .rate_activity(
+                    activity_id,
+                    &period,
+                    family,
+                    activity_players,
+                    &mut ratings,
+                )
+                .await?
+            {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    //rates an activity between two teams, updating the players' ratings and
+    //storing them. Ratings are keyed by the players' identities, so linked
+    //memberships share a rating. Returns false if the activity could not be
+    //rated
+    async fn rate_activity(
+        &mut self,
+        activity_id: i64,
+        period: &str,
+        family: ModeFamily,
+        players: &[RatedPlayer],
+        ratings: &mut HashMap<(ModeFamily, i64), (SkillRating, String)>,
+    ) -> Result<bool, Error> {
+        let played = DateTime::parse_from_rfc3339(period)?.with_timezone(&Utc);
+
+        //team id, standing, score and the players on the team
+        let mut teams: Vec<(i32, i32, i32, Vec<&RatedPlayer>)> = Vec::new();
+        for player in players {
+            match teams.iter_mut().find(|e| e.0 == player.team_id) {
+                Some(e) => e.3.push(player),
+                None => teams.push((
+                    player.team_id,
+                    player.standing,
+                    player.score,
+                    vec![player],
+                )),
+            }
+        }
+
+        if teams.len() != 2 {
+            return Ok(false);
+        }
+
+        //the standing decides the result, as the team with the higher score
+        //doesnt always win (such as when the other team quits)
+        let (first, second) = (&teams[0], &teams[1]);
+        let score = if first.1 == 0 && second.1 != 0 {
+            1.0
+        } else if first.1 != 0 && second.1 == 0 {
+            0.0
+        } else if first.2 != second.2 {
+            if first.2 > second.2 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            0.5
+        };
+
+        //ratings before the activity
+        let mut pre: Vec<Vec<SkillRating>> = Vec::with_capacity(2);
+        for team in &teams {
+            let mut out = Vec::with_capacity(team.3.len());
+            for player in &team.3 {
+                let rating = match ratings.get(&(family, player.identity)) {
+                    Some((rating, last)) => {
+                        let last = DateTime::parse_from_rfc3339(last)?
+                            .with_timezone(&Utc);
+                        rating.with_inactivity((played - last).num_seconds())
+                    }
+                    None => SkillRating::default(),
+                };
+                out.push(rating);
+            }
+            pre.push(out);
+        }
+
+        let (first_post, second_post) =
+            skillrating::rate_teams(&pre[0], &pre[1], score);
+
+        for (team, (pre, post)) in teams
+            .iter()
+            .zip([(&pre[0], first_post), (&pre[1], second_post)])
+        {
+            for ((player, pre), post) in team.3.iter().zip(pre).zip(post) {
+                sqlx::query(
+                    r#"
+                    INSERT OR REPLACE INTO skill_rating
+                        ("member", "family", "activity", "period",
+                        "pre_rating", "pre_deviation",
+                        "rating", "deviation", "volatility")
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                )
+                .bind(player.member_id)
+                .bind(family.as_id())
+                .bind(activity_id)
+                .bind(period)
+                .bind(pre.rating)
+                .bind(pre.deviation)
+                .bind(post.rating)
+                .bind(post.deviation)
+                .bind(post.volatility)
+                .execute(&mut self.db)
+                .await?;
+
+                ratings.insert(
+                    (family, player.identity),
+                    (post, period.to_string()),
+                );
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the member's skill ratings for the mode family after each
+    /// rated activity within the period, in the order they were played.
+    ///
+    /// Ratings for the member's linked memberships are included.
+    pub async fn retrieve_skill_ratings(
+        &mut self,
+        member: &Member,
+        family: &ModeFamily,
+        period: &DateTimePeriod,
+    ) -> Result<Vec<SkillRatingEntry>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM skill_rating
+            WHERE
+                member IN
+                    (SELECT linked FROM linked_member WHERE member = ?) AND
+                family = ? AND period >= ? AND period <= ?
+            ORDER BY
+                period ASC, activity ASC
+        "#,
+        )
+        .bind(member.id)
+        .bind(family.as_id())
+        .bind(period.get_start().to_rfc3339())
+        .bind(period.get_end().to_rfc3339())
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<SkillRatingEntry> = Vec::with_capacity(rows.len());
+        for row in &rows {
+            out.push(parse_skill_rating_row(row)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Returns the skill ratings for the players in the activity, keyed by
+    /// member id. Players who were not rated in the activity (such as in
+    /// private matches, or activities which have not been rated yet) are not
+    /// included.
+    pub async fn retrieve_activity_skill_ratings(
+        &mut self,
+        activity_id: i64,
+    ) -> Result<HashMap<i64, SkillRatingEntry>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM skill_rating WHERE activity = ?
+        "#,
+        )
+        .bind(activity_id)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: HashMap<i64, SkillRatingEntry> = HashMap::new();
+        for row in &rows {
+            let member_id: i64 = row.try_get("member")?;
+            out.insert(member_id, parse_skill_rating_row(row)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Merges the activity data from another dcli.sqlite3 data store into
+    /// this store.
+    ///
+    /// Rows which already exist in this store (based on the activity_id and
+    /// the tables UNIQUE constraints) are not duplicated. If both stores have
+    /// conflicting data for the same row, the data in this store is kept and
+    /// the conflict is included in the returned TransferResult. Activities
+    /// pending their details are queued to be synced if a synced player
+    /// played in them.
+    pub async fn merge_store(
+        &mut self,
+        source: &Path,
+    ) -> Result<TransferResult, Error> {
+        if !source.is_file() {
+            return Err(Error::IoFileDoesNotExist {
+                description: format!(
+                    "Data store not found : {}",
+                    source.display()
+                ),
+            });
+        }
+
+        if let (Ok(a), Ok(b)) =
+            (source.canonicalize(), Path::new(&self.path).canonicalize())
+        {
+            if a == b {
+                return Err(Error::InvalidArgument {
+                    description: "Cannot merge a data store into itself."
+                        .to_string(),
+                });
+            }
+        }
+
+        //note, attach cannot be called within a transaction
+        sqlx::query(
+            r#"
+            ATTACH DATABASE ? AS merge_source
+        "#,
+        )
+        .bind(source.display().to_string())
+        .execute(&mut self.db)
+        .await?;
+
+        let result = self.merge_attached_store().await;
+
+        sqlx::query(
+            r#"
+            DETACH DATABASE merge_source
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?;
+
+        result
+    }
+
+    async fn merge_attached_store(&mut self) -> Result<TransferResult, Error> {
+        let version: i32 = match sqlx::query(
+            r#"
+            SELECT max(version) as max_version FROM merge_source.version
+        "#,
+        )
+        .fetch_one(&mut self.db)
+        .await
+        {
+            Ok(e) => e.try_get("max_version").unwrap_or(-1),
+            Err(_e) => -1,
+        };
+
+        if version != DB_SCHEMA_VERSION {
+            return Err(Error::Database {
+                description: format!(
+                    "Data store schema version {} does not match expected version {}. Run dclisync against the data store to update it.",
+                    version, DB_SCHEMA_VERSION
+                ),
+            });
+        }
+
+        let conflicts = self.find_merge_conflicts().await?;
+
+        self.begin_transaction().await?;
+        let result = match self.insert_merge_rows().await {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+        self.commit_transaction().await?;
+
+        Ok(TransferResult {
+            conflicts,
+            ..result
+        })
+    }
+
+    //finds rows which exist in both stores with different data. The local
+    //data is kept for all of these. Activities pending their details in
+    //either store are not compared, since they are replaced once the
+    //details are synced
+    async fn find_merge_conflicts(&mut self) -> Result<Vec<String>, Error> {
+        let queries = [
+            r#"
+            SELECT
+                printf('Member %d name : %s#%s (local) / %s#%s (merged)',
+                    l.member_id,
+                    l.bungie_display_name, l.bungie_display_name_code,
+                    s.bungie_display_name, s.bungie_display_name_code
+                ) as description
+            FROM
+                main.member as l
+            INNER JOIN
+                merge_source.member as s ON l.member_id = s.member_id
+            WHERE
+                l.bungie_display_name IS NOT NULL AND
+                s.bungie_display_name IS NOT NULL AND
+                (l.bungie_display_name != s.bungie_display_name OR
+                l.bungie_display_name_code IS NOT s.bungie_display_name_code)
+        "#,
+            r#"
+            SELECT
+                printf('Member %d link : identity %d (local) / identity %d (merged)',
+                    l.member, l.identity, s.identity
+                ) as description
+            FROM
+                main.member_link as l
+            INNER JOIN
+                merge_source.member_link as s ON l.member = s.member
+            WHERE
+                l.identity != s.identity
+        "#,
+            r#"
+            SELECT
+                printf('Character %d : member %d class %d (local) / member %d class %d (merged)',
+                    l.character_id, l.member, l.class, s.member, s.class
+                ) as description
+            FROM
+                main.character as l
+            INNER JOIN
+                merge_source.character as s
+                    ON l.character_id = s.character_id
+            WHERE
+                l.member != s.member OR l.class != s.class
+        "#,
+            r#"
+            SELECT
+                printf('Activity %d : %s mode %d (local) / %s mode %d (merged)',
+                    l.activity_id, l.period, l.mode, s.period, s.mode
+                ) as description
+            FROM
+                main.activity as l
+            INNER JOIN
+                merge_source.activity as s ON l.activity_id = s.activity_id
+            WHERE
+                l.detail_pending = 0 AND s.detail_pending = 0 AND
+                (l.period != s.period OR l.mode != s.mode OR
+                l.director_activity_hash != s.director_activity_hash)
+        "#,
+            r#"
+            SELECT
+                printf('Activity %d character %d stats : %d/%d/%d (local) / %d/%d/%d (merged) kills/deaths/assists',
+                    l.activity, l.character,
+                    l.kills, l.deaths, l.assists,
+                    s.kills, s.deaths, s.assists
+                ) as description
+            FROM
+                main.character_activity_stats as l
+            INNER JOIN
+                merge_source.character_activity_stats as s
+                    ON l.activity = s.activity AND l.character = s.character
+            WHERE
+                (l.kills != s.kills OR l.deaths != s.deaths OR
+                l.assists != s.assists OR l.score != s.score OR
+                l.time_played_seconds != s.time_played_seconds) AND
+                l.activity NOT IN
+                    (SELECT activity_id FROM main.activity
+                    WHERE detail_pending = 1) AND
+                s.activity NOT IN
+                    (SELECT activity_id FROM merge_source.activity
+                    WHERE detail_pending = 1)
+        "#,
+        ];
+
+        let mut out: Vec<String> = Vec::new();
+        for query in queries.iter() {
+            let rows = sqlx::query(query).fetch_all(&mut self.db).await?;
+
+            for row in rows.iter() {
+                let description: String = row.try_get("description")?;
+                out.push(description);
+            }
+        }
+
+        Ok(out)
+    }
+
+    //copies the rows from the attached merge_source store. Should be called
+    //within a transaction
+    async fn insert_merge_rows(&mut self) -> Result<TransferResult, Error> {
+        //only fill in member names which are missing locally
+        let members = sqlx::query(
+            r#"
+            INSERT INTO main.member
+                ("member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code")
+            SELECT
+                member_id, platform_id, display_name,
+                bungie_display_name, bungie_display_name_code
+            FROM merge_source.member WHERE true
+            ON CONFLICT(member_id) DO UPDATE SET
+                display_name =
+                    coalesce(member.display_name, excluded.display_name),
+                bungie_display_name = coalesce(member.bungie_display_name,
+                    excluded.bungie_display_name),
+                bungie_display_name_code =
+                    coalesce(member.bungie_display_name_code,
+                        excluded.bungie_display_name_code)
+            WHERE
+                (member.bungie_display_name IS NULL AND
+                    excluded.bungie_display_name IS NOT NULL) OR
+                (member.bungie_display_name_code IS NULL AND
+                    excluded.bungie_display_name_code IS NOT NULL) OR
+                (member.display_name IS NULL AND
+                    excluded.display_name IS NOT NULL)
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let member_links = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.member_link
+                ("member", "identity", "has_profile")
+            SELECT member, identity, has_profile FROM merge_source.member_link
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let member_names = sqlx::query(
+            r#"
+            INSERT INTO main.member_name
+                ("member", "bungie_display_name", "bungie_display_name_code",
+                "first_seen", "last_seen")
+            SELECT
+                member, bungie_display_name, bungie_display_name_code,
+                first_seen, last_seen
+            FROM merge_source.member_name WHERE true
+            ON CONFLICT(member, bungie_display_name, bungie_display_name_code)
+            DO UPDATE SET
+                first_seen = min(first_seen, excluded.first_seen),
+                last_seen = max(last_seen, excluded.last_seen)
+            WHERE
+                excluded.first_seen < member_name.first_seen OR
+                excluded.last_seen > member_name.last_seen
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let characters = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.character
+                ("character_id", "member", "class")
+            SELECT character_id, member, class FROM merge_source.character
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        //activities only stored from the activity history locally are
+        //replaced if the merged store has their details. Their stats and
+        //modes are removed via ON DELETE CASCADE
+        sqlx::query(
+            r#"
+            DELETE FROM main.activity
+            WHERE
+                detail_pending = 1 AND
+                activity_id IN (
+                    SELECT activity_id FROM merge_source.activity
+                    WHERE detail_pending = 0
+                )
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?;
+
+        let activities = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.activity
+                ("activity_id", "period", "mode", "platform",
+                "director_activity_hash", "reference_id", "detail_pending")
+            SELECT
+                activity_id, period, mode, platform,
+                director_activity_hash, reference_id, detail_pending
+            FROM merge_source.activity
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let modes = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.modes ("mode", "activity")
+            SELECT mode, activity FROM merge_source.modes
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let team_results = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.team_result
+                ("team_id", "activity", "score", "standing")
+            SELECT team_id, activity, score, standing
+            FROM merge_source.team_result
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let character_activity_stats = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.character_activity_stats
+                ("character", "activity", "assists", "score", "kills",
+                "deaths", "average_score_per_kill", "average_score_per_life",
+                "completed", "opponents_defeated",
+                "activity_duration_seconds", "standing", "team",
+                "completion_reason", "start_seconds", "time_played_seconds",
+                "player_count", "team_score", "precision_kills",
+                "weapon_kills_ability", "weapon_kills_grenade",
+                "weapon_kills_melee", "weapon_kills_super",
+                "all_medals_earned", "light_level", "emblem_hash",
+                "fireteam_id")
+            SELECT
+                character, activity, assists, score, kills,
+                deaths, average_score_per_kill, average_score_per_life,
+                completed, opponents_defeated,
+                activity_duration_seconds, standing, team,
+                completion_reason, start_seconds, time_played_seconds,
+                player_count, team_score, precision_kills,
+                weapon_kills_ability, weapon_kills_grenade,
+                weapon_kills_melee, weapon_kills_super,
+                all_medals_earned, light_level, emblem_hash,
+                fireteam_id
+            FROM merge_source.character_activity_stats
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        //character_activity_stats ids are different between stores, so we
+        //map them through the activity and character
+        let weapon_results = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.weapon_result
+                ("reference_id", "kills", "precision_kills",
+                "kills_precision_kills_ratio", "character_activity_stats")
+            SELECT
+                w.reference_id, w.kills, w.precision_kills,
+                w.kills_precision_kills_ratio, l.id
+            FROM
+                merge_source.weapon_result as w
+            INNER JOIN
+                merge_source.character_activity_stats as s
+                    ON w.character_activity_stats = s.id
+            INNER JOIN
+                main.character_activity_stats as l
+                    ON l.activity = s.activity AND l.character = s.character
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        let medal_results = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.medal_result
+                ("reference_id", "count", "character_activity_stats")
+            SELECT
+                m.reference_id, m.count, l.id
+            FROM
+                merge_source.medal_result as m
+            INNER JOIN
+                merge_source.character_activity_stats as s
+                    ON m.character_activity_stats = s.id
+            INNER JOIN
+                main.character_activity_stats as l
+                    ON l.activity = s.activity AND l.character = s.character
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        //keep the most recent sync time for each member. History limits are
+        //only copied for new members
+        let sync_entries = sqlx::query(
+            r#"
+            INSERT INTO main.sync (
+                "member", "last_sync", "sync_since", "max_activities",
+                "history_since", "history_max_activities"
+            )
+            SELECT
+                member, last_sync, sync_since, max_activities,
+                history_since, history_max_activities
+            FROM merge_source.sync WHERE true
+            ON CONFLICT(member) DO UPDATE SET
+                last_sync = excluded.last_sync
+            WHERE
+                excluded.last_sync > sync.last_sync
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        self.queue_pending_activities().await?;
+
+        Ok(TransferResult {
+            members,
+            member_links,
+            member_names,
+            characters,
+            activities,
+            modes,
+            team_results,
+            character_activity_stats,
+            weapon_results,
+            medal_results,
+            sync_entries,
+            conflicts: Vec::new(),
+        })
+    }
+
+    /// Exports the store to a gzip compressed JSON lines archive at path.
+    ///
+    /// If member is specified, only activities the member played in are
+    /// exported, along with the members and characters in those activities.
+    /// Only activities within period are exported.
+    pub async fn export_archive(
+        &mut self,
+        path: &Path,
+        member: Option<&Member>,
+        period: &DateTimePeriod,
+    ) -> Result<TransferResult, Error> {
+        let file = File::create(path)?;
+        let mut writer =
+            GzEncoder::new(BufWriter::new(file), Compression::default());
+
+        //read everything within a single transaction, so we export a
+        //consistent snapshot even if the store is being synced
+        self.begin_read_transaction().await?;
+        let result = match self
+            .write_archive(&mut writer, member.map(|e| e.id), period)
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+        self.rollback_transaction().await?;
+
+        writer.finish()?.flush()?;
+
+        Ok(result)
+    }
+
+    async fn write_archive<W: Write>(
+        &mut self,
+        writer: &mut W,
+        member_id: Option<i64>,
+        period: &DateTimePeriod,
+    ) -> Result<TransferResult, Error> {
+        self.select_archive_rows(member_id, period).await?;
+
+        let header = serde_json::json!({
+            "type": ARCHIVE_HEADER_TYPE,
+            "format": ARCHIVE_FORMAT,
+            "version": ARCHIVE_VERSION,
+            "schema_version": DB_SCHEMA_VERSION,
+            "created": Utc::now().to_rfc3339(),
+            "member": member_id,
+            "start": period.get_start().to_rfc3339(),
+            "end": period.get_end().to_rfc3339(),
+        });
+        writeln!(writer, "{}", header)?;
+
+        let mut result = TransferResult::default();
+        for table in ARCHIVE_TABLES.iter() {
+            let mut rows = sqlx::query(table.export_sql).fetch(&mut self.db);
+
+            while let Some(row) = rows.try_next().await? {
+                let mut record = serde_json::Map::new();
+                record.insert(
+                    "type".to_string(),
+                    serde_json::Value::from(table.name),
+                );
+
+                for (i, column) in row.columns().iter().enumerate() {
+                    record.insert(
+                        column.name().to_string(),
+                        sqlite_value_to_json(&row, i)?,
+                    );
+                }
+
+                writeln!(writer, "{}", serde_json::Value::Object(record))?;
+                result.increment(table.name, 1);
+            }
+        }
+
+        Ok(result)
+    }
+
+    //populates the temp tables with the ids of the rows to be exported
+    async fn select_archive_rows(
+        &mut self,
+        member_id: Option<i64>,
+        period: &DateTimePeriod,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            DROP TABLE IF EXISTS temp.archive_activity;
+            DROP TABLE IF EXISTS temp.archive_sync;
+            DROP TABLE IF EXISTS temp.archive_character;
+            DROP TABLE IF EXISTS temp.archive_member;
+            CREATE TEMP TABLE archive_activity
+                (activity_id INTEGER PRIMARY KEY);
+            CREATE TEMP TABLE archive_sync (member INTEGER PRIMARY KEY);
+            CREATE TEMP TABLE archive_character
+                (character_id INTEGER PRIMARY KEY);
+            CREATE TEMP TABLE archive_member (member_id INTEGER PRIMARY KEY);
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO archive_activity
+            SELECT
+                activity_id
+            FROM
+                main.activity
+            WHERE
+                period > ? AND
+                period < ? AND
+                (? IS NULL OR activity_id IN (
+                    SELECT
+                        s.activity
+                    FROM
+                        main.character_activity_stats as s
+                    INNER JOIN
+                        main.character as c ON s.character = c.character_id
+                    WHERE
+                        c.member IN
+                            (SELECT linked FROM linked_member WHERE member = ?)
+                ))
+        "#,
+        )
+        .bind(period.get_start().to_rfc3339())
+        .bind(period.get_end().to_rfc3339())
+        .bind(member_id)
+        .bind(member_id)
+        .execute(&mut self.db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO archive_sync
+            SELECT member FROM main.sync WHERE ? IS NULL OR member IN
+                (SELECT linked FROM linked_member WHERE member = ?)
+        "#,
+        )
+        .bind(member_id)
+        .bind(member_id)
+        .execute(&mut self.db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO archive_character
+            SELECT
+                character
+            FROM
+                main.character_activity_stats
+            WHERE
+                activity IN (SELECT activity_id FROM archive_activity)
+            UNION
+            SELECT
+                character_id
+            FROM
+                main.character
+            WHERE
+                member IN (SELECT member FROM archive_sync);
+
+            INSERT INTO archive_member
+            SELECT
+                member
+            FROM
+                main.character
+            WHERE
+                character_id IN (SELECT character_id FROM archive_character)
+            UNION
+            SELECT member FROM archive_sync;
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Imports an archive created by export_archive into the store.
+    ///
+    /// Rows which already exist in the store are not duplicated, and
+    /// existing data is not overwritten. Conflicting member links are
+    /// included in the returned TransferResult.
+    pub async fn import_archive(
+        &mut self,
+        path: &Path,
+    ) -> Result<TransferResult, Error> {
+        if !path.is_file() {
+            return Err(Error::IoFileDoesNotExist {
+                description: format!("{}", path.display()),
+            });
+        }
+
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        let mut lines = reader.lines();
+
+        let header = match lines.next() {
+            Some(e) => e?,
+            None => {
+                return Err(Error::Archive {
+                    description: "Archive is empty.".to_string(),
+                })
+            }
+        };
+
+        let header: serde_json::Value =
+            serde_json::from_str(&header).map_err(|_e| Error::Archive {
+                description: "Could not parse archive header.".to_string(),
+            })?;
+
+        if header["format"] != ARCHIVE_FORMAT {
+            return Err(Error::Archive {
+                description: "File is not a dcli activity archive.".to_string(),
+            });
+        }
+
+        let version = header["version"].as_u64().unwrap_or(0);
+        if version == 0 || version > ARCHIVE_VERSION as u64 {
+            return Err(Error::Archive {
+                description: format!(
+                    "Archive version {} is not supported (expected version {} or lower). Make sure you are running the latest version of dcli.",
+                    version, ARCHIVE_VERSION
+                ),
+            });
+        }
+
+        self.begin_transaction().await?;
+        let result = match self.import_archive_records(lines).await {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+        self.commit_transaction().await?;
+
+        Ok(result)
+    }
+
+    async fn import_archive_records<R: BufRead>(
+        &mut self,
+        lines: std::io::Lines<R>,
+    ) -> Result<TransferResult, Error> {
+        let mut result = TransferResult::default();
+
+        //line numbers start at 2, after the header
+        for (line, i) in lines.zip(2..) {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&line).map_err(|e| Error::Archive {
+                    description: format!(
+                        "Could not parse record on line {} : {}",
+                        i, e
+                    ),
+                })?;
+
+            let record_type =
+                record.get("type").and_then(|e| e.as_str()).unwrap_or("");
+
+            //ignore record types we dont know about
+            let table =
+                match ARCHIVE_TABLES.iter().find(|e| e.name == record_type) {
+                    Some(e) => e,
+                    None => continue,
+                };
+
+            if let Some(conflict_sql) = table.conflict_sql {
+                let row = bind_archive_record(
+                    sqlx::query(conflict_sql),
+                    &record,
+                    table.import_fields,
+                )
+                .fetch_optional(&mut self.db)
+                .await?;
+
+                if let Some(row) = row {
+                    result.conflicts.push(row.try_get("description")?);
+                }
+            }
+
+            let count = bind_archive_record(
+                sqlx::query(table.import_sql),
+                &record,
+                table.import_fields,
+            )
+            .execute(&mut self.db)
+            .await?
+            .rows_affected();
+            result.increment(table.name, count);
+        }
+
+        self.queue_pending_activities().await?;
+
+        Ok(result)
+    }
+
+    //queues merged or imported activities which are pending their details,
+    //since they arent found when syncing the activity history. Should be
+    //called within a transaction
+    async fn queue_pending_activities(&mut self) -> Result<(), Error> {
+        let queued = sqlx::query(QUEUE_PENDING_ACTIVITIES_SQL)
+            .execute(&mut self.db)
+            .await?
+            .rows_affected();
+
+        if queued > 0 {
+            tell::verbose!("Queued {} pending activities", queued);
+        }
+
+        Ok(())
+    }
+
+    /// Removes data from the store based on the retention rules in options,
+    /// and then compacts the database file.
+    ///
+    /// Only data for players who are not being synced is removed, which
+    /// includes players who have been removed from syncing. Members and
+    /// characters left without any activities are also removed. Child rows
+    /// are removed via the ON DELETE CASCADE foreign keys.
+    pub async fn prune(
+        &mut self,
+        options: &PruneOptions,
+    ) -> Result<PruneResult, Error> {
+        let size_before = self.get_storage_size();
+
+        self.begin_transaction().await?;
+        let result = match self.delete_pruned_rows(options).await {
+            Ok(e) => e,
+            Err(e) => {
+                self.rollback_transaction().await?;
+                return Err(e);
+            }
+        };
+        self.commit_transaction().await?;
 
-            total_synced += a.total_synced + c.total_synced;
-            total_in_queue += (a.total_available + c.total_available)
-                - (a.total_synced + c.total_synced);
-        }
+        tell::progress!("Compacting data store. This may take a while.");
 
-        self.update_sync_entry(&member.id).await?;
+        //note, VACUUM cannot be run within a transaction
+        sqlx::query(
+            r#"
+            VACUUM;
+            ANALYZE;
+            PRAGMA wal_checkpoint(TRUNCATE);
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?;
 
-        Ok(SyncResult {
-            total_synced,
-            total_available: total_in_queue,
+        Ok(PruneResult {
+            size_before,
+            size_after: self.get_storage_size(),
+            ..result
         })
     }
 
-    /// download results from ids in queue, and return number of items synced
-    async fn sync_activities(
+    async fn delete_pruned_rows(
         &mut self,
-        character_id: &i64,
-    ) -> Result<SyncResult, Error> {
-        let mut ids: Vec<i64> = Vec::new();
+        options: &PruneOptions,
+    ) -> Result<PruneResult, Error> {
+        let mut result = PruneResult::default();
 
-        //This is to scope rows, so the mutable borrow of self goes out of scope
-        {
-            let mut rows = sqlx::query(
+        if let Some(before) = options.detail_before {
+            result.weapon_results = sqlx::query(
                 r#"
+                DELETE FROM weapon_result
+                WHERE character_activity_stats IN (
                     SELECT
-                        "activity_id"
+                        s.id
                     FROM
-                        "activity_queue"
+                        character_activity_stats as s
+                    INNER JOIN
+                        activity as a ON s.activity = a.activity_id
+                    INNER JOIN
+                        character as c ON s.character = c.character_id
                     WHERE
-                        character = ? AND synced = 0
-                "#,
+                        a.period < ? AND
+                        c.member NOT IN (
+                            SELECT linked FROM linked_member
+                            WHERE member IN (SELECT member FROM sync)
+                        )
+                )
+            "#,
             )
-            .bind(character_id)
-            .fetch(&mut self.db);
+            .bind(before.to_rfc3339())
+            .execute(&mut self.db)
+            .await?
+            .rows_affected();
 
-            //todo: could probably get this without looping through entire set
-            while let Some(row) = rows.try_next().await? {
-                let activity_id: i64 = row.try_get("activity_id")?;
-                ids.push(activity_id);
-            }
-        };
+            result.medal_results = sqlx::query(
+                r#"
+                DELETE FROM medal_result
+                WHERE character_activity_stats IN (
+                    SELECT
+                        s.id
+                    FROM
+                        character_activity_stats as s
+                    INNER JOIN
+                        activity as a ON s.activity = a.activity_id
+                    INNER JOIN
+                        character as c ON s.character = c.character_id
+                    WHERE
+                        a.period < ? AND
+                        c.member NOT IN (
+                            SELECT linked FROM linked_member
+                            WHERE member IN (SELECT member FROM sync)
+                        )
+                )
+            "#,
+            )
+            .bind(before.to_rfc3339())
+            .execute(&mut self.db)
+            .await?
+            .rows_affected();
+        }
 
-        if ids.is_empty() {
-            return Ok(SyncResult {
-                total_available: 0,
-                total_synced: 0,
-            });
+        if let Some(before) = options.activities_before {
+            //modes, team results and all player stats for the activities
+            //are removed via ON DELETE CASCADE
+            result.activities = sqlx::query(
+                r#"
+                DELETE FROM activity
+                WHERE
+                    period < ? AND
+                    activity_id NOT IN (
+                        SELECT
+                            s.activity
+                        FROM
+                            character_activity_stats as s
+                        INNER JOIN
+                            character as c ON s.character = c.character_id
+                        WHERE
+                            c.member IN (
+                                SELECT linked FROM linked_member
+                                WHERE member IN (SELECT member FROM sync)
+                            )
+                    )
+            "#,
+            )
+            .bind(before.to_rfc3339())
+            .execute(&mut self.db)
+            .await?
+            .rows_affected();
         }
 
-        let mut filtered_ids = Vec::new();
+        //characters and members of players who are not synced, and no longer
+        //have any activities in the store. Queued activities and combat
+        //ratings are removed via ON DELETE CASCADE
+        result.characters = sqlx::query(
+            r#"
+            DELETE FROM character
+            WHERE
+                character_id NOT IN (
+                    SELECT character FROM character_activity_stats
+                ) AND
+                member NOT IN (
+                    SELECT linked FROM linked_member
+                    WHERE member IN (SELECT member FROM sync)
+                )
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
 
-        //remove ids to sync for activities we have already synced
-        for id in ids {
-            if self.has_activity(&id).await {
-                self.remove_from_activity_queue(character_id, &id).await?;
-                continue;
-            } else {
-                filtered_ids.push(id)
+        //members with aliases or in subscribed groups are kept, since they
+        //were added explicitly
+        result.members = sqlx::query(
+            r#"
+            DELETE FROM member
+            WHERE
+                member_id NOT IN (SELECT member FROM character) AND
+                member_id NOT IN (
+                    SELECT linked FROM linked_member
+                    WHERE member IN (SELECT member FROM sync)
+                ) AND
+                member_id NOT IN (SELECT member FROM member_alias) AND
+                member_id NOT IN (SELECT member FROM group_member)
+        "#,
+        )
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        Ok(result)
+    }
+
+    /// Returns the size in bytes of the database file, including the
+    /// write ahead log.
+    pub fn get_storage_size(&self) -> u64 {
+        [self.path.clone(), format!("{}-wal", self.path)]
+            .iter()
+            .filter_map(|e| std::fs::metadata(e).ok())
+            .map(|e| e.len())
+            .sum()
+    }
+
+    /// Scans the store for data integrity issues, such as orphaned rows,
+    /// activities missing data and activities stuck in the sync queue.
+    ///
+    /// If a manifest is provided, weapon and medal ids are checked against
+    /// it. If fix is true, issues which can be fixed are fixed.
+    pub async fn check_integrity(
+        &mut self,
+        manifest: Option<&mut ManifestInterface>,
+        fix: bool,
+    ) -> Result<Vec<IntegrityIssue>, Error> {
+        let mut out: Vec<IntegrityIssue> = Vec::new();
+
+        if fix {
+            self.begin_transaction().await?;
+        } else {
+            self.begin_read_transaction().await?;
+        }
+        for check in integrity_checks().iter() {
+            match self.run_integrity_check(check, fix).await {
+                Ok(e) => out.push(e),
+                Err(e) => {
+                    self.rollback_transaction().await?;
+                    return Err(e);
+                }
             }
         }
-        ids = filtered_ids;
+        self.commit_transaction().await?;
 
-        let total_available = ids.len() as u32;
-        let mut total_synced = 0;
+        if let Some(manifest) = manifest {
+            out.append(&mut self.check_manifest_ids(manifest).await?);
+        }
 
-        use std::fmt::Write;
+        Ok(out)
+    }
 
-        let pb = if Tell::is_active(TellLevel::Progress) {
-            ProgressBar::new(ids.len() as u64)
-        } else {
-            ProgressBar::hidden()
-        };
+    async fn run_integrity_check(
+        &mut self,
+        check: &IntegrityCheck,
+        fix: bool,
+    ) -> Result<IntegrityIssue, Error> {
+        let row = sqlx::query(&format!(
+            "SELECT count(*) as count FROM ({})",
+            check.sql
+        ))
+        .fetch_one(&mut self.db)
+        .await?;
+        let count: i64 = row.try_get("count")?;
 
-        pb.set_style(ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ETA:({eta_precise})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
+        let rows = sqlx::query(&format!(
+            "SELECT CAST(item as TEXT) as item FROM ({}) LIMIT {}",
+            check.sql, INTEGRITY_EXAMPLE_COUNT
+        ))
+        .fetch_all(&mut self.db)
+        .await?;
 
-        let mut count: u64 = 0;
-        for id_chunks in ids.chunks(PGCR_REQUEST_CHUNK_AMOUNT) {
-            let mut f = Vec::new();
+        let mut examples: Vec<String> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            examples.push(row.try_get("item")?);
+        }
 
-            for c in id_chunks {
-                //this is saving the future, call hasnt been made yet
-                f.push(
-                    self.api_interface.retrieve_post_game_carnage_report(*c),
-                );
+        let mut fixed = false;
+        if fix && count > 0 {
+            if let Some(fix_sql) = &check.fix_sql {
+                sqlx::query(fix_sql).execute(&mut self.db).await?;
+                fixed = true;
             }
+        }
 
-            count += id_chunks.len() as u64;
-            pb.set_position(count);
-
-            //tell::progress!(".");
+        Ok(IntegrityIssue {
+            description: check.description.clone(),
+            count: count as u64,
+            examples,
+            fixable: check.fix_sql.is_some(),
+            fixed,
+        })
+    }
 
-            //TODO: look into using threading for this
-            let results = futures::future::join_all(f).await;
+    //checks that the weapon and medal ids in the store exist in the manifest
+    async fn check_manifest_ids(
+        &mut self,
+        manifest: &mut ManifestInterface,
+    ) -> Result<Vec<IntegrityIssue>, Error> {
+        let mut weapons: Vec<String> = Vec::new();
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT reference_id FROM weapon_result
+        "#,
+        )
+        .fetch_all(&mut self.db)
+        .await?;
 
-            //loop through. if we get results. grab those, otherwise, we ignore
-            //any errors, as that will keep the IDs in the queue to try next time
-            //TODO: this is a mess. can we simpify and not nest so deeply?
-            for r in results {
-                match r {
-                    Ok(e) => {
-                        match e {
-                            Some(mut e) => match self
-                                .insert_activity(&mut e, character_id)
-                                .await
-                            {
-                                Ok(_e) => {
-                                    total_synced += 1;
-                                }
-                                Err(e) => {
-                                    tell::error!(
-                                        "Error inserting data into character activity stats table. Skipping. : {}",
-                                        e,
-                                    );
-                                }
-                            },
-                            None => {
-                                tell::error!(
-                                    "PGCR returned empty response. Ignoring."
-                                );
-                                //TODO: should not get here, as none means either an API error
-                                //occured or there is no data associated with the ID (which is
-                                //an api data error).
-                                //we will just ignore it here, with the assumption that any error
-                                //is temporary, and will be fixed next time we sync
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        tell::error!(
-                            "Error retrieving activity details from api. Skipping.");
-                    }
-                }
+        for row in rows.iter() {
+            let id: i64 = row.try_get("reference_id")?;
+            if manifest
+                .get_iventory_item_definition(id as u32)
+                .await?
+                .is_none()
+            {
+                weapons.push(id.to_string());
             }
         }
 
-        pb.finish_and_clear();
+        let mut medals: Vec<String> = Vec::new();
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT CAST(reference_id as TEXT) as reference_id
+            FROM medal_result
+        "#,
+        )
+        .fetch_all(&mut self.db)
+        .await?;
 
-        if !ids.is_empty() {
-            sqlx::query("PRAGMA OPTIMIZE;")
-                .execute(&mut self.db)
-                .await?;
+        for row in rows.iter() {
+            let id: String = row.try_get("reference_id")?;
+            if manifest
+                .get_historical_stats_definition(&id)
+                .await?
+                .is_none()
+            {
+                medals.push(id);
+            }
         }
 
-        Ok(SyncResult {
-            total_available,
-            total_synced,
-        })
-    }
+        let mut out = Vec::new();
+        for (description, ids) in [
+            ("Weapon ids not found in manifest", weapons),
+            ("Medal ids not found in manifest", medals),
+        ] {
+            out.push(IntegrityIssue {
+                description: description.to_string(),
+                count: ids.len() as u64,
+                examples: ids
+                    .into_iter()
+                    .take(INTEGRITY_EXAMPLE_COUNT)
+                    .collect(),
+                fixable: false,
+                fixed: false,
+            });
+        }
 
-    async fn update_activity_queue(
-        &mut self,
-        member_id: &i64,
-        character_id: &i64,
-        platform: &Platform,
-    ) -> Result<SyncResult, Error> {
-        //TODO catch errors so we can continue?
-        let prv_result = self
-            ._update_activity_queue(
-                member_id,
-                character_id,
-                platform,
-                &Mode::PrivateMatchesAll,
-            )
-            .await?;
+        Ok(out)
+    }
 
-        let pub_result = self
-            ._update_activity_queue(
-                member_id,
-                character_id,
-                platform,
-                &Mode::AllPvP,
-            )
+    //transactions take the write lock when they begin, so concurrent syncs
+    //wait for each other, rather than failing when both try to write
+    async fn begin_transaction(&mut self) -> Result<(), Error> {
+        sqlx::query("BEGIN IMMEDIATE TRANSACTION;")
+            .execute(&mut self.db)
             .await?;
 
-        Ok(pub_result + prv_result)
+        Ok(())
     }
 
-    async fn begin_transaction(&mut self) -> Result<(), Error> {
-        sqlx::query("BEGIN TRANSACTION;")
+    //for reading a consistent snapshot. Doesnt take the write lock, so
+    //writers (i.e. a running sync) are not blocked while reading
+    async fn begin_read_transaction(&mut self) -> Result<(), Error> {
+        sqlx::query("BEGIN DEFERRED TRANSACTION;")
             .execute(&mut self.db)
             .await?;
 
@@ -689,14 +4582,37 @@ impl ActivityStoreInterface {
     }
 
     //updates activity id queue with ids which have not been synced
-    async fn _update_activity_queue(
+    //retrieves the activities for the mode which have not been queued yet.
+    //Returns whether the history was walked (i.e. on the first sync, or when
+    //backfilling), in which case the limit is applied. Otherwise, only
+    //activities newer than the most recent stored activity are returned
+    async fn retrieve_new_activities(
         &mut self,
         member_id: &i64,
         character_id: &i64,
         platform: &Platform,
         mode: &Mode,
-    ) -> Result<SyncResult, Error> {
-        let max_id: i64 = self.get_max_activity_id(character_id, mode).await?;
+        limit: &HistoryLimit,
+        backfill: bool,
+    ) -> Result<(Vec<Activity>, bool), Error> {
+        //when backfilling, we start from the most recent activity. Activities
+        //which are already queued are ignored below
+        let max_id: i64 = if backfill {
+            0
+        } else {
+            self.get_max_activity_id(character_id, mode).await?
+        };
+
+        //the max activities only applies when walking the history, so new
+        //activities are never skipped
+        let limit = if max_id == 0 {
+            *limit
+        } else {
+            HistoryLimit {
+                since: limit.since,
+                max_activities: None,
+            }
+        };
 
         let result = self
             .api_interface
@@ -706,21 +4622,32 @@ impl ActivityStoreInterface {
                 platform,
                 mode,
                 max_id,
+                &limit,
             )
             .await?;
 
-        if result.is_none() {
-            return Ok(SyncResult {
-                total_available: 0,
-                total_synced: 0,
-            });
-        }
-
-        let mut activities = result.unwrap();
-        //tell::progress!(format!("{} new activities found", activities.len()));
+        let activities = result
+            .unwrap_or_default()
+            .into_iter()
+            //gambit private matches. ignoring
+            .filter(|e| {
+                !GAMBIT_PRIVATE_MATCH_HASHES
+                    .contains(&e.details.director_activity_hash)
+            })
+            .collect();
+
+        Ok((activities, max_id == 0))
+    }
 
-        //reverse them so we add the oldest first
-        activities.reverse();
+    //queues the activities to have their details synced, and stores the data
+    //from the history so they can be queried until then
+    async fn queue_activities(
+        &mut self,
+        character_id: &i64,
+        mut activities: Vec<Activity>,
+    ) -> Result<SyncResult, Error> {
+        //sort them so we add the oldest first
+        activities.sort_by_key(|e| e.period);
 
         // TODO: think through this
         // Right now, we do all inserts in one transaction. This gives a significant performance
@@ -729,29 +4656,11 @@ impl ActivityStoreInterface {
         // come across some data that causes a bug inserting, then nothing would ever be inserted
         // (until we fixed the bug). Probably shouldnt be an issue, since any weird stuff with
         // api data should be caught by the json deserializer in apiinterface
-        sqlx::query("BEGIN TRANSACTION;")
-            .execute(&mut self.db)
-            .await?;
+        self.begin_transaction().await?;
 
         let mut total = 0;
 
         for activity in activities {
-            let director_activity_hash =
-                activity.details.director_activity_hash;
-
-            //these are DestinyActivityDefinition manifest hashes for gambit private
-            //matches
-            //TODO: can rewrite this to short circuit when first result found
-            //if !(director_activity_hash != 2526740498 && director_activity_hash != 248695599)
-
-            if director_activity_hash == 2526740498
-                || director_activity_hash == 248695599
-            {
-                //gambit private matches. ignoring
-
-                continue;
-            }
-
             total += 1;
 
             let instance_id = activity.details.instance_id;
@@ -778,6 +4687,15 @@ impl ActivityStoreInterface {
                     return Err(Error::from(e));
                 }
             };
+
+            //store what the history gives us, so the activity can be queried
+            //before its details have been synced
+            if let Err(e) =
+                self._insert_activity_index(activity, character_id).await
+            {
+                sqlx::query("ROLLBACK;").execute(&mut self.db).await?;
+                return Err(e);
+            }
         }
         sqlx::query("COMMIT;").execute(&mut self.db).await?;
 
@@ -787,6 +4705,105 @@ impl ActivityStoreInterface {
         })
     }
 
+    //stores the activity, and the character's stats for it, from the activity
+    //history. The activity is flagged as pending until the details (all
+    //players, weapons, medals) are synced from the PGCR, which replaces it
+    async fn _insert_activity_index(
+        &mut self,
+        activity: Activity,
+        character_id: &i64,
+    ) -> Result<(), Error> {
+        let values = activity.values;
+
+        //so the same mode fixes are applied as when the details are synced
+        let mut data = DestinyPostGameCarnageReportData {
+            activity_details: activity.details,
+            entries: Vec::new(),
+            period: activity.period,
+            teams: Vec::new(),
+        };
+        self.fix_pgcr_data(&mut data);
+
+        let activity_id = data.activity_details.instance_id;
+
+        //ignored if the activity has already been stored, either from the
+        //details or the history of another character
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO "main"."activity"
+                ("activity_id","period","mode","platform","director_activity_hash", "reference_id", "detail_pending")
+            VALUES (?,?,?,?,?,?,1)
+        "#,
+        )
+        .bind(activity_id)
+        .bind(data.period.to_rfc3339())
+        .bind(data.activity_details.mode.as_id().to_string())
+        .bind(data.activity_details.membership_type.as_id().to_string())
+        .bind(data.activity_details.director_activity_hash.to_string())
+        .bind(data.activity_details.reference_id.to_string())
+        .execute(&mut self.db)
+        .await?;
+
+        for mode in &data.activity_details.modes {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO "main"."modes"
+                (
+                    "mode", "activity"
+                )
+                VALUES(?,?)
+                "#,
+            )
+            .bind(mode.as_id().to_string())
+            .bind(activity_id)
+            .execute(&mut self.db)
+            .await?;
+        }
+
+        //the history doesnt include the extended stats, light level or
+        //emblem, so they are 0 until the details are synced
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO "main"."character_activity_stats"
+            (
+                "character", "assists", "score", "kills", "deaths",
+                "average_score_per_kill", "average_score_per_life", "completed",
+                "opponents_defeated", "activity_duration_seconds", "standing",
+                "team", "completion_reason", "start_seconds", "time_played_seconds",
+                "player_count", "team_score", "precision_kills", "weapon_kills_ability",
+                "weapon_kills_grenade", "weapon_kills_melee", "weapon_kills_super",
+                "all_medals_earned", "light_level", "activity", "fireteam_id", "emblem_hash"
+            )
+            VALUES (
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, 0, 0, 0,
+                0, 0, ?, ?, 0 )
+            "#,
+        )
+        .bind(character_id)
+        .bind(values.assists as i32)
+        .bind(values.score as i32)
+        .bind(values.kills as i32)
+        .bind(values.deaths as i32)
+        .bind(values.average_score_per_kill)
+        .bind(values.average_score_per_life)
+        .bind(values.completed as i32)
+        .bind(values.opponents_defeated as i32)
+        .bind(format!("{}", values.activity_duration_seconds as u32))
+        .bind(values.standing as i32)
+        .bind(values.team as i32)
+        .bind(values.completion_reason as i32)
+        .bind(values.start_seconds as i32)
+        .bind(values.time_played_seconds as i32)
+        .bind(values.player_count as i32)
+        .bind(values.team_score as i32)
+        .bind(activity_id)
+        .bind(values.fireteam_id.to_string())
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
     //todo: this doesnt need to be an instance fn, not sure if it matters
     fn get_medal_hash_value(
         &self,
@@ -804,9 +4821,7 @@ impl ActivityStoreInterface {
         data: &mut DestinyPostGameCarnageReportData,
         character_id: &i64,
     ) -> Result<(), Error> {
-        sqlx::query("BEGIN TRANSACTION;")
-            .execute(&mut self.db)
-            .await?;
+        self.begin_transaction().await?;
 
         match self._insert_activity(data, character_id).await {
             Ok(_e) => {
@@ -1099,6 +5114,26 @@ impl ActivityStoreInterface {
     ) -> Result<(), Error> {
         let activity_id = data.activity_details.instance_id;
 
+        //another member being synced at the same time may have already
+        //stored the activity
+        if self.has_activity_detail(&activity_id).await {
+            self.remove_from_activity_queue(character_id, &activity_id)
+                .await?;
+            return Ok(());
+        }
+
+        //remove the activity stored from the activity history. Its stats and
+        //modes are removed via ON DELETE CASCADE
+        sqlx::query(
+            r#"
+            DELETE FROM "main"."activity"
+            WHERE activity_id = ? AND detail_pending = 1
+        "#,
+        )
+        .bind(activity_id)
+        .execute(&mut self.db)
+        .await?;
+
         self.fix_pgcr_data(data);
 
         //throw an error if we try to insert and it already exists. That should never
@@ -1160,7 +5195,7 @@ impl ActivityStoreInterface {
         for entry in &data.entries {
             //todo: not sure if we should use membership type of crosssave override
             let member = &entry.player.user_info.to_member();
-            self.insert_member(member).await?;
+            self.insert_activity_member(member, &data.period).await?;
 
             let class_type = CharacterClass::from_hash(entry.player.class_hash);
 
@@ -1335,6 +5370,153 @@ impl ActivityStoreInterface {
         Ok(())
     }
 
+    //records a failed attempt to retrieve an activity. Activities which have
+    //failed max_activity_attempts times are moved to the failed list
+    async fn record_activity_failure(
+        &mut self,
+        character_id: &i64,
+        activity_id: &i64,
+        error: &str,
+    ) -> Result<(), Error> {
+        let row = sqlx::query(
+            r#"
+            UPDATE "main"."activity_queue"
+            SET
+                attempts = attempts + 1,
+                last_error = ?,
+                last_attempt = ?,
+                synced = CASE
+                    WHEN ? > 0 AND attempts + 1 >= ? THEN ?
+                    ELSE synced
+                END
+            WHERE character = ? and activity_id = ?
+            RETURNING attempts, synced
+        "#,
+        )
+        .bind(error)
+        .bind(Utc::now().to_rfc3339())
+        .bind(self.max_activity_attempts)
+        .bind(self.max_activity_attempts)
+        .bind(QUEUE_FAILED)
+        .bind(character_id)
+        .bind(activity_id)
+        .fetch_optional(&mut self.db)
+        .await?;
+
+        if let Some(row) = row {
+            let synced: i32 = row.try_get("synced")?;
+            let attempts: i32 = row.try_get("attempts")?;
+
+            if synced == QUEUE_FAILED {
+                tell::error!(
+                    "Activity {} failed {} times. Moving to failed list. It can be retried with dclisync --retry-failed.",
+                    activity_id,
+                    attempts
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns activities which have been moved to the failed list after
+    /// failing to be retrieved from the API too many times.
+    pub async fn retrieve_failed_activities(
+        &mut self,
+    ) -> Result<Vec<FailedActivity>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                activity_queue.activity_id, activity_queue.attempts,
+                activity_queue.last_error, activity_queue.last_attempt,
+                character.character_id, character.class,
+                "member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code"
+            FROM
+                activity_queue
+            INNER JOIN
+                character ON activity_queue.character = character.character_id
+            INNER JOIN
+                member ON character.member = member.member_id
+            WHERE
+                activity_queue.synced = ?
+            ORDER BY
+                activity_queue.activity_id DESC
+        "#,
+        )
+        .bind(QUEUE_FAILED)
+        .fetch_all(&mut self.db)
+        .await?;
+
+        let mut out: Vec<FailedActivity> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let member = self.parse_member_row(row)?;
+            let class_id: u32 = row.try_get("class")?;
+            let last_attempt: Option<String> = row.try_get("last_attempt")?;
+
+            out.push(FailedActivity {
+                activity_id: row.try_get("activity_id")?,
+                character_id: row.try_get("character_id")?,
+                character_class: CharacterClass::from_id(class_id),
+                member,
+                attempts: row.try_get("attempts")?,
+                last_error: row.try_get("last_error")?,
+                last_attempt: last_attempt.and_then(|e| {
+                    DateTime::parse_from_rfc3339(&e)
+                        .ok()
+                        .map(|e| e.with_timezone(&Utc))
+                }),
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Returns the number of activities in the sync queue which are waiting
+    /// to be synced, and the number which are on the failed list.
+    pub async fn get_activity_queue_depth(
+        &mut self,
+    ) -> Result<QueueDepth, Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COUNT(CASE WHEN synced = ? THEN 1 END) AS pending,
+                COUNT(CASE WHEN synced = ? THEN 1 END) AS failed
+            FROM
+                activity_queue
+        "#,
+        )
+        .bind(QUEUE_PENDING)
+        .bind(QUEUE_FAILED)
+        .fetch_one(&mut self.db)
+        .await?;
+
+        Ok(QueueDepth {
+            pending: row.try_get("pending")?,
+            failed: row.try_get("failed")?,
+        })
+    }
+
+    /// Moves all activities on the failed list back into the queue, so they
+    /// are retried the next time their characters are synced. Returns the
+    /// number of activities moved.
+    pub async fn retry_failed_activities(&mut self) -> Result<u64, Error> {
+        let count = sqlx::query(
+            r#"
+            UPDATE "main"."activity_queue"
+            SET synced = ?, attempts = 0
+            WHERE synced = ?
+        "#,
+        )
+        .bind(QUEUE_PENDING)
+        .bind(QUEUE_FAILED)
+        .execute(&mut self.db)
+        .await?
+        .rows_affected();
+
+        Ok(count)
+    }
+
     async fn remove_from_activity_queue(
         &mut self,
         character_id: &i64,
@@ -1355,10 +5537,13 @@ impl ActivityStoreInterface {
         Ok(())
     }
 
-    async fn has_activity(&mut self, activity_id: &i64) -> bool {
+    //whether the activity has been stored with its details, and not just from
+    //the activity history
+    async fn has_activity_detail(&mut self, activity_id: &i64) -> bool {
         let out = sqlx::query(
             r#"
-            SELECT activity_id FROM "activity" WHERE activity_id = ?
+            SELECT activity_id FROM "activity"
+            WHERE activity_id = ? AND detail_pending = 0
         "#,
         )
         .bind(activity_id)
@@ -1440,6 +5625,67 @@ impl ActivityStoreInterface {
         Ok(())
     }
 
+    //stores a member who played in an activity, and records their name in
+    //the name history. Since activities are not synced in order, the
+    //member's name is only updated if this is the most recent activity they
+    //have been seen in
+    async fn insert_activity_member(
+        &mut self,
+        member: &Member,
+        period: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (name, code) = match (
+            &member.name.bungie_display_name,
+            &member.name.bungie_display_name_code,
+        ) {
+            (Some(name), Some(code)) => (name, code),
+            _ => return self.insert_member(member).await,
+        };
+
+        let period = period.to_rfc3339();
+
+        let row = sqlx::query(
+            r#"
+            SELECT MAX(last_seen) AS last_seen FROM member_name WHERE member = ?
+        "#,
+        )
+        .bind(member.id)
+        .fetch_one(&mut self.db)
+        .await?;
+
+        let last_seen: Option<String> = row.try_get("last_seen")?;
+        let is_latest = match last_seen {
+            Some(e) => period >= e,
+            None => true,
+        };
+
+        if is_latest || !self.has_member(member).await {
+            self.insert_member(member).await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO "member_name"
+                ("member", "bungie_display_name", "bungie_display_name_code",
+                "first_seen", "last_seen")
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(member, bungie_display_name, bungie_display_name_code)
+            DO UPDATE SET
+                first_seen = min(first_seen, excluded.first_seen),
+                last_seen = max(last_seen, excluded.last_seen)
+        "#,
+        )
+        .bind(member.id)
+        .bind(name)
+        .bind(code)
+        .bind(&period)
+        .bind(&period)
+        .execute(&mut self.db)
+        .await?;
+
+        Ok(())
+    }
+
     async fn has_member(&mut self, member: &Member) -> bool {
         let out = sqlx::query(
             r#"
@@ -1555,7 +5801,8 @@ impl ActivityStoreInterface {
                 activity.mode as activity_mode,
                 activity.director_activity_hash,
                 activity.reference_id,
-                activity.platform
+                activity.platform,
+                activity.detail_pending
             FROM
                 activity
             INNER JOIN
@@ -1610,13 +5857,15 @@ impl ActivityStoreInterface {
                     activity.mode as activity_mode,
                     activity.director_activity_hash,
                     activity.reference_id,
-                    activity.platform
+                    activity.platform,
+                    activity.detail_pending
                 FROM
                     activity
                 INNER JOIN
                     character_activity_stats on character_activity_stats.activity = activity.activity_id,
                     character on character_activity_stats.character = character.character_id,
-                    member on character.member = member.member_id AND member.member_id = ?
+                    member on character.member = member.member_id AND member.member_id IN
+                        (SELECT linked FROM linked_member WHERE member = ?)
                 WHERE
                     exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
                     (character.class = ? OR 4 = ?) 
@@ -1783,7 +6032,8 @@ impl ActivityStoreInterface {
             character on character_activity_stats.character = character.character_id,
             member on member.member_id = character.member
         WHERE 
-            member.member_id = ?
+            member.member_id IN
+                (SELECT linked FROM linked_member WHERE member = ?)
         ORDER BY
             activity.period DESC
             limit 1
@@ -1823,6 +6073,8 @@ impl ActivityStoreInterface {
         character_selection: &CharacterClassSelection,
         mode: &Mode,
         time_period: &DateTimePeriod,
+        filter: &ActivityFilter,
+        window: &ActivityWindow,
     ) -> Result<Option<PlayerActivitiesSummary>, Error> {
         let restrict_mode_id = if mode.is_private() {
             -1
@@ -1839,6 +6091,9 @@ impl ActivityStoreInterface {
         SELECT
             count(*) as total_activities,
             COALESCE(sum(time_played_seconds),0) as time_played_seconds,
+            COALESCE(sum(activity_duration_seconds),0) as activity_duration_seconds,
+            COALESCE(sum(detail_pending = 1),0) as pending_activities,
+            COALESCE(sum(CASE WHEN detail_pending = 0 THEN time_played_seconds END),0) as detailed_time_played_seconds,
             COALESCE(sum(character_activity_stats.standing = 0),0) as wins,
             COALESCE(sum( character_activity_stats.completion_reason = 4),0) as completion_reason_mercy,
             COALESCE(sum(completed),0) as completed,
@@ -1886,19 +6141,30 @@ impl ActivityStoreInterface {
                     1) as real
                 )),0.0)
             as highest_efficiency
-        FROM
-            character_activity_stats
-        INNER JOIN
-            activity ON character_activity_stats.activity = activity.activity_id,
-            character on character_activity_stats.character = character.character_id,
-            member on member.member_id = character.member
-        WHERE
-            member.member_id = ? AND
-            (character.class = ? OR 4 = ?) AND
-            period > ? AND
-            period < ? AND
-            exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
-            not exists (select 1 from modes where activity = activity.activity_id and mode = ?)
+        FROM (
+            SELECT
+                character_activity_stats.*, activity.detail_pending
+            FROM
+                character_activity_stats
+            INNER JOIN
+                activity ON character_activity_stats.activity = activity.activity_id,
+                character on character_activity_stats.character = character.character_id,
+                member on member.member_id = character.member
+            WHERE
+                member.member_id IN
+                    (SELECT linked FROM linked_member WHERE member = ?) AND
+                (character.class = ? OR 4 = ?) AND
+                period > ? AND
+                period < ? AND
+                exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
+                not exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
+                (? OR character_activity_stats.start_seconds <= ?) AND
+                (? OR character_activity_stats.completed = 1) AND
+                (? OR character_activity_stats.completion_reason != 4)
+            ORDER BY
+                activity.period DESC
+            LIMIT ? OFFSET ?
+        ) AS character_activity_stats
         "#,
         )
         .bind(member.id)
@@ -1908,18 +6174,27 @@ impl ActivityStoreInterface {
         .bind(time_period.get_end().to_rfc3339())
         .bind(mode.as_id())
         .bind(restrict_mode_id)
+        .bind(filter.include_late_joins)
+        .bind(filter.late_join_threshold_seconds)
+        .bind(filter.include_left_early)
+        .bind(filter.include_mercy)
+        .bind(window.sql_limit())
+        .bind(window.skip)
         .fetch_one(&mut self.db)
         .await?;
 
         Ok(Some(summary))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn retrieve_activities_since(
         &mut self,
         member: &Member,
         character_selection: &CharacterClassSelection,
         mode: &Mode,
         time_period: &DateTimePeriod,
+        filter: &ActivityFilter,
+        window: &ActivityWindow,
         manifest: &mut ManifestInterface,
     ) -> Result<Option<Vec<CruciblePlayerActivityPerformance>>, Error> {
         //if mode if private, we dont restrict results
@@ -1949,14 +6224,19 @@ impl ActivityStoreInterface {
                 character on character_activity_stats.character = character.character_id,
                 member on member.member_id = character.member
             WHERE
-                member.member_id = ? AND
+                member.member_id IN
+                    (SELECT linked FROM linked_member WHERE member = ?) AND
                 (character.class = ? OR 4 = ?) AND
                 period > ? AND
                 period < ? AND
                 exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
-                not exists (select 1 from modes where activity = activity.activity_id and mode = ?)
+                not exists (select 1 from modes where activity = activity.activity_id and mode = ?) AND
+                (? OR character_activity_stats.start_seconds <= ?) AND
+                (? OR character_activity_stats.completed = 1) AND
+                (? OR character_activity_stats.completion_reason != 4)
             ORDER BY
                 activity.period DESC
+            LIMIT ? OFFSET ?
             "#,
         )
         .bind(member.id)
@@ -1966,6 +6246,12 @@ impl ActivityStoreInterface {
         .bind(time_period.get_end().to_rfc3339())
         .bind(mode.as_id().to_string())
         .bind(restrict_mode_id.to_string())
+        .bind(filter.include_late_joins)
+        .bind(filter.late_join_threshold_seconds)
+        .bind(filter.include_left_early)
+        .bind(filter.include_mercy)
+        .bind(window.sql_limit())
+        .bind(window.skip)
         .fetch_all(&mut self.db)
         .await?;
 
@@ -2022,6 +6308,8 @@ impl ActivityStoreInterface {
         let reference_id: u32 =
             activity_row.try_get_unchecked("reference_id")?;
 
+        let detail_pending: bool = activity_row.try_get("detail_pending")?;
+
         let activity_definition =
             manifest.get_activity_definition(reference_id).await?;
 
@@ -2038,6 +6326,7 @@ impl ActivityStoreInterface {
             platform: Platform::from_id(platform_id),
             director_activity_hash,
             reference_id,
+            detail_pending,
         };
 
         Ok(activity_detail)
@@ -2352,3 +6641,543 @@ impl std::ops::Add<SyncResult> for SyncResult {
         }
     }
 }
+
+/// A data integrity issue found in the store
+#[derive(Debug)]
+pub struct IntegrityIssue {
+    pub description: String,
+    pub count: u64,
+    pub examples: Vec<String>,
+
+    //whether the issue can be fixed by check_integrity
+    pub fixable: bool,
+    pub fixed: bool,
+}
+
+//a player in an activity being rated
+struct RatedPlayer {
+    member_id: i64,
+
+    //member_id of the player's linked identity, or their own if not linked
+    identity: i64,
+    team_id: i32,
+    standing: i32,
+    score: i32,
+}
+
+struct IntegrityCheck {
+    description: String,
+
+    //selects the problem rows, with an item column identifying each row
+    sql: String,
+    fix_sql: Option<String>,
+}
+
+//check for rows in table which reference rows which dont exist. Fixed by
+//removing the rows
+fn orphan_check(
+    description: &str,
+    table: &str,
+    item: &str,
+    condition: &str,
+) -> IntegrityCheck {
+    IntegrityCheck {
+        description: description.to_string(),
+        sql: format!(
+            "SELECT {} as item FROM {} WHERE {}",
+            item, table, condition
+        ),
+        fix_sql: Some(format!("DELETE FROM {} WHERE {}", table, condition)),
+    }
+}
+
+fn integrity_checks() -> Vec<IntegrityCheck> {
+    //activity ids increase over time, so if a newer activity for the
+    //character is older than the cut off, the queued activity is too
+    let stuck_before =
+        (Utc::now() - chrono::Duration::days(STUCK_QUEUE_DAYS)).to_rfc3339();
+
+    vec![
+        orphan_check(
+            "Characters without a member",
+            "character",
+            "character_id",
+            "member NOT IN (SELECT member_id FROM member)",
+        ),
+        orphan_check(
+            "Sync entries without a member",
+            "sync",
+            "member",
+            "member NOT IN (SELECT member_id FROM member)",
+        ),
+        orphan_check(
+            "Activity stats without an activity",
+            "character_activity_stats",
+            "id",
+            "activity NOT IN (SELECT activity_id FROM activity)",
+        ),
+        orphan_check(
+            "Activity stats without a character",
+            "character_activity_stats",
+            "id",
+            "character NOT IN (SELECT character_id FROM character)",
+        ),
+        orphan_check(
+            "Weapon results without activity stats",
+            "weapon_result",
+            "id",
+            "character_activity_stats NOT IN (SELECT id FROM character_activity_stats)",
+        ),
+        orphan_check(
+            "Medal results without activity stats",
+            "medal_result",
+            "id",
+            "character_activity_stats NOT IN (SELECT id FROM character_activity_stats)",
+        ),
+        orphan_check(
+            "Modes without an activity",
+            "modes",
+            "id",
+            "activity NOT IN (SELECT activity_id FROM activity)",
+        ),
+        orphan_check(
+            "Team results without an activity",
+            "team_result",
+            "id",
+            "activity NOT IN (SELECT activity_id FROM activity)",
+        ),
+        orphan_check(
+            "Activity queue entries without a character",
+            "activity_queue",
+            "id",
+            "character NOT IN (SELECT character_id FROM character)",
+        ),
+        IntegrityCheck {
+            description: "Activities without modes".to_string(),
+            sql: r#"
+                SELECT
+                    activity_id as item
+                FROM
+                    activity
+                WHERE
+                    activity_id NOT IN (SELECT activity FROM modes)
+            "#
+            .to_string(),
+            fix_sql: Some(
+                r#"
+                INSERT OR IGNORE INTO modes ("mode", "activity")
+                SELECT
+                    mode, activity_id
+                FROM
+                    activity
+                WHERE
+                    activity_id NOT IN (SELECT activity FROM modes)
+            "#
+                .to_string(),
+            ),
+        },
+        //activities where players were assigned to teams, but no team
+        //results were stored. Free for all modes dont assign teams. These
+        //are displayed with all players on a single team (NO_TEAMS_INDEX).
+        //Not fixed, since the activity may not be able to be synced again
+        IntegrityCheck {
+            description: "Team mode activities without team results"
+                .to_string(),
+            sql: format!(
+                r#"
+                SELECT
+                    activity_id as item
+                FROM
+                    activity
+                WHERE
+                    detail_pending = 0 AND
+                    activity_id NOT IN (SELECT activity FROM team_result) AND
+                    activity_id IN (
+                        SELECT activity FROM character_activity_stats
+                        WHERE team NOT IN (0, {})
+                    )
+            "#,
+                NO_TEAMS_INDEX
+            ),
+            fix_sql: None,
+        },
+        IntegrityCheck {
+            description: format!(
+                "Activity queue entries not synced for {}+ days",
+                STUCK_QUEUE_DAYS
+            ),
+            sql: format!(
+                r#"
+                SELECT
+                    q.activity_id as item
+                FROM
+                    activity_queue as q
+                WHERE
+                    q.synced = 0 AND
+                    EXISTS (
+                        SELECT
+                            1
+                        FROM
+                            character_activity_stats as s
+                        INNER JOIN
+                            activity as a ON s.activity = a.activity_id
+                        WHERE
+                            s.character = q.character AND
+                            a.activity_id > q.activity_id AND
+                            a.period < '{}'
+                    )
+            "#,
+                stuck_before
+            ),
+            fix_sql: None,
+        },
+        //these are never replaced with their details (i.e. when merged from
+        //another store). Fixed by queuing them to be synced, which is only
+        //possible if a synced player played in them
+        IntegrityCheck {
+            description: "Pending activities which are not queued".to_string(),
+            sql: r#"
+                SELECT
+                    activity_id as item
+                FROM
+                    activity
+                WHERE
+                    detail_pending = 1 AND
+                    activity_id NOT IN (SELECT activity_id FROM activity_queue)
+            "#
+            .to_string(),
+            fix_sql: Some(QUEUE_PENDING_ACTIVITIES_SQL.to_string()),
+        },
+    ]
+}
+
+/// A Destiny 2 group / clan whose roster is reconciled with the players
+/// being synced
+#[derive(Debug)]
+pub struct GroupSubscription {
+    pub group_id: u32,
+    pub remove_departed: bool,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub members: u32,
+}
+
+/// Changes to a group roster since it was last reconciled. Departed players
+/// are still synced, removed players are no longer synced.
+#[derive(Debug, Default)]
+pub struct RosterChanges {
+    pub added: Vec<Member>,
+    pub departed: Vec<Member>,
+    pub removed: Vec<Member>,
+}
+
+/// Number of activities in the sync queue
+#[derive(Debug, Default)]
+pub struct QueueDepth {
+    pub pending: u32,
+    pub failed: u32,
+}
+
+/// Sync status of a member flagged to be synced
+#[derive(Debug)]
+pub struct SyncStatus {
+    pub member: Member,
+    pub history_limit: HistoryLimit,
+    pub characters: u32,
+    pub activities: u32,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub queued: u32,
+    pub failed: u32,
+}
+
+//the phases members are synced in, when syncing multiple members
+#[derive(Debug, Clone, Copy)]
+enum SyncPhase {
+    History,
+    Details,
+}
+
+/// Result of verifying a member's stored activities against their activity
+/// history
+#[derive(Debug, Default)]
+pub struct HistoryVerification {
+    //activities in the history within the period
+    pub checked: u32,
+
+    //activities which were not stored, and have been queued
+    pub missing: u32,
+
+    //character histories which could not be retrieved
+    pub failed: u32,
+}
+
+/// A combat rating retrieved from the API, and when it was retrieved
+#[derive(Debug, Clone, Copy)]
+pub struct CombatRating {
+    pub rating: f32,
+    pub fetched: DateTime<Utc>,
+}
+
+/// A player's skill rating after a rated activity
+#[derive(Debug, Clone, Copy)]
+pub struct SkillRatingEntry {
+    pub activity_id: i64,
+    pub period: DateTime<Utc>,
+    pub family: ModeFamily,
+
+    /// Rating and deviation going into the activity, with the deviation
+    /// increased for the time since the player last played
+    pub pre_rating: f64,
+    pub pre_deviation: f64,
+
+    pub rating: SkillRating,
+}
+
+/// Result of syncing a member as part of syncing multiple members
+#[derive(Debug)]
+pub struct MemberSyncResult {
+    pub member: Member,
+    pub result: Result<SyncResult, Error>,
+    pub duration: std::time::Duration,
+}
+
+/// A player in the data store, whether they (or a linked membership) are
+/// being synced, and the period of their most recent stored activity
+#[derive(Debug)]
+pub struct StoredMember {
+    pub member: Member,
+    pub synced: bool,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+/// A local alias which can be used in place of a member's Bungie name
+#[derive(Debug)]
+pub struct MemberAlias {
+    pub alias: String,
+    pub member: Member,
+}
+
+/// A Bungie name a member has been seen with, and the periods of the first
+/// and last synced activities they were seen with it
+#[derive(Debug)]
+pub struct NameHistoryEntry {
+    pub name: PlayerName,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// An activity which could not be retrieved from the API, and is no longer
+/// retried when syncing
+#[derive(Debug)]
+pub struct FailedActivity {
+    pub activity_id: i64,
+    pub character_id: i64,
+    pub character_class: CharacterClass,
+    pub member: Member,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_attempt: Option<DateTime<Utc>>,
+}
+
+/// Retention rules for pruning the store. Rules which are None are not
+/// applied.
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+    /// Remove weapon and medal results for players who are not synced from
+    /// activities before this time
+    pub detail_before: Option<DateTime<Utc>>,
+
+    /// Remove activities before this time which no synced players played in.
+    /// This covers activities of players who have been removed from syncing,
+    /// as well as those of players who were never synced (i.e. merged from
+    /// another store).
+    pub activities_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+pub struct PruneResult {
+    pub weapon_results: u64,
+    pub medal_results: u64,
+    pub activities: u64,
+    pub characters: u64,
+    pub members: u64,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Counts of the rows merged, imported or exported between stores
+#[derive(Debug, Default)]
+pub struct TransferResult {
+    pub members: u64,
+    pub member_links: u64,
+    pub member_names: u64,
+    pub characters: u64,
+    pub activities: u64,
+    pub modes: u64,
+    pub team_results: u64,
+    pub character_activity_stats: u64,
+    pub weapon_results: u64,
+    pub medal_results: u64,
+    pub sync_entries: u64,
+    pub conflicts: Vec<String>,
+}
+
+impl TransferResult {
+    //increments the count for the archive record type
+    fn increment(&mut self, record_type: &str, count: u64) {
+        let field = match record_type {
+            "member" => &mut self.members,
+            "member_link" => &mut self.member_links,
+            "member_name" => &mut self.member_names,
+            "character" => &mut self.characters,
+            "activity" => &mut self.activities,
+            "mode" => &mut self.modes,
+            "team_result" => &mut self.team_results,
+            "character_activity_stats" => &mut self.character_activity_stats,
+            "weapon_result" => &mut self.weapon_results,
+            "medal_result" => &mut self.medal_results,
+            "sync" => &mut self.sync_entries,
+            _ => return,
+        };
+
+        *field += count;
+    }
+}
+
+//parses a history limit from the since and max activities columns of row
+fn parse_history_limit(
+    row: &sqlx::sqlite::SqliteRow,
+    since_column: &str,
+    max_column: &str,
+) -> Result<HistoryLimit, Error> {
+    let since: Option<String> = row.try_get(since_column)?;
+    let max_activities: Option<u32> = row.try_get(max_column)?;
+
+    Ok(HistoryLimit {
+        since: since.and_then(|e| {
+            DateTime::parse_from_rfc3339(&e)
+                .ok()
+                .map(|e| e.with_timezone(&Utc))
+        }),
+        max_activities,
+    })
+}
+
+//parses a combat rating from the rating and fetched columns of row
+fn parse_combat_rating_row(
+    row: &sqlx::sqlite::SqliteRow,
+) -> Result<CombatRating, Error> {
+    let fetched: String = row.try_get("fetched")?;
+
+    Ok(CombatRating {
+        rating: row.try_get("rating")?,
+        fetched: DateTime::parse_from_rfc3339(&fetched)?.with_timezone(&Utc),
+    })
+}
+
+//returns the mode family of the activity from its mode and modes columns
+fn parse_mode_family(
+    row: &sqlx::sqlite::SqliteRow,
+) -> Result<Option<ModeFamily>, Error> {
+    let mode: u32 = row.try_get_unchecked("mode")?;
+    let modes: Option<String> = row.try_get("modes")?;
+
+    let mut out = vec![Mode::from_id(mode)?];
+    for id in modes.as_deref().unwrap_or_default().split(',') {
+        if let Ok(e) = id.parse::<u32>() {
+            out.push(Mode::from_id(e)?);
+        }
+    }
+
+    Ok(ModeFamily::from_modes(&out))
+}
+
+//parses a skill rating entry from a row of the skill_rating table
+fn parse_skill_rating_row(
+    row: &sqlx::sqlite::SqliteRow,
+) -> Result<SkillRatingEntry, Error> {
+    let period: String = row.try_get("period")?;
+    let family: u32 = row.try_get_unchecked("family")?;
+
+    Ok(SkillRatingEntry {
+        activity_id: row.try_get("activity")?,
+        period: DateTime::parse_from_rfc3339(&period)?.with_timezone(&Utc),
+        family: ModeFamily::from_id(family)?,
+        pre_rating: row.try_get("pre_rating")?,
+        pre_deviation: row.try_get("pre_deviation")?,
+        rating: SkillRating {
+            rating: row.try_get("rating")?,
+            deviation: row.try_get("deviation")?,
+            volatility: row.try_get("volatility")?,
+        },
+    })
+}
+
+//opens a connection to the store database at path
+async fn connect(path: &str) -> Result<SqliteConnection, Error> {
+    let read_only = false;
+
+    //TODO: Is this still the correct / best journal mode for us?
+    let db = SqliteConnectOptions::from_str(path)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .create_if_missing(true)
+        .read_only(read_only)
+        .busy_timeout(std::time::Duration::from_secs(
+            STORE_BUSY_TIMEOUT_SECONDS,
+        ))
+        .connect()
+        .await?;
+
+    Ok(db)
+}
+
+//LIKE pattern matching values which start with prefix, with the wildcards
+//in prefix escaped (use with ESCAPE '\')
+fn like_prefix_pattern(prefix: &str) -> String {
+    format!(
+        "{}%",
+        prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
+
+fn sqlite_value_to_json(
+    row: &sqlx::sqlite::SqliteRow,
+    index: usize,
+) -> Result<serde_json::Value, Error> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok(serde_json::Value::Null);
+    }
+
+    let value = match raw.type_info().name() {
+        "INTEGER" => serde_json::Value::from(row.try_get::<i64, _>(index)?),
+        "REAL" => serde_json::Value::from(row.try_get::<f64, _>(index)?),
+        _ => serde_json::Value::from(row.try_get::<String, _>(index)?),
+    };
+
+    Ok(value)
+}
+
+//binds the fields of an archive record to query, in the order of fields
+fn bind_archive_record<'q>(
+    mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    record: &serde_json::Map<String, serde_json::Value>,
+    fields: &[&str],
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    for field in fields.iter() {
+        query = match record.get(*field) {
+            Some(serde_json::Value::Number(e)) if e.is_f64() => {
+                query.bind(e.as_f64())
+            }
+            Some(serde_json::Value::Number(e)) => query.bind(e.as_i64()),
+            Some(serde_json::Value::String(e)) => query.bind(e.to_string()),
+            Some(serde_json::Value::Bool(e)) => query.bind(*e),
+            _ => query.bind(Option::<i64>::None),
+        };
+    }
+
+    query
+}
@@ -21,12 +21,15 @@
 */
 
 use log::{debug, info};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tell::{Tell, TellLevel};
 
 use reqwest::header::{HeaderMap, HeaderValue, CONNECTION};
 use reqwest::{Client, Url};
 
 use crate::error::Error;
+use crate::metrics;
 use crate::response::drs::{
     check_destiny_response_status, IsDestinyAPIResponse,
 };
@@ -37,8 +40,43 @@ const API_TIMEOUT: u64 = 10; //seconds
 //this makes sure that the env variable isnt set, but empty
 static_assertions::const_assert!(!DESTINY_API_KEY.is_empty());
 
+/// Limits the rate of requests made to the Bungie API. Clones share the same
+/// budget, so a single budget can be shared by all of the clients making
+/// requests at the same time.
+#[derive(Clone, Debug)]
+pub struct RequestBudget {
+    interval: Duration,
+    next: Arc<Mutex<Instant>>,
+}
+
+impl RequestBudget {
+    pub fn new(requests_per_second: u32) -> RequestBudget {
+        RequestBudget {
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    //waits until the next request can be made within the budget
+    async fn acquire(&self) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next).max(now);
+            *next = slot + self.interval;
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct ApiClient {
     client: Client,
+    budget: Option<RequestBudget>,
 }
 
 impl ApiClient {
@@ -61,7 +99,15 @@ impl ApiClient {
             .timeout(std::time::Duration::from_secs(API_TIMEOUT))
             .build()?;
 
-        Ok(ApiClient { client })
+        Ok(ApiClient {
+            client,
+            budget: None,
+        })
+    }
+
+    /// Sets the budget which limits the rate of requests made by the client
+    pub fn set_request_budget(&mut self, budget: RequestBudget) {
+        self.budget = Some(budget);
     }
 
     pub async fn call(&self, url: &str) -> Result<reqwest::Response, Error> {
@@ -71,12 +117,22 @@ impl ApiClient {
         tell::verbose!("{}", url);
         info!("API call : {}", url);
 
+        if let Some(budget) = &self.budget {
+            budget.acquire().await;
+        }
+
+        let path = url.path().to_string();
+        let start = Instant::now();
         let response = self
             .client
             .get(url)
             //.header("X-API-Key", DESTINY_API_KEY)
             .send()
-            .await?; //this either returns a reqwest::Response for an Error which is returned
+            .await;
+        record_request(&path, &response, start);
+
+        //this either returns a reqwest::Response for an Error which is returned
+        let response = response?;
 
         Ok(response)
     }
@@ -130,12 +186,22 @@ impl ApiClient {
         info!("Calling API [post] : {}", url);
         tell::verbose!("{}", url);
 
+        if let Some(budget) = &self.budget {
+            budget.acquire().await;
+        }
+
+        let path = url.path().to_string();
+        let start = Instant::now();
         let response = self
             .client
             .post(url)
             .body(post_data.to_string())
             .send()
-            .await?; //this either returns a reqwest::Response for an Error which is returned
+            .await;
+        record_request(&path, &response, start);
+
+        //this either returns a reqwest::Response for an Error which is returned
+        let response = response?;
 
         Ok(response)
     }
@@ -177,3 +243,18 @@ impl ApiClient {
         Ok(r)
     }
 }
+
+//records the endpoint, status and latency of an api request for metrics
+fn record_request(
+    path: &str,
+    response: &Result<reqwest::Response, reqwest::Error>,
+    start: Instant,
+) {
+    let status = match response {
+        Ok(e) => e.status().as_u16().to_string(),
+        Err(e) if e.is_timeout() => Error::RequestTimedOut.name().to_string(),
+        Err(_) => Error::Request.name().to_string(),
+    };
+
+    metrics::record_api_request(path, &status, start.elapsed());
+}
@@ -35,7 +35,8 @@ use crate::response::{gpr::{CharacterActivitiesData, GetProfileResponse}, cr::Ge
 use crate::response::pgcr::{DestinyPostGameCarnageReportData, PGCRResponse};
 use crate::response::sdpr::{
     DestinyLinkedProfilesResponse, LinkedProfilesResponse,
-    SearchDestinyPlayerResponse,
+    SearchByGlobalNamePrefixResponse, SearchDestinyPlayerResponse,
+    UserSearchPrefixPostData, UserSearchResponse,
 };
 use crate::response::stats::{
     AllTimePvPStatsResponse, DailyPvPStatsResponse, DailyPvPStatsValuesData,
@@ -51,13 +52,21 @@ use crate::response::{
     gmd::UserMembershipData,
 };
 use crate::utils::Period;
-use crate::{apiclient::ApiClient, crucible::Player};
+use crate::{
+    apiclient::{ApiClient, RequestBudget},
+    crucible::Player,
+};
 use crate::{
     apiutils::{API_BASE_URL, PGCR_BASE_URL},
     character::PlayerInfo,
 };
 use crate::{crucible::Member, response::drs::API_RESPONSE_STATUS_SUCCESS};
-use crate::{crucible::PlayerName, error::Error};
+use crate::{
+    crucible::{
+        HistoryLimit, PlayerName, PlayerSearchResult, PlayerSearchResults,
+    },
+    error::Error,
+};
 use crate::{enums::mode::Mode, response::pgcr::UserInfoCard};
 use crate::{
     enums::platform::Platform, response::pgcr::DestinyProfileUserInfoCard,
@@ -65,6 +74,10 @@ use crate::{
 
 use crate::character::Characters;
 
+//number of player profiles retrieved at the same time when searching
+const SEARCH_PROFILE_CHUNK_AMOUNT: usize = 10;
+
+#[derive(Clone)]
 pub struct ApiInterface {
     client: ApiClient,
 }
@@ -83,6 +96,12 @@ impl ApiInterface {
         //some methods may require it and will throw errors if its not set
     }
 
+    /// Sets the budget which limits the rate of requests made to the API.
+    /// Interfaces sharing a budget share the same limit.
+    pub fn set_request_budget(&mut self, budget: RequestBudget) {
+        self.client.set_request_budget(budget);
+    }
+
     pub async fn retrieve_group_members(
         &self,
         group_id: u32,
@@ -284,6 +303,124 @@ impl ApiInterface {
         Ok(out)
     }
 
+    /// Retrieves a page of players whose Bungie name starts with prefix
+    /// (case insensitive). Pages start at 0.
+    pub async fn retrieve_players_by_prefix(
+        &self,
+        prefix: &str,
+        page: u32,
+    ) -> Result<UserSearchResponse, Error> {
+        let url = format!(
+            "{base}/Platform/User/Search/GlobalName/{page}/",
+            base = API_BASE_URL,
+            page = page
+        );
+
+        let post_data = UserSearchPrefixPostData {
+            display_name_prefix: prefix.to_string(),
+        };
+
+        let post_data_json = post_data.to_json()?;
+
+        let r: SearchByGlobalNamePrefixResponse = self
+            .client
+            .call_post_and_parse::<SearchByGlobalNamePrefixResponse>(
+                &url,
+                &post_data_json,
+            )
+            .await?;
+
+        let response = match r.response {
+            Some(e) => e,
+            None => {
+                return Err(Error::ApiRequest {
+                    description: String::from(
+                        "No response data from API Call.",
+                    ),
+                })
+            }
+        };
+
+        Ok(response)
+    }
+
+    /// Searches for players whose Bungie name starts with prefix, retrieving
+    /// up to max_pages pages of results, along with when each player last
+    /// played. Players without Destiny memberships are not included.
+    pub async fn search_players_by_prefix(
+        &self,
+        prefix: &str,
+        max_pages: u32,
+    ) -> Result<PlayerSearchResults, Error> {
+        let mut out = PlayerSearchResults::default();
+
+        for page in 0..max_pages {
+            let response =
+                self.retrieve_players_by_prefix(prefix, page).await?;
+
+            for detail in response.search_results.iter() {
+                //use the cross save primary membership if there is one
+                let card = match detail
+                    .destiny_memberships
+                    .iter()
+                    .find(|e| {
+                        e.cross_save_override != Platform::Unknown
+                            && e.cross_save_override == e.membership_type
+                    })
+                    .or_else(|| detail.destiny_memberships.first())
+                {
+                    Some(e) => e,
+                    None => continue,
+                };
+
+                let mut member = card.to_member();
+                if member.name.bungie_display_name.is_none() {
+                    member.name.bungie_display_name =
+                        detail.bungie_display_name.clone();
+                }
+
+                if member.name.bungie_display_name_code.is_none() {
+                    member.name.bungie_display_name_code = detail
+                        .bungie_display_name_code
+                        .map(PlayerName::format_bungie_display_name_code);
+                }
+
+                out.players.push(PlayerSearchResult {
+                    member,
+                    platforms: detail
+                        .destiny_memberships
+                        .iter()
+                        .map(|e| e.membership_type)
+                        .collect(),
+                    last_played: None,
+                });
+            }
+
+            out.has_more = response.has_more;
+            if !response.has_more {
+                break;
+            }
+        }
+
+        //last played is only available from the profiles, so we retrieve
+        //them a chunk at a time. If a call fails, we just dont show the date
+        for chunk in out.players.chunks_mut(SEARCH_PROFILE_CHUNK_AMOUNT) {
+            let f = chunk.iter().map(|e| {
+                self.retrieve_linked_profiles(&e.member.id, &e.member.platform)
+            });
+            let results = futures::future::join_all(f).await;
+
+            for (player, r) in chunk.iter_mut().zip(results) {
+                if let Ok(e) = r {
+                    player.last_played =
+                        e.profiles.iter().map(|e| e.date_last_played).max();
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
     //todo: this could take a UserInfoCard. Might be more flexible as it though
     pub async fn retrieve_linked_profiles(
         &self,
@@ -662,6 +799,9 @@ impl ApiInterface {
         Ok(Some(out))
     }
 
+    /// Retrieves the activities for the mode newer than activity_id (or all
+    /// activities if activity_id is not found), up to the history limit,
+    /// newest first. The limit only applies to the specified mode.
     pub async fn retrieve_activities_since_id(
         &self,
         member_id: &i64,
@@ -669,6 +809,7 @@ impl ApiInterface {
         platform: &Platform,
         mode: &Mode,
         activity_id: i64,
+        limit: &HistoryLimit,
     ) -> Result<Option<Vec<Activity>>, Error> {
         let mut out: Vec<Activity> = Vec::new();
         let mut page = 0;
@@ -733,7 +874,20 @@ impl ApiInterface {
                     break;
                 }
 
+                //activities are returned newest first, so we can stop once
+                //we are past the limit
+                if matches!(limit.since, Some(e) if activity.period < e) {
+                    should_break = true;
+                    break;
+                }
+
                 out.push(activity);
+
+                if matches!(limit.max_activities, Some(e) if out.len() >= e as usize)
+                {
+                    should_break = true;
+                    break;
+                }
             }
 
             if should_break || len < count {
@@ -0,0 +1,420 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//Loads default command line options from a shared dcli.toml config file.
+//
+//Config files are read from the system config directory (i.e.
+//~/.config/dcli/dcli.toml) and the dcli data directory, with values in the
+//data directory overriding those in the config directory. Example:
+//
+//  [default]
+//  name = "mesh#3230"
+//  mode = "all_pvp"
+//
+//  [dcliah]
+//  moment = "weekly"
+//
+//  [profile.trials]
+//  mode = "trials_of_osiris"
+//  moment = "weekend"
+//  class = "hunter"
+//
+//Keys are the long names of command line options. Options specified on the
+//command line always take precedence, followed by the profile selected with
+//--profile, the tool specific section and then the default section.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use serde_derive::Deserialize;
+use structopt::clap::{App, ErrorKind};
+use structopt::StructOpt;
+
+use crate::error::Error;
+use crate::utils::default_data_dir;
+
+pub const CONFIG_FILE_NAME: &str = "dcli.toml";
+const PROFILE_ARG: &str = "profile";
+const DATA_DIR_ARG: &str = "data-dir";
+
+//options which can also be set via an environment variable. Options set via
+//their environment variable are not loaded from the config file.
+const OPTION_ENV_VARS: &[(&str, &str)] = &[("api-key", "DESTINY_API_KEY")];
+
+type ConfigSection = HashMap<String, toml::Value>;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub default: ConfigSection,
+
+    #[serde(default)]
+    pub profile: HashMap<String, ConfigSection>,
+
+    //sections for individual tools, i.e. [dcliah]
+    #[serde(flatten)]
+    pub tools: HashMap<String, ConfigSection>,
+}
+
+impl Config {
+    pub fn from_path(path: &Path) -> Result<Config, Error> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&contents)?;
+
+        Ok(config)
+    }
+
+    /// Returns the merged options for the tool, with profile values
+    /// overriding tool values, which override default values.
+    pub fn get_options(
+        &self,
+        tool: &str,
+        profile: Option<&str>,
+    ) -> Result<ConfigSection, Error> {
+        let mut out = self.default.clone();
+
+        if let Some(e) = self.tools.get(tool) {
+            out.extend(e.clone());
+        }
+
+        if let Some(name) = profile {
+            match self.profile.get(name) {
+                Some(e) => out.extend(e.clone()),
+                None => {
+                    return Err(Error::Config {
+                        description: format!("Profile not found : {}", name),
+                    })
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn merge(&mut self, other: Config) {
+        self.default.extend(other.default);
+
+        for (name, section) in other.profile {
+            self.profile.entry(name).or_default().extend(section);
+        }
+
+        for (name, section) in other.tools {
+            self.tools.entry(name).or_default().extend(section);
+        }
+    }
+}
+
+/// Loads and merges config files from the system config directory and the
+/// data directory.
+pub fn load_config(data_dir: Option<PathBuf>) -> Result<Config, Error> {
+    let mut config = match dirs_next::config_dir() {
+        Some(e) => Config::from_path(&e.join("dcli").join(CONFIG_FILE_NAME))?,
+        None => Config::default(),
+    };
+
+    //data dir can be set in the system config file
+    let data_dir = match data_dir {
+        Some(e) => Some(e),
+        None => config
+            .default
+            .get("data-dir")
+            .and_then(|e| e.as_str())
+            .map(PathBuf::from),
+    };
+
+    //dont create the data dir here, since this runs before arguments
+    //(i.e. --help) are handled
+    let data_dir = match data_dir {
+        Some(e) => e,
+        None => default_data_dir()?,
+    };
+    config.merge(Config::from_path(&data_dir.join(CONFIG_FILE_NAME))?);
+
+    Ok(config)
+}
+
+/// Returns the command line arguments for the process, with any options
+/// from the config file that were not specified on the command line
+/// appended.
+///
+/// options contains the long name and optional short name of each option the
+/// tool supports loading from the config file. Boolean values are treated as
+/// flags, and arrays as multiple values. Options which are set via an
+/// environment variable are not loaded from the config file.
+///
+/// If the config file cannot be loaded, an error is printed and the command
+/// line arguments are returned unchanged.
+pub fn args_with_config<T: StructOpt>(
+    tool: &str,
+    options: &[(&str, Option<char>)],
+) -> Vec<OsString> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    match append_config_args::<T>(tool, options, args.clone()) {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!("Could not load config file. Ignoring it. {}", e);
+            args
+        }
+    }
+}
+
+fn append_config_args<T: StructOpt>(
+    tool: &str,
+    options: &[(&str, Option<char>)],
+    mut args: Vec<OsString>,
+) -> Result<Vec<OsString>, Error> {
+    let specified = parse_command_line(&T::clap(), &args);
+
+    let data_dir =
+        find_arg_value(&specified, DATA_DIR_ARG, Some('D')).map(PathBuf::from);
+    let profile = find_arg_value(&specified, PROFILE_ARG, None);
+
+    let config = load_config(data_dir)?;
+    let config_options = config.get_options(tool, profile.as_deref())?;
+
+    for (long, short) in options {
+        let value = match config_options.get(*long) {
+            Some(e) => e,
+            None => continue,
+        };
+
+        if has_arg(&specified, long, *short) || has_env_var(long) {
+            continue;
+        }
+
+        let flag = OsString::from(format!("--{}", long));
+        match value {
+            toml::Value::Boolean(true) => args.push(flag),
+            toml::Value::Boolean(false) => (),
+            toml::Value::Array(e) => {
+                args.push(flag);
+                for v in e {
+                    args.push(OsString::from(value_to_string(v)?));
+                }
+            }
+            _ => {
+                args.push(flag);
+                args.push(OsString::from(value_to_string(value)?));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+fn value_to_string(value: &toml::Value) -> Result<String, Error> {
+    match value {
+        toml::Value::String(e) => Ok(e.to_string()),
+        toml::Value::Integer(e) => Ok(e.to_string()),
+        toml::Value::Float(e) => Ok(e.to_string()),
+        toml::Value::Boolean(e) => Ok(e.to_string()),
+        _ => Err(Error::Config {
+            description: format!("Unsupported value : {}", value),
+        }),
+    }
+}
+
+//returns whether the option is set by its environment variable, which takes
+//precedence over the config file
+fn has_env_var(long: &str) -> bool {
+    OPTION_ENV_VARS
+        .iter()
+        .filter(|(option, _)| *option == long)
+        .any(|(_, var)| std::env::var_os(var).is_some())
+}
+
+//an option specified on the command line, by its long (without --) or short
+//name, along with its value if it takes one
+#[derive(Debug, PartialEq)]
+enum CommandLineArg {
+    Long(String, Option<String>),
+    Short(char, Option<String>),
+}
+
+//returns the options specified on the command line. Options can be specified
+//as --long value, --long=value, -s value, -svalue or clustered with other
+//short flags (i.e. -vD value). app is used to determine which options take a
+//value.
+fn parse_command_line(app: &App, args: &[OsString]) -> Vec<CommandLineArg> {
+    let mut out = Vec::new();
+
+    let mut iter = args.iter().skip(1).map(|e| e.to_string_lossy());
+    while let Some(arg) = iter.next() {
+        //everything after -- is a positional argument
+        if arg == "--" {
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None if takes_value(app, &arg) => {
+                    (long, iter.next().map(|e| e.to_string()))
+                }
+                None => (long, None),
+            };
+
+            out.push(CommandLineArg::Long(name.to_string(), value));
+            continue;
+        }
+
+        //values and positional arguments
+        let cluster = match arg.strip_prefix('-') {
+            Some(e) if !e.is_empty() => e,
+            _ => continue,
+        };
+
+        for (i, c) in cluster.char_indices() {
+            if !takes_value(app, &format!("-{}", c)) {
+                out.push(CommandLineArg::Short(c, None));
+                continue;
+            }
+
+            //the rest of the cluster is the value if there is any
+            let rest = &cluster[i + c.len_utf8()..];
+            let value = if rest.is_empty() {
+                iter.next().map(|e| e.to_string())
+            } else {
+                Some(rest.strip_prefix('=').unwrap_or(rest).to_string())
+            };
+
+            out.push(CommandLineArg::Short(c, value));
+            break;
+        }
+    }
+
+    out
+}
+
+//returns whether the option (i.e. --name or -n) takes a value, by checking
+//whether clap accepts an argument after it
+fn takes_value(app: &App, arg: &str) -> bool {
+    match app.clone().get_matches_from_safe(vec!["", arg, "value"]) {
+        Ok(_) => true,
+        Err(e) => !matches!(
+            e.kind,
+            ErrorKind::UnknownArgument
+                | ErrorKind::HelpDisplayed
+                | ErrorKind::VersionDisplayed
+        ),
+    }
+}
+
+fn matches_arg(arg: &CommandLineArg, long: &str, short: Option<char>) -> bool {
+    match arg {
+        CommandLineArg::Long(name, _) => name == long,
+        CommandLineArg::Short(c, _) => Some(*c) == short,
+    }
+}
+
+//returns whether the option was specified on the command line
+fn has_arg(args: &[CommandLineArg], long: &str, short: Option<char>) -> bool {
+    args.iter().any(|e| matches_arg(e, long, short))
+}
+
+//returns the value for an option specified on the command line
+fn find_arg_value(
+    args: &[CommandLineArg],
+    long: &str,
+    short: Option<char>,
+) -> Option<String> {
+    args.iter()
+        .filter(|e| matches_arg(e, long, short))
+        .find_map(|e| match e {
+            CommandLineArg::Long(_, value)
+            | CommandLineArg::Short(_, value) => value.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(StructOpt, Debug)]
+    struct TestOpt {
+        #[structopt(short = "v", long = "verbose")]
+        verbose: bool,
+
+        #[structopt(short = "s", long = "sync")]
+        sync: bool,
+
+        #[structopt(short = "D", long = "data-dir")]
+        data_dir: Option<String>,
+
+        #[structopt(short = "n", long = "name", required = true)]
+        name: String,
+    }
+
+    fn parse(args: &[&str]) -> Vec<CommandLineArg> {
+        let args: Vec<OsString> = std::iter::once("test")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        parse_command_line(&TestOpt::clap(), &args)
+    }
+
+    #[test]
+    fn parses_clustered_short_flags() {
+        let args = parse(&["-vD", "dir", "-n", "foo#1234"]);
+
+        assert!(has_arg(&args, "verbose", Some('v')));
+        assert!(has_arg(&args, "name", Some('n')));
+        assert!(!has_arg(&args, "sync", Some('s')));
+        assert_eq!(
+            find_arg_value(&args, DATA_DIR_ARG, Some('D')),
+            Some("dir".to_string())
+        );
+    }
+
+    #[test]
+    fn does_not_treat_values_as_flags() {
+        //s is part of the name value, not the sync flag
+        let args = parse(&["-nsfoo", "--data-dir=dir", "-v"]);
+
+        assert!(!has_arg(&args, "sync", Some('s')));
+        assert_eq!(
+            find_arg_value(&args, "name", Some('n')),
+            Some("sfoo".to_string())
+        );
+        assert_eq!(
+            find_arg_value(&args, DATA_DIR_ARG, Some('D')),
+            Some("dir".to_string())
+        );
+
+        //-s after a value taking long option is its value
+        let args = parse(&["--name", "-s"]);
+        assert!(!has_arg(&args, "sync", Some('s')));
+    }
+
+    #[test]
+    fn ignores_args_after_separator() {
+        let args = parse(&["--", "-s"]);
+        assert!(!has_arg(&args, "sync", Some('s')));
+    }
+}
@@ -34,12 +34,14 @@ use std::str::FromStr;
 use std::{cmp::max, collections::hash_map::DefaultHasher, hash::Hasher};
 use std::{collections::HashMap, hash::Hash};
 
+use crate::enums::averageweighting::AverageWeighting;
 use crate::utils::{
     calculate_efficiency, calculate_kills_deaths_assists,
-    calculate_kills_deaths_ratio,
+    calculate_kills_deaths_ratio, calculate_per_minute,
+    calculate_per_ten_minutes, calculate_weighted_average,
 };
 
-const PLAYER_START_BUFFER: u32 = 30;
+pub const PLAYER_START_BUFFER: u32 = 30;
 
 #[derive(Debug, Clone)]
 pub struct Team {
@@ -111,10 +113,16 @@ pub struct CrucibleStats {
 }
 
 impl CrucibleStats {
+    /// Returns true if the player joined more than threshold_seconds after
+    /// the activity started
+    pub fn is_late_join(&self, threshold_seconds: u32) -> bool {
+        self.start_seconds > threshold_seconds
+    }
+
     pub fn generate_status(&self) -> String {
         let mut out: Vec<String> = Vec::new();
 
-        if self.start_seconds > PLAYER_START_BUFFER {
+        if self.is_late_join(PLAYER_START_BUFFER) {
             out.push("L".to_string());
         }
 
@@ -263,6 +271,92 @@ impl FromStr for PlayerName {
     }
 }
 
+/// Limits how far back a player's activity history is synced. Activities
+/// newer than the most recent synced activity are always synced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryLimit {
+    /// Only sync activities played after this date / time
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only sync this many of the most recent activities for each character,
+    /// across all modes
+    pub max_activities: Option<u32>,
+}
+
+impl HistoryLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.since.is_none() && self.max_activities.is_none()
+    }
+
+    /// Returns whether the limit includes older activities than other
+    pub fn extends(&self, other: &HistoryLimit) -> bool {
+        let since = match (self.since, other.since) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(a), Some(b)) => a < b,
+        };
+
+        let max_activities = match (self.max_activities, other.max_activities) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(a), Some(b)) => a > b,
+        };
+
+        since || max_activities
+    }
+}
+
+/// A player found by searching for the start of their Bungie name
+#[derive(Debug, Clone)]
+pub struct PlayerSearchResult {
+    /// Primary (cross save) membership for the player
+    pub member: Member,
+    pub platforms: Vec<Platform>,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+/// Players found by a Bungie name search, and whether there are more
+/// results which were not retrieved
+#[derive(Debug, Default)]
+pub struct PlayerSearchResults {
+    pub players: Vec<PlayerSearchResult>,
+    pub has_more: bool,
+}
+
+/// A player specified on the command line via a Bungie name (NAME#CODE), a
+/// Destiny membership id, or a query which is matched against local aliases
+/// and the names of players in the activity store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerSelector {
+    BungieName(PlayerName),
+    MembershipId(i64),
+    Query(String),
+}
+
+impl FromStr for PlayerSelector {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Err("Player name cannot be empty.");
+        }
+
+        if let Ok(name) = PlayerName::from_str(s) {
+            return Ok(PlayerSelector::BungieName(name));
+        }
+
+        if s.chars().all(|c| c.is_ascii_digit()) {
+            if let Ok(id) = s.parse::<i64>() {
+                return Ok(PlayerSelector::MembershipId(id));
+            }
+        }
+
+        Ok(PlayerSelector::Query(s.to_string()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WeaponStat {
     pub weapon: Item,
@@ -559,6 +653,29 @@ impl AggregateCruciblePerformances {
     }
 }
 
+impl AggregateCruciblePerformances {
+    /// Returns value per minute of time played across all activities
+    pub fn per_minute(&self, value: u32) -> f32 {
+        calculate_per_minute(value, self.time_played_seconds)
+    }
+
+    /// Returns value per ten minutes of time played across all activities
+    pub fn per_ten_minutes(&self, value: u32) -> f32 {
+        calculate_per_ten_minutes(value, self.time_played_seconds)
+    }
+
+    /// Returns the per game average of value using the specified weighting
+    pub fn average(&self, value: u32, weighting: &AverageWeighting) -> f32 {
+        calculate_weighted_average(
+            value,
+            self.total_activities,
+            self.time_played_seconds,
+            self.activity_duration_seconds,
+            weighting,
+        )
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ExtendedCruciblePlayerActivityPerformances {
     pub precision_kills: u32,
@@ -588,4 +705,8 @@ pub struct ActivityDetail {
     pub platform: Platform,
     pub director_activity_hash: u32,
     pub reference_id: u32,
+
+    /// Whether the activity was stored from the activity history, and its
+    /// details (all players, weapons, medals) have not been synced yet
+    pub detail_pending: bool,
 }
@@ -20,11 +20,13 @@
 * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+pub mod averageweighting;
 pub mod character;
 pub mod completionreason;
 pub mod itemtype;
 pub mod medaltier;
 pub mod mode;
+pub mod modefamily;
 pub mod moment;
 pub mod platform;
 pub mod standing;
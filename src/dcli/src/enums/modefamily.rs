@@ -0,0 +1,115 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use std::fmt;
+
+use crate::enums::mode::Mode;
+use crate::error::Error;
+
+/// Groups of Crucible modes which skill ratings are calculated separately
+/// for, as players' skill (and the lobbies they are matched into) differs
+/// between them.
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone)]
+#[repr(u32)]
+pub enum ModeFamily {
+    Quickplay = 0,
+    Competitive = 1,
+    TrialsOfOsiris = 2,
+    IronBanner = 3,
+}
+
+impl ModeFamily {
+    /// Returns the family for an activity with the specified modes, or None if
+    /// the activity is not rated, such as private matches.
+    pub fn from_modes(modes: &[Mode]) -> Option<ModeFamily> {
+        if modes.iter().any(|m| m.is_private())
+            || !modes.iter().any(|m| m.is_crucible())
+        {
+            return None;
+        }
+
+        let has = |list: &[Mode]| modes.iter().any(|m| list.contains(m));
+
+        let family = if has(&[
+            Mode::TrialsOfOsiris,
+            Mode::TrialsOfTheNine,
+            Mode::TrialsCountdown,
+            Mode::TrialsSurvival,
+        ]) {
+            ModeFamily::TrialsOfOsiris
+        } else if has(&[
+            Mode::IronBanner,
+            Mode::IronBannerControl,
+            Mode::IronBannerClash,
+            Mode::IronBannerSupremacy,
+            Mode::IronBannerSalvage,
+            Mode::IronBannerRift,
+            Mode::IronBannerZoneControl,
+            Mode::IronBannerTribute,
+            Mode::IronBannerFortress,
+        ]) {
+            ModeFamily::IronBanner
+        } else if has(&[
+            Mode::PvPCompetitive,
+            Mode::ClashCompetitive,
+            Mode::ControlCompetitive,
+            Mode::RiftCompetitive,
+            Mode::ShowdownCompetitive,
+            Mode::SurvivalCompetitive,
+            Mode::CountdownCompetitive,
+            Mode::CollisionCompetitive,
+        ]) {
+            ModeFamily::Competitive
+        } else {
+            ModeFamily::Quickplay
+        };
+
+        Some(family)
+    }
+
+    pub fn from_id(id: u32) -> Result<ModeFamily, Error> {
+        match id {
+            0 => Ok(ModeFamily::Quickplay),
+            1 => Ok(ModeFamily::Competitive),
+            2 => Ok(ModeFamily::TrialsOfOsiris),
+            3 => Ok(ModeFamily::IronBanner),
+            _ => Err(Error::UnknownEnumValue),
+        }
+    }
+
+    pub fn as_id(&self) -> u32 {
+        *self as u32
+    }
+}
+
+impl fmt::Display for ModeFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let out = match self {
+            ModeFamily::Quickplay => "Quickplay",
+            ModeFamily::Competitive => "Competitive",
+            ModeFamily::TrialsOfOsiris => "Trials of Osiris",
+            ModeFamily::IronBanner => "Iron Banner",
+        };
+
+        write!(f, "{}", out)
+    }
+}
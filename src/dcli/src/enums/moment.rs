@@ -21,19 +21,69 @@
 */
 
 use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
 
 use chrono::prelude::*;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 
 use crate::{
     error::Error,
+    manifestinterface::ManifestInterface,
     utils::{
         get_destiny2_launch_date, get_last_daily_reset, get_last_friday_reset,
         get_last_weekly_reset,
     },
 };
 
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum SeasonMoment {
+    Current,
+    Previous,
+    Number(u32),
+}
+
+impl SeasonMoment {
+    /// Returns the start (or end if end is true) of the season, based on the
+    /// season definitions in the manifest. If the season has not ended, the
+    /// end is the current time.
+    pub async fn get_date_time(
+        &self,
+        manifest: &mut ManifestInterface,
+        end: bool,
+    ) -> Result<DateTime<Utc>, Error> {
+        let season = match manifest.get_season_definition(self).await? {
+            Some(e) => e,
+            None => {
+                return Err(Error::ManifestItemNotFound {
+                    description: format!("Could not find {}", self),
+                })
+            }
+        };
+
+        //get_season_definition only returns seasons with a start date
+        if !end {
+            return Ok(season.start_date.unwrap());
+        }
+
+        let now = Utc::now();
+        Ok(match season.end_date {
+            Some(e) if e < now => e,
+            _ => now,
+        })
+    }
+}
+
+impl fmt::Display for SeasonMoment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeasonMoment::Current => write!(f, "current season"),
+            SeasonMoment::Previous => write!(f, "previous season"),
+            SeasonMoment::Number(n) => write!(f, "season {}", n),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum Moment {
     Now,
@@ -52,6 +102,18 @@ pub enum Moment {
     AllTime,
     Custom,
 
+    //relative duration before now, i.e. 3d, 36h, 2w
+    Ago(Duration),
+    //start of calendar date in local time zone, i.e. 2023-05-01
+    Date(NaiveDate),
+    //N weekly resets ago, where 0 is the last weekly reset
+    WeeklyResetsAgo(u32),
+    Today,
+    Yesterday,
+    //season:current, season:previous and season:<number>, resolved from
+    //the manifest
+    Season(SeasonMoment),
+
     Launch,
     CurseOfOsiris,
     Warmind,
@@ -80,8 +142,10 @@ pub enum Moment {
 }
 
 impl Moment {
-    pub fn get_date_time(&self) -> DateTime<Utc> {
-        match self {
+    /// Returns the date / time for the moment. Custom and season moments
+    /// return an error, and must be resolved with resolve_date_time.
+    pub fn get_date_time(&self) -> Result<DateTime<Utc>, Error> {
+        let dt = match self {
             Moment::Now => Utc::now(),
             Moment::Daily => get_last_daily_reset(),
             Moment::NextDaily => get_last_daily_reset() + Duration::days(1),
@@ -96,7 +160,34 @@ impl Moment {
             Moment::Month => Utc::now() - Duration::days(30),
             Moment::NextMonth => Utc::now() + Duration::days(30),
             Moment::AllTime => get_destiny2_launch_date(),
-            Moment::Custom => Utc.with_ymd_and_hms(0, 0, 0, 0, 0, 0).unwrap(),
+            Moment::Custom => {
+                return Err(Error::InvalidArgument {
+                    description: "Custom moment requires a custom time"
+                        .to_string(),
+                })
+            }
+
+            //clamp to launch if out of range, since nothing happened before it
+            Moment::Ago(d) => Utc::now()
+                .checked_sub_signed(*d)
+                .unwrap_or_else(get_destiny2_launch_date),
+            Moment::Date(d) => local_start_of_day(d),
+            Moment::WeeklyResetsAgo(n) => get_last_weekly_reset()
+                .checked_sub_signed(Duration::weeks(*n as i64))
+                .unwrap_or_else(get_destiny2_launch_date),
+            Moment::Today => local_start_of_day(&Local::now().date_naive()),
+            Moment::Yesterday => local_start_of_day(
+                &(Local::now().date_naive() - Duration::days(1)),
+            ),
+
+            Moment::Season(e) => {
+                return Err(Error::InvalidArgument {
+                    description: format!(
+                        "{} must be resolved from the manifest",
+                        e
+                    ),
+                })
+            }
 
             Moment::Launch => {
                 Utc.with_ymd_and_hms(2017, 9, 6, 0, 0, 1).unwrap()
@@ -181,6 +272,43 @@ impl Moment {
             Moment::SeasonOfTheWish => {
                 Utc.with_ymd_and_hms(2023, 11, 28, 17, 0, 0).unwrap()
             }
+        };
+
+        Ok(dt)
+    }
+}
+
+impl Moment {
+    /// Returns the date / time for the moment, using custom_time for custom
+    /// moments and the manifest stored in data_dir for season moments. end
+    /// specifies whether the moment is being used as the end of a period.
+    pub async fn resolve_date_time(
+        &self,
+        custom_time: Option<DateTime<Utc>>,
+        end: bool,
+        data_dir: &Path,
+    ) -> Result<DateTime<Utc>, Error> {
+        match self {
+            Moment::Custom => custom_time.ok_or(Error::InvalidArgument {
+                description: "Custom moment requires a custom time".to_string(),
+            }),
+            Moment::Season(season) => {
+                let mut manifest = ManifestInterface::new(data_dir, false)
+                    .await
+                    .map_err(|e| match e {
+                        Error::IoFileDoesNotExist { description } => {
+                            Error::IoFileDoesNotExist {
+                                description: format!(
+                                    "{} Season moments require the manifest. Have you run dclim?",
+                                    description
+                                ),
+                            }
+                        }
+                        e => e,
+                    })?;
+                season.get_date_time(&mut manifest, end).await
+            }
+            _ => self.get_date_time(),
         }
     }
 }
@@ -236,59 +364,145 @@ impl FromStr for Moment {
             "season_of_the_witch" => Ok(Moment::SeasonOfTheWitch),
             "season_of_the_wish" => Ok(Moment::SeasonOfTheWish),
 
-            _ => Err("Unknown Moment type"),
+            "today" => Ok(Moment::Today),
+            "yesterday" => Ok(Moment::Yesterday),
+
+            _ => parse_moment_expression(&s).ok_or("Unknown Moment type"),
         }
     }
 }
 
-impl fmt::Display for Moment {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let out = match self {
-            Moment::Now => "now",
-            Moment::Daily => "last daily reset",
-            Moment::NextDaily => "next daily reset",
-            Moment::Weekend => "last weekend reset",
-            Moment::NextWeekend => "next weekend reset",
-            Moment::Weekly => "last weekly reset",
-            Moment::NextWeekly => "next weekly reset",
-            Moment::Day => "last day",
-            Moment::NextDay => "next day",
-            Moment::Week => "last week",
-            Moment::NextWeek => "next week",
-            Moment::Month => "last month",
-            Moment::NextMonth => "next month",
-            Moment::AllTime => "all time",
-
-            Moment::Custom => "custom",
-
-            Moment::Launch => "launch",
-            Moment::CurseOfOsiris => "Curse of Osiris",
-            Moment::Warmind => "Warmind",
-            Moment::SeasonOfTheOutlaw => "Season of the Outlaw",
-            Moment::SeasonOfTheForge => "Season of the Forge",
-            Moment::SeasonOfTheDrifter => "Season of the Drifter",
-            Moment::SeasonOfOpulence => "Season of Opulence",
-            Moment::SeasonOfTheUndying => "Season of the Undying",
-            Moment::SeasonOfDawn => "Season of Dawn",
-            Moment::SeasonOfTheWorthy => "Season of the Worthy",
-            Moment::SeasonOfArrivals => "Season of Arrivals",
-            Moment::SeasonOfTheHunt => "Season of the Hunt",
-            Moment::SeasonOfTheChosen => "Season of the Chosen",
-            Moment::SeasonOfTheSplicer => "Season of the Splicer",
-            Moment::SeasonOfTheLost => "Season of the Lost",
-            Moment::SeasonOfTheRisen => "Season of the Risen",
-            Moment::WitchQueen => "The Witch Queen",
-            Moment::SeasonOfTheHaunted => "Season of the Haunted",
-            Moment::SeasonOfPlunder => "Season of Plunder",
-            Moment::SeasonOfTheSeraph => "Season of the Seraph",
-            Moment::Lightfall => "Lightfall",
-            Moment::SeasonOfDefiance => "Season of Defiance",
-            Moment::SeasonOfTheDeep => "Season of the Deep",
-            Moment::SeasonOfTheWitch => "Season of the Witch",
-            Moment::SeasonOfTheWish => "Season of the Wish",
+//parses season (season:current, season:previous, season:22), relative
+//(3d, 36h, 2w, 90m), calendar date (2023-05-01) and N weekly resets ago
+//(2 weekly resets ago, 2_weekly_resets_ago or weekly:-2) moments
+fn parse_moment_expression(s: &str) -> Option<Moment> {
+    let s = s.trim();
+
+    if let Some(season) = s.strip_prefix("season:") {
+        return match season {
+            "current" => Some(Moment::Season(SeasonMoment::Current)),
+            "previous" => Some(Moment::Season(SeasonMoment::Previous)),
+            _ => season
+                .parse::<u32>()
+                .ok()
+                .map(|n| Moment::Season(SeasonMoment::Number(n))),
         };
+    }
 
-        write!(f, "{}", out)
+    if let Some(n) = s.strip_prefix("weekly:") {
+        return n
+            .strip_prefix('-')?
+            .parse::<u32>()
+            .ok()
+            .map(Moment::WeeklyResetsAgo);
+    }
+
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(Moment::Date(d));
+    }
+
+    let normalized = s.replace('_', " ");
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+    if let [count, "weekly", "resets" | "reset", "ago"] = words[..] {
+        return count.parse::<u32>().ok().map(Moment::WeeklyResetsAgo);
+    }
+
+    let unit = s.chars().last()?;
+    let count: u32 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    let count = count as i64;
+
+    let d = match unit {
+        'm' => Duration::minutes(count),
+        'h' => Duration::hours(count),
+        'd' => Duration::days(count),
+        'w' => Duration::weeks(count),
+        _ => return None,
+    };
+
+    Some(Moment::Ago(d))
+}
+
+fn local_start_of_day(date: &NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+
+    //earliest handles days where local midnight is ambiguous due to daylight
+    //saving changes. Fall back to treating it as UTC if it doesn't exist.
+    match Local.from_local_datetime(&midnight).earliest() {
+        Some(e) => e.with_timezone(&Utc),
+        None => Utc.from_utc_datetime(&midnight),
+    }
+}
+
+fn format_duration_ago(d: &Duration) -> String {
+    let minutes = d.num_minutes();
+
+    let (count, unit) = if minutes % (60 * 24 * 7) == 0 && minutes != 0 {
+        (d.num_weeks(), "week")
+    } else if minutes % (60 * 24) == 0 && minutes != 0 {
+        (d.num_days(), "day")
+    } else if minutes % 60 == 0 && minutes != 0 {
+        (d.num_hours(), "hour")
+    } else {
+        (minutes, "minute")
+    };
+
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+impl fmt::Display for Moment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Moment::Now => write!(f, "now"),
+            Moment::Daily => write!(f, "last daily reset"),
+            Moment::NextDaily => write!(f, "next daily reset"),
+            Moment::Weekend => write!(f, "last weekend reset"),
+            Moment::NextWeekend => write!(f, "next weekend reset"),
+            Moment::Weekly => write!(f, "last weekly reset"),
+            Moment::NextWeekly => write!(f, "next weekly reset"),
+            Moment::Day => write!(f, "last day"),
+            Moment::NextDay => write!(f, "next day"),
+            Moment::Week => write!(f, "last week"),
+            Moment::NextWeek => write!(f, "next week"),
+            Moment::Month => write!(f, "last month"),
+            Moment::NextMonth => write!(f, "next month"),
+            Moment::AllTime => write!(f, "all time"),
+
+            Moment::Custom => write!(f, "custom"),
+            Moment::Today => write!(f, "today"),
+            Moment::Yesterday => write!(f, "yesterday"),
+            Moment::Ago(d) => write!(f, "{}", format_duration_ago(d)),
+            Moment::Date(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            Moment::WeeklyResetsAgo(1) => write!(f, "1 weekly reset ago"),
+            Moment::WeeklyResetsAgo(n) => write!(f, "{} weekly resets ago", n),
+            Moment::Season(e) => write!(f, "{}", e),
+
+            Moment::Launch => write!(f, "launch"),
+            Moment::CurseOfOsiris => write!(f, "Curse of Osiris"),
+            Moment::Warmind => write!(f, "Warmind"),
+            Moment::SeasonOfTheOutlaw => write!(f, "Season of the Outlaw"),
+            Moment::SeasonOfTheForge => write!(f, "Season of the Forge"),
+            Moment::SeasonOfTheDrifter => write!(f, "Season of the Drifter"),
+            Moment::SeasonOfOpulence => write!(f, "Season of Opulence"),
+            Moment::SeasonOfTheUndying => write!(f, "Season of the Undying"),
+            Moment::SeasonOfDawn => write!(f, "Season of Dawn"),
+            Moment::SeasonOfTheWorthy => write!(f, "Season of the Worthy"),
+            Moment::SeasonOfArrivals => write!(f, "Season of Arrivals"),
+            Moment::SeasonOfTheHunt => write!(f, "Season of the Hunt"),
+            Moment::SeasonOfTheChosen => write!(f, "Season of the Chosen"),
+            Moment::SeasonOfTheSplicer => write!(f, "Season of the Splicer"),
+            Moment::SeasonOfTheLost => write!(f, "Season of the Lost"),
+            Moment::SeasonOfTheRisen => write!(f, "Season of the Risen"),
+            Moment::WitchQueen => write!(f, "The Witch Queen"),
+            Moment::SeasonOfTheHaunted => write!(f, "Season of the Haunted"),
+            Moment::SeasonOfPlunder => write!(f, "Season of Plunder"),
+            Moment::SeasonOfTheSeraph => write!(f, "Season of the Seraph"),
+            Moment::Lightfall => write!(f, "Lightfall"),
+            Moment::SeasonOfDefiance => write!(f, "Season of Defiance"),
+            Moment::SeasonOfTheDeep => write!(f, "Season of the Deep"),
+            Moment::SeasonOfTheWitch => write!(f, "Season of the Witch"),
+            Moment::SeasonOfTheWish => write!(f, "Season of the Wish"),
+        }
     }
 }
 
@@ -330,3 +544,94 @@ impl DateTimePeriod {
         Ok(DateTimePeriod { start, end })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_moments() {
+        assert_eq!(
+            parse_moment_expression("30d"),
+            Some(Moment::Ago(Duration::days(30)))
+        );
+        assert_eq!(
+            parse_moment_expression("2w"),
+            Some(Moment::Ago(Duration::weeks(2)))
+        );
+        assert_eq!(
+            parse_moment_expression("36h"),
+            Some(Moment::Ago(Duration::hours(36)))
+        );
+        assert_eq!(
+            parse_moment_expression("90m"),
+            Some(Moment::Ago(Duration::minutes(90)))
+        );
+    }
+
+    #[test]
+    fn parses_weekly_resets_ago() {
+        assert_eq!(
+            parse_moment_expression("weekly:-2"),
+            Some(Moment::WeeklyResetsAgo(2))
+        );
+        assert_eq!(
+            parse_moment_expression("2 weekly resets ago"),
+            Some(Moment::WeeklyResetsAgo(2))
+        );
+        assert_eq!(
+            parse_moment_expression("1_weekly_reset_ago"),
+            Some(Moment::WeeklyResetsAgo(1))
+        );
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(
+            parse_moment_expression("2023-05-01"),
+            Some(Moment::Date(NaiveDate::from_ymd_opt(2023, 5, 1).unwrap()))
+        );
+        assert_eq!(parse_moment_expression("2023-02-30"), None);
+    }
+
+    #[test]
+    fn parses_season_moments() {
+        assert_eq!(
+            parse_moment_expression("season:current"),
+            Some(Moment::Season(SeasonMoment::Current))
+        );
+        assert_eq!(
+            parse_moment_expression("season:22"),
+            Some(Moment::Season(SeasonMoment::Number(22)))
+        );
+    }
+
+    #[test]
+    fn season_and_custom_moments_require_resolve() {
+        assert!(Moment::Season(SeasonMoment::Current)
+            .get_date_time()
+            .is_err());
+        assert!(Moment::Custom.get_date_time().is_err());
+        assert!(Moment::Weekly.get_date_time().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_moments() {
+        for s in [
+            "",
+            "d",
+            "30",
+            "30y",
+            "-3d",
+            "weekly:2",
+            "weekly:-x",
+            "season:next",
+            "2 weekly resets",
+            "2023-05",
+        ] {
+            assert_eq!(parse_moment_expression(s), None, "{}", s);
+        }
+
+        assert!(Moment::from_str("not_a_moment").is_err());
+    }
+}
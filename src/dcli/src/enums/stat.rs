@@ -37,6 +37,17 @@ pub enum Stat {
     DeathsAvg,
     AssistsAvg,
 
+    KillsPerMin,
+    DeathsPerMin,
+    AssistsPerMin,
+    AbilityKillsPerMin,
+    SuperKillsPerMin,
+    KillsPer10Min,
+    DeathsPer10Min,
+    AssistsPer10Min,
+    AbilityKillsPer10Min,
+    SuperKillsPer10Min,
+
     KDMax,
     EfficiencyMax,
     KDAMax,
@@ -49,6 +60,7 @@ pub enum Stat {
     Wins,
     Losses,
     Mercies,
+    Pending,
 }
 
 impl FromStr for Stat {
@@ -72,6 +84,17 @@ impl FromStr for Stat {
             "deaths_avg" => Ok(Stat::DeathsAvg),
             "assists_avg" => Ok(Stat::AssistsAvg),
 
+            "kills_per_min" => Ok(Stat::KillsPerMin),
+            "deaths_per_min" => Ok(Stat::DeathsPerMin),
+            "assists_per_min" => Ok(Stat::AssistsPerMin),
+            "ability_kills_per_min" => Ok(Stat::AbilityKillsPerMin),
+            "super_kills_per_min" => Ok(Stat::SuperKillsPerMin),
+            "kills_per_10min" => Ok(Stat::KillsPer10Min),
+            "deaths_per_10min" => Ok(Stat::DeathsPer10Min),
+            "assists_per_10min" => Ok(Stat::AssistsPer10Min),
+            "ability_kills_per_10min" => Ok(Stat::AbilityKillsPer10Min),
+            "super_kills_per_10min" => Ok(Stat::SuperKillsPer10Min),
+
             "kd_max" => Ok(Stat::KDMax),
             "efficiency_max" => Ok(Stat::EfficiencyMax),
             "kda_max" => Ok(Stat::KDAMax),
@@ -84,6 +107,7 @@ impl FromStr for Stat {
             "wins" => Ok(Stat::Wins),
             "losses" => Ok(Stat::Losses),
             "mercies" => Ok(Stat::Mercies),
+            "pending" => Ok(Stat::Pending),
 
             _ => Err("Unknown Stat type"),
         }
@@ -105,6 +129,17 @@ impl fmt::Display for Stat {
             Stat::OpponentsDefeatedAvg => "Opponents defeated per game",
             Stat::AssistsAvg => "Assists per game",
 
+            Stat::KillsPerMin => "Kills per minute",
+            Stat::DeathsPerMin => "Deaths per minute",
+            Stat::AssistsPerMin => "Assists per minute",
+            Stat::AbilityKillsPerMin => "Ability kills per minute",
+            Stat::SuperKillsPerMin => "Super kills per minute",
+            Stat::KillsPer10Min => "Kills per 10 minutes",
+            Stat::DeathsPer10Min => "Deaths per 10 minutes",
+            Stat::AssistsPer10Min => "Assists per 10 minutes",
+            Stat::AbilityKillsPer10Min => "Ability kills per 10 minutes",
+            Stat::SuperKillsPer10Min => "Super kills per 10 minutes",
+
             Stat::KDMax => "Highest game kills deaths ration",
             Stat::EfficiencyMax => "Highest game efficiency",
             Stat::KDAMax => "Highest game kills deaths assists ratio",
@@ -119,6 +154,7 @@ impl fmt::Display for Stat {
             Stat::Wins => "wins",
             Stat::Losses => "losses",
             Stat::Mercies => "games ending in mercy",
+            Stat::Pending => "games whose details are still being synced",
         };
 
         write!(f, "{}", out)
@@ -66,8 +66,58 @@ pub enum Error {
     ActivityNotFound,
     DateTimePeriodOrder,
     BungieNameNotFound,
+    PlayerNotFound { description: String },
+    AmbiguousPlayerName { description: String },
     NoProfilesFound,
     InvalidArgument { description: String },
+    Config { description: String },
+    Archive { description: String },
+}
+
+impl Error {
+    /// Returns the name of the error variant, used to group errors in
+    /// metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::ApiRequest { .. } => "ApiRequest",
+            Error::ApiStatus { .. } => "ApiStatus",
+            Error::ApiResponseMissing => "ApiResponseMissing",
+            Error::ParameterParseFailure => "ParameterParseFailure",
+            Error::InvalidParameters => "InvalidParameters",
+            Error::ApiKeyMissingFromRequest => "ApiKeyMissingFromRequest",
+            Error::ApiNotAvailableException => "ApiNotAvailableException",
+            Error::RequestTimedOut => "RequestTimedOut",
+            Error::Request => "Request",
+            Error::PrivacyException => "PrivacyException",
+            Error::Database { .. } => "Database",
+            Error::ApiParse { .. } => "ApiParse",
+            Error::IoError { .. } => "IoError",
+            Error::IoErrorDirIsFile { .. } => "IoErrorDirIsFile",
+            Error::IoFileDoesNotExist { .. } => "IoFileDoesNotExist",
+            Error::ZipError { .. } => "ZipError",
+            Error::Unknown { .. } => "Unknown",
+            Error::ManifestNotSet => "ManifestNotSet",
+            Error::ManifestItemNotFound { .. } => "ManifestItemNotFound",
+            Error::MaxActivitiesRequestCountExceeded => {
+                "MaxActivitiesRequestCountExceeded"
+            }
+            Error::CharacterDataNotFound => "CharacterDataNotFound",
+            Error::SystemDirectoryNotFound => "SystemDirectoryNotFound",
+            Error::ChronoParse { .. } => "ChronoParse",
+            Error::UnknownEnumValue => "UnknownEnumValue",
+            Error::NoCharacters => "NoCharacters",
+            Error::CharacterDoesNotExist => "CharacterDoesNotExist",
+            Error::ActivityNotFound => "ActivityNotFound",
+            Error::DateTimePeriodOrder => "DateTimePeriodOrder",
+            Error::BungieNameNotFound => "BungieNameNotFound",
+            Error::PlayerNotFound { .. } => "PlayerNotFound",
+            Error::AmbiguousPlayerName { .. } => "AmbiguousPlayerName",
+            Error::NoProfilesFound => "NoProfilesFound",
+            Error::InvalidArgument { .. } => "InvalidArgument",
+            Error::Config { .. } => "Config",
+            Error::Archive { .. } => "Archive",
+        }
+    }
 }
 
 impl Display for Error {
@@ -79,6 +129,12 @@ impl Display for Error {
             Error::InvalidArgument { description } => {
                 write!(f, "Invalid Argument. {}", description)
             },
+            Error::Config { description } => {
+                write!(f, "Error loading config file. {}", description)
+            },
+            Error::Archive { description } => {
+                write!(f, "Error reading activity store archive. {}", description)
+            },
             Error::ApiStatus { description } => {
                 write!(f, "Destiny 2 API call returned an error. {}", description)
             },
@@ -171,6 +227,12 @@ impl Display for Error {
             Error::BungieNameNotFound  => {
                 write!(f, "Bungie name not found.")
             },
+            Error::PlayerNotFound { description } => {
+                write!(f, "Player not found. {}", description)
+            },
+            Error::AmbiguousPlayerName { description } => {
+                write!(f, "Multiple players match the name. {}", description)
+            },
             Error::NoProfilesFound  => {
                 write!(f, "No player profiles found.")
             },
@@ -186,6 +248,14 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Error {
+        Error::Config {
+            description: format!("toml::de::Error : {}", err),
+        }
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Error {
         /*
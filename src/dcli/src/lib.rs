@@ -20,11 +20,13 @@
 * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+pub mod activityfilter;
 pub mod activitystoreinterface;
 pub mod apiclient;
 pub mod apiinterface;
 pub mod apiutils;
 pub mod character;
+pub mod config;
 pub mod crucible;
 pub mod cruciblestats;
 pub mod emblem;
@@ -32,8 +34,11 @@ pub mod enums;
 pub mod error;
 pub mod manifest;
 pub mod manifestinterface;
+pub mod metrics;
 pub mod output;
 pub mod playeractivitiessummary;
 pub mod response;
+pub mod skillrating;
 pub mod statscontainer;
+pub mod storearchive;
 pub mod utils;
@@ -1,8 +1,9 @@
+use chrono::{DateTime, Utc};
 use serde_derive::{Deserialize, Serialize};
 
 use crate::enums::itemtype::{ItemSubType, ItemType};
 use crate::enums::medaltier::MedalTier;
-use crate::response::utils::prepend_base_url_option;
+use crate::response::utils::{prepend_base_url_option, str_to_datetime_option};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DisplayPropertiesData {
@@ -113,3 +114,31 @@ pub struct ActivityTypeDefinitionData {
     #[serde(rename = "displayProperties")]
     pub display_properties: DisplayPropertiesData,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeasonDefinitionData {
+    #[serde(rename = "hash")]
+    pub id: u32,
+
+    #[serde(rename = "displayProperties")]
+    pub display_properties: DisplayPropertiesData,
+
+    #[serde(rename = "seasonNumber")]
+    pub season_number: u32,
+
+    #[serde(
+        default,
+        skip_serializing,
+        rename = "startDate",
+        deserialize_with = "str_to_datetime_option"
+    )]
+    pub start_date: Option<DateTime<Utc>>,
+
+    #[serde(
+        default,
+        skip_serializing,
+        rename = "endDate",
+        deserialize_with = "str_to_datetime_option"
+    )]
+    pub end_date: Option<DateTime<Utc>>,
+}
@@ -30,12 +30,15 @@ use sqlx::Row;
 use sqlx::{ConnectOptions, Connection, SqliteConnection};
 use std::collections::HashMap;
 
+use chrono::Utc;
+
+use crate::enums::moment::SeasonMoment;
 use crate::error::Error;
 use crate::manifest::definitions::{
     ActivityDefinitionData, ActivityTypeDefinitionData,
     DestinationDefinitionData, DisplayPropertiesData,
     HistoricalStatsDefinition, InventoryItemDefinitionData,
-    PlaceDefinitionData,
+    PlaceDefinitionData, SeasonDefinitionData,
 };
 
 pub const MANIFEST_FILE_NAME: &str = "manifest.sqlite3";
@@ -343,6 +346,55 @@ impl ManifestInterface {
         Ok(data)
     }
 
+    /// Returns all seasons which have a start date, ordered by start date
+    pub async fn get_season_definitions(
+        &mut self,
+    ) -> Result<Vec<SeasonDefinitionData>, Error> {
+        let rows = sqlx::query("SELECT json FROM DestinySeasonDefinition")
+            .fetch_all(&mut self.manifest_db)
+            .await?;
+
+        let mut seasons: Vec<SeasonDefinitionData> =
+            Vec::with_capacity(rows.len());
+        for row in rows {
+            let json: &str = row.try_get_unchecked("json")?;
+            let season: SeasonDefinitionData = serde_json::from_str(json)?;
+
+            //the manifest contains placeholder seasons without dates
+            if season.start_date.is_some() {
+                seasons.push(season);
+            }
+        }
+
+        seasons.sort_by_key(|e| e.start_date);
+
+        Ok(seasons)
+    }
+
+    pub async fn get_season_definition(
+        &mut self,
+        season: &SeasonMoment,
+    ) -> Result<Option<SeasonDefinitionData>, Error> {
+        let seasons = self.get_season_definitions().await?;
+
+        let now = Utc::now();
+        let current_index =
+            seasons.iter().rposition(|e| e.start_date.unwrap() <= now);
+
+        let out = match season {
+            SeasonMoment::Current => current_index.map(|i| &seasons[i]),
+            SeasonMoment::Previous => match current_index {
+                Some(i) if i > 0 => Some(&seasons[i - 1]),
+                _ => None,
+            },
+            SeasonMoment::Number(n) => {
+                seasons.iter().find(|e| e.season_number == *n)
+            }
+        };
+
+        Ok(out.cloned())
+    }
+
     async fn get_definition<T: serde::de::DeserializeOwned>(
         &mut self,
         query: &str,
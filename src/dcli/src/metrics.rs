@@ -0,0 +1,387 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//Collects metrics while syncing, and serves them in the Prometheus text
+//exposition format via a simple local HTTP listener.
+//
+//Metrics are only recorded once enabled (by calling enable() or serve()), so
+//tools which share the API and data store code do not collect them.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::info;
+
+use crate::error::Error;
+
+pub const METRICS_PATH: &str = "/metrics";
+
+//upper bounds (in seconds) of the API request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+const REQUEST_TIMEOUT: u64 = 5; //seconds
+
+static METRICS: Mutex<Option<Metrics>> = Mutex::new(None);
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    sync_cycles: u64,
+    sync_cycle_duration: f64,
+    member_activities: BTreeMap<String, u64>,
+    member_syncs: BTreeMap<String, u64>,
+    api_requests: BTreeMap<(String, String), u64>,
+    api_latency: BTreeMap<String, Histogram>,
+    errors: BTreeMap<&'static str, u64>,
+    queue_pending: u32,
+    queue_failed: u32,
+    last_sync: Option<DateTime<Utc>>,
+    database_size: u64,
+}
+
+/// Starts recording metrics. Metrics recorded before this is called are
+/// ignored.
+pub fn enable() {
+    let mut metrics = METRICS.lock().unwrap();
+    if metrics.is_none() {
+        *metrics = Some(Metrics::default());
+    }
+}
+
+fn with_metrics<F: FnOnce(&mut Metrics)>(f: F) {
+    if let Ok(mut metrics) = METRICS.lock() {
+        if let Some(m) = metrics.as_mut() {
+            f(m);
+        }
+    }
+}
+
+/// Records a call to the API. status is the HTTP status code, or the name of
+/// the error if the request could not be made.
+pub fn record_api_request(path: &str, status: &str, duration: Duration) {
+    with_metrics(|m| {
+        let endpoint = endpoint_from_path(path);
+
+        *m.api_requests
+            .entry((endpoint.clone(), status.to_string()))
+            .or_insert(0) += 1;
+
+        m.api_latency
+            .entry(endpoint)
+            .or_default()
+            .observe(duration.as_secs_f64());
+    });
+}
+
+pub fn record_error(error: &Error) {
+    with_metrics(|m| {
+        *m.errors.entry(error.name()).or_insert(0) += 1;
+    });
+}
+
+/// Records a successful sync of a member, along with the number of new
+/// activities which were synced.
+pub fn record_member_sync(member: &str, activities: u32) {
+    with_metrics(|m| {
+        *m.member_activities.entry(member.to_string()).or_insert(0) +=
+            activities as u64;
+        *m.member_syncs.entry(member.to_string()).or_insert(0) += 1;
+        m.last_sync = Some(Utc::now());
+    });
+}
+
+pub fn record_sync_cycle(duration: Duration) {
+    with_metrics(|m| {
+        m.sync_cycles += 1;
+        m.sync_cycle_duration = duration.as_secs_f64();
+    });
+}
+
+pub fn set_queue_depth(pending: u32, failed: u32) {
+    with_metrics(|m| {
+        m.queue_pending = pending;
+        m.queue_failed = failed;
+    });
+}
+
+pub fn set_database_size(bytes: u64) {
+    with_metrics(|m| {
+        m.database_size = bytes;
+    });
+}
+
+//replace ids in api paths, so requests are grouped by endpoint, i.e.
+///Platform/Destiny2/Stats/PostGameCarnageReport/{id}/
+fn endpoint_from_path(path: &str) -> String {
+    path.split('/')
+        .map(|e| {
+            if !e.is_empty() && e.parse::<i64>().is_ok() {
+                "{id}"
+            } else {
+                e
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    out.push_str(&format!("# HELP {} {}\n", name, help));
+    out.push_str(&format!("# TYPE {} {}\n", name, kind));
+}
+
+/// Returns all recorded metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    let metrics = METRICS.lock().unwrap();
+    let m = match metrics.as_ref() {
+        Some(e) => e,
+        None => return String::new(),
+    };
+
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "dcli_sync_cycles_total",
+        "Number of completed sync cycles.",
+        "counter",
+    );
+    out.push_str(&format!("dcli_sync_cycles_total {}\n", m.sync_cycles));
+
+    write_header(
+        &mut out,
+        "dcli_sync_cycle_duration_seconds",
+        "Duration of the most recent sync cycle.",
+        "gauge",
+    );
+    out.push_str(&format!(
+        "dcli_sync_cycle_duration_seconds {}\n",
+        m.sync_cycle_duration
+    ));
+
+    write_header(
+        &mut out,
+        "dcli_member_syncs_total",
+        "Number of successful syncs for each member.",
+        "counter",
+    );
+    for (member, count) in m.member_syncs.iter() {
+        out.push_str(&format!(
+            "dcli_member_syncs_total{{member=\"{}\"}} {}\n",
+            escape_label(member),
+            count
+        ));
+    }
+
+    write_header(
+        &mut out,
+        "dcli_activities_synced_total",
+        "Number of activities synced for each member.",
+        "counter",
+    );
+    for (member, count) in m.member_activities.iter() {
+        out.push_str(&format!(
+            "dcli_activities_synced_total{{member=\"{}\"}} {}\n",
+            escape_label(member),
+            count
+        ));
+    }
+
+    write_header(
+        &mut out,
+        "dcli_api_requests_total",
+        "Number of Destiny 2 API requests by endpoint and status.",
+        "counter",
+    );
+    for ((endpoint, status), count) in m.api_requests.iter() {
+        out.push_str(&format!(
+            "dcli_api_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}\n",
+            escape_label(endpoint),
+            escape_label(status),
+            count
+        ));
+    }
+
+    write_header(
+        &mut out,
+        "dcli_api_request_duration_seconds",
+        "Latency of Destiny 2 API requests by endpoint.",
+        "histogram",
+    );
+    for (endpoint, h) in m.api_latency.iter() {
+        let endpoint = escape_label(endpoint);
+        for (count, bound) in h.buckets.iter().zip(LATENCY_BUCKETS) {
+            out.push_str(&format!(
+                "dcli_api_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}\n",
+                endpoint, bound, count
+            ));
+        }
+        out.push_str(&format!(
+            "dcli_api_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}\n",
+            endpoint, h.count
+        ));
+        out.push_str(&format!(
+            "dcli_api_request_duration_seconds_sum{{endpoint=\"{}\"}} {}\n",
+            endpoint, h.sum
+        ));
+        out.push_str(&format!(
+            "dcli_api_request_duration_seconds_count{{endpoint=\"{}\"}} {}\n",
+            endpoint, h.count
+        ));
+    }
+
+    write_header(
+        &mut out,
+        "dcli_errors_total",
+        "Number of errors encountered while syncing, by error type.",
+        "counter",
+    );
+    for (variant, count) in m.errors.iter() {
+        out.push_str(&format!(
+            "dcli_errors_total{{variant=\"{}\"}} {}\n",
+            variant, count
+        ));
+    }
+
+    write_header(
+        &mut out,
+        "dcli_activity_queue_depth",
+        "Number of activities in the sync queue, by state.",
+        "gauge",
+    );
+    out.push_str(&format!(
+        "dcli_activity_queue_depth{{state=\"pending\"}} {}\n",
+        m.queue_pending
+    ));
+    out.push_str(&format!(
+        "dcli_activity_queue_depth{{state=\"failed\"}} {}\n",
+        m.queue_failed
+    ));
+
+    write_header(
+        &mut out,
+        "dcli_last_successful_sync_timestamp_seconds",
+        "Unix timestamp of the last successful member sync.",
+        "gauge",
+    );
+    out.push_str(&format!(
+        "dcli_last_successful_sync_timestamp_seconds {}\n",
+        m.last_sync.map(|e| e.timestamp()).unwrap_or(0)
+    ));
+
+    write_header(
+        &mut out,
+        "dcli_database_size_bytes",
+        "Size of the data store, including the write ahead log.",
+        "gauge",
+    );
+    out.push_str(&format!("dcli_database_size_bytes {}\n", m.database_size));
+
+    out
+}
+
+/// Enables metrics, and starts a HTTP listener on a background thread
+/// which serves them at /metrics.
+pub fn serve(address: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)?;
+    enable();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = match stream {
+                Ok(e) => handle_request(e),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                info!("Error handling metrics request : {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_request(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT)))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    //read the rest of the headers, so the client doesn't see the connection
+    //reset when we close it
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = if path == METRICS_PATH {
+        ("200 OK", "text/plain; version=0.0.4", render())
+    } else {
+        ("404 Not Found", "text/plain", "Not Found\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+
+    stream.flush()
+}
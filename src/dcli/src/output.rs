@@ -25,6 +25,7 @@ use std::str::FromStr;
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Output {
     Tsv,
+    Json,
     Default,
 }
 
@@ -38,6 +39,7 @@ impl FromStr for Output {
         //get a slice to get a &str for the match
         match &s[..] {
             "tsv" => Ok(Output::Tsv),
+            "json" => Ok(Output::Json),
             "default" => Ok(Output::Default),
             _ => Err("Unknown Output type"),
         }
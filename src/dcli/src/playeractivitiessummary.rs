@@ -20,10 +20,23 @@
 * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+use crate::enums::averageweighting::AverageWeighting;
+use crate::utils::{
+    calculate_per_minute, calculate_per_ten_minutes, calculate_weighted_average,
+};
+
 #[derive(sqlx::FromRow, Debug)]
 pub struct PlayerActivitiesSummary {
     pub total_activities: u32,
     pub time_played_seconds: u32,
+    pub activity_duration_seconds: u32,
+
+    //activities whose details have not been synced yet. They have no
+    //extended stats (grenade, melee, super and ability kills, and precision),
+    //which are 0 for them
+    pub pending_activities: u32,
+    pub detailed_time_played_seconds: u32,
+
     pub wins: u32,
     pub completion_reason_mercy: u32,
     pub completed: u32,
@@ -50,3 +63,38 @@ pub struct PlayerActivitiesSummary {
     pub highest_kills_deaths_ratio: f32,
     pub highest_efficiency: f32,
 }
+
+impl PlayerActivitiesSummary {
+    /// Returns value per minute of time played across all activities
+    pub fn per_minute(&self, value: u32) -> f32 {
+        calculate_per_minute(value, self.time_played_seconds)
+    }
+
+    /// Returns value per ten minutes of time played across all activities
+    pub fn per_ten_minutes(&self, value: u32) -> f32 {
+        calculate_per_ten_minutes(value, self.time_played_seconds)
+    }
+
+    /// Returns the per game average of value using the specified weighting
+    pub fn average(&self, value: u32, weighting: &AverageWeighting) -> f32 {
+        calculate_weighted_average(
+            value,
+            self.total_activities,
+            self.time_played_seconds,
+            self.activity_duration_seconds,
+            weighting,
+        )
+    }
+
+    /// Returns value per minute of time played across the activities whose
+    /// details have been synced. Used for extended stats.
+    pub fn detailed_per_minute(&self, value: u32) -> f32 {
+        calculate_per_minute(value, self.detailed_time_played_seconds)
+    }
+
+    /// Returns value per ten minutes of time played across the activities
+    /// whose details have been synced. Used for extended stats.
+    pub fn detailed_per_ten_minutes(&self, value: u32) -> f32 {
+        calculate_per_ten_minutes(value, self.detailed_time_played_seconds)
+    }
+}
@@ -77,3 +77,56 @@ impl IsDestinyAPIResponse for LinkedProfilesResponse {
 pub struct DestinyLinkedProfilesResponse {
     pub profiles: Vec<DestinyProfileUserInfoCard>,
 }
+
+#[derive(Serialize, Debug)]
+pub struct UserSearchPrefixPostData {
+    #[serde(rename = "displayNamePrefix")]
+    pub display_name_prefix: String,
+}
+
+impl UserSearchPrefixPostData {
+    pub fn to_json(&self) -> Result<String, serde_json::error::Error> {
+        let out = serde_json::to_string(&self)?;
+        Ok(out)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchByGlobalNamePrefixResponse {
+    #[serde(rename = "Response")]
+    pub response: Option<UserSearchResponse>,
+
+    #[serde(flatten)]
+    pub status: DestinyResponseStatus,
+}
+
+impl IsDestinyAPIResponse for SearchByGlobalNamePrefixResponse {
+    fn get_status(&self) -> &DestinyResponseStatus {
+        &self.status
+    }
+}
+
+//https://bungie-net.github.io/multi/schema_User-UserSearchResponse.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserSearchResponse {
+    #[serde(rename = "searchResults")]
+    pub search_results: Vec<UserSearchResponseDetail>,
+
+    pub page: u32,
+
+    #[serde(rename = "hasMore")]
+    pub has_more: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserSearchResponseDetail {
+    #[serde(rename = "bungieGlobalDisplayName")]
+    pub bungie_display_name: Option<String>,
+
+    #[serde(rename = "bungieGlobalDisplayNameCode")]
+    pub bungie_display_name_code: Option<u32>,
+
+    #[serde(rename = "destinyMemberships")]
+    #[serde(default)]
+    pub destiny_memberships: Vec<UserInfoCard>,
+}
@@ -20,7 +20,7 @@
 * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use serde::Deserialize;
 //use serde_derive::Deserialize;
 
@@ -215,6 +215,30 @@ where
     Ok(dt)
 }
 
+pub fn str_to_datetime_option<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    let s = match Option::<String>::deserialize(deserializer)? {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+
+    let n = match NaiveDateTime::parse_from_str(&s, API_DATE_TIME_FORMAT) {
+        Ok(e) => e,
+        Err(e) => {
+            return Err(serde::de::Error::custom(format!(
+                "Could not parse date-time : {}",
+                e
+            )))
+        }
+    };
+
+    Ok(Some(Utc.from_utc_datetime(&n)))
+}
+
 pub fn standing_default() -> u32 {
     STANDING_UNKNOWN_MAGIC_NUMBER
 }
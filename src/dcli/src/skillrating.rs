@@ -0,0 +1,322 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//Team based skill ratings, calculated using Glicko-2:
+//http://www.glicko.net/glicko/glicko2.pdf
+//
+//Glicko-2 rates players against individual opponents, so for team activities
+//each player is rated as if they played a single game against a composite
+//opponent made up of the opposing team, with their expected result based on
+//the average rating of their own team.
+
+use std::f64::consts::PI;
+
+pub const DEFAULT_RATING: f64 = 1500.0;
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+
+/// Ratings with a deviation above this are provisional, as not enough
+/// activities have been played to be confident in them.
+pub const PROVISIONAL_DEVIATION: f64 = 110.0;
+
+//converts between the rating scale and the Glicko-2 scale
+const GLICKO2_SCALE: f64 = 173.7178;
+
+//constrains how much the volatility can change after each activity
+const TAU: f64 = 0.5;
+
+const CONVERGENCE_TOLERANCE: f64 = 0.000001;
+
+//number of seconds in a rating period. Deviations increase for each period
+//a player doesnt play
+const RATING_PERIOD_SECONDS: f64 = 86400.0;
+
+/// A player's Glicko-2 skill rating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkillRating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for SkillRating {
+    fn default() -> Self {
+        SkillRating {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+impl SkillRating {
+    pub fn is_provisional(&self) -> bool {
+        self.deviation > PROVISIONAL_DEVIATION
+    }
+
+    /// Returns the rating with its deviation increased for the time since
+    /// the player last played, as we are less certain of their skill.
+    pub fn with_inactivity(&self, seconds: i64) -> SkillRating {
+        let periods = (seconds as f64 / RATING_PERIOD_SECONDS).max(0.0);
+        let phi = self.deviation / GLICKO2_SCALE;
+        let phi =
+            (phi * phi + periods * self.volatility * self.volatility).sqrt();
+
+        SkillRating {
+            deviation: (phi * GLICKO2_SCALE).min(DEFAULT_DEVIATION),
+            ..*self
+        }
+    }
+
+    //returns the rating after games against opponents with the specified
+    //rating and deviation (on the Glicko-2 scale), and score against each.
+    //team_mu is the rating the expected results are based on.
+    fn update(&self, team_mu: f64, results: &[(f64, f64, f64)]) -> SkillRating {
+        let mu = (self.rating - DEFAULT_RATING) / GLICKO2_SCALE;
+        let phi = self.deviation / GLICKO2_SCALE;
+
+        let mut v_inverse = 0.0;
+        let mut improvement = 0.0;
+
+        for (opponent_mu, opponent_phi, score) in results.iter() {
+            let g = 1.0
+                / (1.0 + 3.0 * opponent_phi * opponent_phi / (PI * PI)).sqrt();
+            let expected = 1.0 / (1.0 + (-g * (team_mu - opponent_mu)).exp());
+
+            v_inverse += g * g * expected * (1.0 - expected);
+            improvement += g * (score - expected);
+        }
+
+        let v = 1.0 / v_inverse;
+
+        //the results were so certain that there is nothing to learn from them
+        if !v.is_finite() {
+            return *self;
+        }
+
+        let delta = v * improvement;
+
+        let volatility = self.updated_volatility(phi, delta, v);
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu = mu + phi * phi * improvement;
+
+        SkillRating {
+            rating: mu * GLICKO2_SCALE + DEFAULT_RATING,
+            deviation: (phi * GLICKO2_SCALE).min(DEFAULT_DEVIATION),
+            volatility,
+        }
+    }
+
+    //calculates the new volatility using the Illinois algorithm (step 5 of
+    //the Glicko-2 paper)
+    fn updated_volatility(&self, phi: f64, delta: f64, v: f64) -> f64 {
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + v + ex;
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d)
+                - (x - a) / (TAU * TAU)
+        };
+
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+
+        while (big_b - big_a).abs() > CONVERGENCE_TOLERANCE {
+            let c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(c);
+
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+
+            big_b = c;
+            f_b = f_c;
+        }
+
+        (big_a / 2.0).exp()
+    }
+}
+
+/// Returns the new ratings for the players on two teams after an activity.
+///
+/// score is the result for the first team (1.0 for a win, 0.5 for a draw and
+/// 0.0 for a loss). Ratings are returned in the same order as the teams and
+/// players. Both teams must have at least one player.
+pub fn rate_teams(
+    first: &[SkillRating],
+    second: &[SkillRating],
+    score: f64,
+) -> (Vec<SkillRating>, Vec<SkillRating>) {
+    let (first_mu, first_phi) = composite(first);
+    let (second_mu, second_phi) = composite(second);
+
+    let first = first
+        .iter()
+        .map(|e| e.update(first_mu, &[(second_mu, second_phi, score)]))
+        .collect();
+
+    let second = second
+        .iter()
+        .map(|e| e.update(second_mu, &[(first_mu, first_phi, 1.0 - score)]))
+        .collect();
+
+    (first, second)
+}
+
+//returns the average rating and deviation (on the Glicko-2 scale) of the team
+fn composite(team: &[SkillRating]) -> (f64, f64) {
+    let count = team.len() as f64;
+
+    let mu = team
+        .iter()
+        .map(|e| (e.rating - DEFAULT_RATING) / GLICKO2_SCALE)
+        .sum::<f64>()
+        / count;
+
+    let phi = (team
+        .iter()
+        .map(|e| (e.deviation / GLICKO2_SCALE).powi(2))
+        .sum::<f64>()
+        / count)
+        .sqrt();
+
+    (mu, phi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glicko2(rating: f64, deviation: f64) -> (f64, f64) {
+        (
+            (rating - DEFAULT_RATING) / GLICKO2_SCALE,
+            deviation / GLICKO2_SCALE,
+        )
+    }
+
+    fn assert_near(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() < tolerance, "{} != {}", a, b);
+    }
+
+    //example from section 3 of the Glicko-2 paper
+    #[test]
+    fn test_update_paper_example() {
+        let player = SkillRating {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+
+        let (a_mu, a_phi) = glicko2(1400.0, 30.0);
+        let (b_mu, b_phi) = glicko2(1550.0, 100.0);
+        let (c_mu, c_phi) = glicko2(1700.0, 300.0);
+
+        let out = player.update(
+            0.0,
+            &[(a_mu, a_phi, 1.0), (b_mu, b_phi, 0.0), (c_mu, c_phi, 0.0)],
+        );
+
+        assert_near(out.rating, 1464.06, 0.01);
+        assert_near(out.deviation, 151.52, 0.01);
+        assert_near(out.volatility, 0.05999, 0.00001);
+    }
+
+    #[test]
+    fn test_rate_teams_symmetry() {
+        let first = [
+            SkillRating {
+                rating: 1600.0,
+                deviation: 80.0,
+                volatility: 0.06,
+            },
+            SkillRating::default(),
+        ];
+        let second = [
+            SkillRating {
+                rating: 1450.0,
+                deviation: 120.0,
+                volatility: 0.06,
+            },
+            SkillRating {
+                rating: 1550.0,
+                deviation: 60.0,
+                volatility: 0.06,
+            },
+        ];
+
+        //a win for one team is the same as a loss for the other
+        let (win_first, win_second) = rate_teams(&first, &second, 1.0);
+        let (loss_second, loss_first) = rate_teams(&second, &first, 0.0);
+        assert_eq!(win_first, loss_first);
+        assert_eq!(win_second, loss_second);
+
+        for (before, after) in first.iter().zip(win_first.iter()) {
+            assert!(after.rating > before.rating);
+            assert!(after.deviation < before.deviation);
+        }
+        for (before, after) in second.iter().zip(win_second.iter()) {
+            assert!(after.rating < before.rating);
+        }
+
+        let (draw_first, draw_second) = rate_teams(&first, &second, 0.5);
+        let (draw_second_swapped, draw_first_swapped) =
+            rate_teams(&second, &first, 0.5);
+        assert_eq!(draw_first, draw_first_swapped);
+        assert_eq!(draw_second, draw_second_swapped);
+
+        //the higher rated team is expected to win, so a draw lowers their
+        //ratings and raises the others
+        for (before, after) in first.iter().zip(draw_first.iter()) {
+            assert!(after.rating < before.rating);
+        }
+        for (before, after) in second.iter().zip(draw_second.iter()) {
+            assert!(after.rating > before.rating);
+        }
+    }
+
+    #[test]
+    fn test_rate_teams_even_draw() {
+        let team = [SkillRating::default(), SkillRating::default()];
+        let (first, second) = rate_teams(&team, &team, 0.5);
+
+        for e in first.iter().chain(second.iter()) {
+            assert_near(e.rating, DEFAULT_RATING, 0.000001);
+            assert!(e.deviation < DEFAULT_DEVIATION);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//Definitions for the portable activity store archive format.
+//
+//Archives are gzip compressed JSON lines files. The first line is a header
+//record, and each following line is a single record with a "type" field
+//specifying the kind of record. Records reference each other by their Bungie
+//ids (member, character and activity ids) rather than internal row ids, so
+//archives can be imported into stores with a different schema version.
+//
+//Records are written in the order they need to be imported in order to
+//satisfy the store's foreign keys.
+
+pub const ARCHIVE_FORMAT: &str = "dcli-activity-archive";
+
+//increment if the format of the records changes in a way which older
+//versions cannot read
+pub const ARCHIVE_VERSION: u32 = 1;
+pub const ARCHIVE_HEADER_TYPE: &str = "header";
+
+pub struct ArchiveTable {
+    //value of the type field for records from this table
+    pub name: &'static str,
+
+    //selects the records to export. Expects the temp archive_* tables
+    //to be populated with the ids to export
+    pub export_sql: &'static str,
+
+    //inserts a record, with the record fields bound in the order of
+    //import_fields
+    pub import_sql: &'static str,
+    pub import_fields: &'static [&'static str],
+
+    //selects a description of the conflict if the record conflicts with
+    //existing data, with the record fields bound in the order of
+    //import_fields. The existing data is kept
+    pub conflict_sql: Option<&'static str>,
+}
+
+pub const ARCHIVE_TABLES: &[ArchiveTable] = &[
+    ArchiveTable {
+        name: "member",
+        export_sql: r#"
+            SELECT
+                member_id, platform_id, display_name,
+                bungie_display_name, bungie_display_name_code
+            FROM main.member
+            WHERE member_id IN (SELECT member_id FROM archive_member)
+        "#,
+        import_sql: r#"
+            INSERT INTO main.member
+                ("member_id", "platform_id", "display_name",
+                "bungie_display_name", "bungie_display_name_code")
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(member_id) DO UPDATE SET
+                display_name =
+                    coalesce(member.display_name, excluded.display_name),
+                bungie_display_name = coalesce(member.bungie_display_name,
+                    excluded.bungie_display_name),
+                bungie_display_name_code =
+                    coalesce(member.bungie_display_name_code,
+                        excluded.bungie_display_name_code)
+            WHERE
+                (member.bungie_display_name IS NULL AND
+                    excluded.bungie_display_name IS NOT NULL) OR
+                (member.bungie_display_name_code IS NULL AND
+                    excluded.bungie_display_name_code IS NOT NULL) OR
+                (member.display_name IS NULL AND
+                    excluded.display_name IS NOT NULL)
+        "#,
+        import_fields: &[
+            "member_id",
+            "platform_id",
+            "display_name",
+            "bungie_display_name",
+            "bungie_display_name_code",
+        ],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "member_link",
+        export_sql: r#"
+            SELECT member, identity, has_profile
+            FROM main.member_link
+            WHERE member IN (SELECT member_id FROM archive_member)
+        "#,
+        //has_profile is missing from archives exported before it was added.
+        //It is set when the links are next refreshed
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.member_link
+                ("member", "identity", "has_profile")
+            VALUES (?, ?, coalesce(?, 0))
+        "#,
+        import_fields: &["member", "identity", "has_profile"],
+        conflict_sql: Some(
+            r#"
+            SELECT
+                printf('Member %d link : identity %d (local) / identity %d (restored)',
+                    l.member, l.identity, r.identity
+                ) as description
+            FROM
+                main.member_link as l
+            INNER JOIN
+                (SELECT ? as member, ? as identity, ? as has_profile) as r
+                    ON l.member = r.member
+            WHERE
+                l.identity != r.identity
+        "#,
+        ),
+    },
+    ArchiveTable {
+        name: "member_name",
+        export_sql: r#"
+            SELECT
+                member, bungie_display_name, bungie_display_name_code,
+                first_seen, last_seen
+            FROM main.member_name
+            WHERE member IN (SELECT member_id FROM archive_member)
+        "#,
+        import_sql: r#"
+            INSERT INTO main.member_name
+                ("member", "bungie_display_name", "bungie_display_name_code",
+                "first_seen", "last_seen")
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(member, bungie_display_name, bungie_display_name_code)
+            DO UPDATE SET
+                first_seen = min(first_seen, excluded.first_seen),
+                last_seen = max(last_seen, excluded.last_seen)
+            WHERE
+                excluded.first_seen < member_name.first_seen OR
+                excluded.last_seen > member_name.last_seen
+        "#,
+        import_fields: &[
+            "member",
+            "bungie_display_name",
+            "bungie_display_name_code",
+            "first_seen",
+            "last_seen",
+        ],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "character",
+        export_sql: r#"
+            SELECT character_id, member, class
+            FROM main.character
+            WHERE character_id IN
+                (SELECT character_id FROM archive_character)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.character
+                ("character_id", "member", "class")
+            VALUES (?, ?, ?)
+        "#,
+        import_fields: &["character_id", "member", "class"],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "sync",
+        export_sql: r#"
+            SELECT
+                member, last_sync, sync_since, max_activities,
+                history_since, history_max_activities
+            FROM main.sync
+            WHERE member IN (SELECT member FROM archive_sync)
+        "#,
+        import_sql: r#"
+            INSERT INTO main.sync (
+                "member", "last_sync", "sync_since", "max_activities",
+                "history_since", "history_max_activities"
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(member) DO UPDATE SET
+                last_sync = excluded.last_sync
+            WHERE
+                excluded.last_sync > sync.last_sync
+        "#,
+        import_fields: &[
+            "member",
+            "last_sync",
+            "sync_since",
+            "max_activities",
+            "history_since",
+            "history_max_activities",
+        ],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "activity",
+        export_sql: r#"
+            SELECT
+                activity_id, period, mode, platform,
+                director_activity_hash, reference_id, detail_pending
+            FROM main.activity
+            WHERE activity_id IN (SELECT activity_id FROM archive_activity)
+            ORDER BY period
+        "#,
+        //detail_pending is missing from archives exported before it was
+        //added, which only contained activities with details
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.activity
+                ("activity_id", "period", "mode", "platform",
+                "director_activity_hash", "reference_id", "detail_pending")
+            VALUES (?, ?, ?, ?, ?, ?, coalesce(?, 0))
+        "#,
+        import_fields: &[
+            "activity_id",
+            "period",
+            "mode",
+            "platform",
+            "director_activity_hash",
+            "reference_id",
+            "detail_pending",
+        ],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "mode",
+        export_sql: r#"
+            SELECT activity, mode
+            FROM main.modes
+            WHERE activity IN (SELECT activity_id FROM archive_activity)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.modes ("activity", "mode")
+            VALUES (?, ?)
+        "#,
+        import_fields: &["activity", "mode"],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "team_result",
+        export_sql: r#"
+            SELECT activity, team_id, score, standing
+            FROM main.team_result
+            WHERE activity IN (SELECT activity_id FROM archive_activity)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.team_result
+                ("activity", "team_id", "score", "standing")
+            VALUES (?, ?, ?, ?)
+        "#,
+        import_fields: &["activity", "team_id", "score", "standing"],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "character_activity_stats",
+        export_sql: r#"
+            SELECT
+                character, activity, assists, score, kills,
+                deaths, average_score_per_kill, average_score_per_life,
+                completed, opponents_defeated,
+                activity_duration_seconds, standing, team,
+                completion_reason, start_seconds, time_played_seconds,
+                player_count, team_score, precision_kills,
+                weapon_kills_ability, weapon_kills_grenade,
+                weapon_kills_melee, weapon_kills_super,
+                all_medals_earned, light_level, emblem_hash,
+                fireteam_id
+            FROM main.character_activity_stats
+            WHERE activity IN (SELECT activity_id FROM archive_activity)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.character_activity_stats
+                ("character", "activity", "assists", "score", "kills",
+                "deaths", "average_score_per_kill", "average_score_per_life",
+                "completed", "opponents_defeated",
+                "activity_duration_seconds", "standing", "team",
+                "completion_reason", "start_seconds", "time_played_seconds",
+                "player_count", "team_score", "precision_kills",
+                "weapon_kills_ability", "weapon_kills_grenade",
+                "weapon_kills_melee", "weapon_kills_super",
+                "all_medals_earned", "light_level", "emblem_hash",
+                "fireteam_id")
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        import_fields: &[
+            "character",
+            "activity",
+            "assists",
+            "score",
+            "kills",
+            "deaths",
+            "average_score_per_kill",
+            "average_score_per_life",
+            "completed",
+            "opponents_defeated",
+            "activity_duration_seconds",
+            "standing",
+            "team",
+            "completion_reason",
+            "start_seconds",
+            "time_played_seconds",
+            "player_count",
+            "team_score",
+            "precision_kills",
+            "weapon_kills_ability",
+            "weapon_kills_grenade",
+            "weapon_kills_melee",
+            "weapon_kills_super",
+            "all_medals_earned",
+            "light_level",
+            "emblem_hash",
+            "fireteam_id",
+        ],
+        conflict_sql: None,
+    },
+    //weapon and medal results are keyed by activity and character, since
+    //character_activity_stats ids are specific to each store
+    ArchiveTable {
+        name: "weapon_result",
+        export_sql: r#"
+            SELECT
+                s.activity, s.character, w.reference_id, w.kills,
+                w.precision_kills, w.kills_precision_kills_ratio
+            FROM main.weapon_result as w
+            INNER JOIN main.character_activity_stats as s
+                ON w.character_activity_stats = s.id
+            WHERE s.activity IN (SELECT activity_id FROM archive_activity)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.weapon_result
+                ("reference_id", "kills", "precision_kills",
+                "kills_precision_kills_ratio", "character_activity_stats")
+            SELECT ?, ?, ?, ?, id
+            FROM main.character_activity_stats
+            WHERE activity = ? AND character = ?
+        "#,
+        import_fields: &[
+            "reference_id",
+            "kills",
+            "precision_kills",
+            "kills_precision_kills_ratio",
+            "activity",
+            "character",
+        ],
+        conflict_sql: None,
+    },
+    ArchiveTable {
+        name: "medal_result",
+        export_sql: r#"
+            SELECT s.activity, s.character, m.reference_id, m.count
+            FROM main.medal_result as m
+            INNER JOIN main.character_activity_stats as s
+                ON m.character_activity_stats = s.id
+            WHERE s.activity IN (SELECT activity_id FROM archive_activity)
+        "#,
+        import_sql: r#"
+            INSERT OR IGNORE INTO main.medal_result
+                ("reference_id", "count", "character_activity_stats")
+            SELECT ?, ?, id
+            FROM main.character_activity_stats
+            WHERE activity = ? AND character = ?
+        "#,
+        import_fields: &["reference_id", "count", "activity", "character"],
+        conflict_sql: None,
+    },
+];
@@ -26,7 +26,9 @@ use std::io::stdout;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::enums::averageweighting::AverageWeighting;
 use crate::enums::mode::Mode;
+use crate::output::Output;
 use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc};
 use crossterm::{execute, terminal};
 use std::str::FromStr;
@@ -118,6 +120,44 @@ pub fn calculate_average(value: u32, total_activities: u32) -> f32 {
     value as f32 / total_activities as f32
 }
 
+//returns value per minute of time played
+pub fn calculate_per_minute(value: u32, time_played_seconds: u32) -> f32 {
+    if time_played_seconds == 0 {
+        return 0.0;
+    }
+
+    value as f32 / (time_played_seconds as f32 / 60.0)
+}
+
+pub fn calculate_per_ten_minutes(value: u32, time_played_seconds: u32) -> f32 {
+    calculate_per_minute(value, time_played_seconds) * 10.0
+}
+
+//returns the per game average for value. When weighted by time played, games
+//are counted by the portion of the activity the player was actually in.
+pub fn calculate_weighted_average(
+    value: u32,
+    total_activities: u32,
+    time_played_seconds: u32,
+    activity_duration_seconds: u32,
+    weighting: &AverageWeighting,
+) -> f32 {
+    match weighting {
+        AverageWeighting::Game => calculate_average(value, total_activities),
+        AverageWeighting::TimePlayed => {
+            if time_played_seconds == 0 || activity_duration_seconds == 0 {
+                return 0.0;
+            }
+
+            let games = time_played_seconds as f32
+                / activity_duration_seconds as f32
+                * total_activities as f32;
+
+            value as f32 / games
+        }
+    }
+}
+
 pub fn calculate_efficiency(kills: u32, deaths: u32, assists: u32) -> f32 {
     let t = (kills + assists) as f32;
     if deaths > 0 {
@@ -156,6 +196,24 @@ pub fn format_f32(val: f32, precision: usize) -> String {
     format!("{:.p$}", val, p = precision)
 }
 
+//formats a number of bytes as a human readable size, i.e. 4.2 GB
+pub fn format_file_size(bytes: u64) -> String {
+    let units = ["bytes", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut index = 0;
+    while size >= 1024.0 && index < units.len() - 1 {
+        size /= 1024.0;
+        index += 1;
+    }
+
+    if index == 0 {
+        return format!("{} {}", bytes, units[index]);
+    }
+
+    format!("{:.1} {}", size, units[index])
+}
+
 pub fn repeat_str(s: &str, count: usize) -> String {
     s.repeat(count)
 }
@@ -242,6 +300,17 @@ pub fn build_tsv(name_values: Vec<(&str, String)>) -> String {
         .collect()
 }
 
+pub fn build_json(name_values: Vec<(&str, String)>) -> String {
+    let map: serde_json::Map<String, serde_json::Value> = name_values
+        .into_iter()
+        .map(|(name, value)| {
+            (name.to_string(), serde_json::Value::String(value))
+        })
+        .collect();
+
+    serde_json::Value::Object(map).to_string()
+}
+
 pub fn get_destiny2_launch_date() -> DateTime<Utc> {
     Utc.with_ymd_and_hms(2017, 9, 6, 17, 0, 0).unwrap()
 }
@@ -280,14 +349,17 @@ fn find_previous_moment(
     now - Duration::seconds((now - past_reset).num_seconds() % interval)
 }
 
+//returns the system appropriate data directory, without creating it
+pub fn default_data_dir() -> Result<PathBuf, Error> {
+    let dld =
+        dirs_next::data_local_dir().ok_or(Error::SystemDirectoryNotFound)?;
+    Ok(dld.join("dcli"))
+}
+
 pub fn determine_data_dir(dir: Option<PathBuf>) -> Result<PathBuf, Error> {
     let path = match dir {
         Some(e) => e,
-        None => {
-            let dld = dirs_next::data_local_dir()
-                .ok_or(Error::SystemDirectoryNotFound)?;
-            dld.join("dcli")
-        }
+        None => default_data_dir()?,
     };
 
     if !path.exists() {
@@ -348,6 +420,17 @@ pub fn parse_rfc3339(src: &str) -> Result<DateTime<Utc>, String> {
     Ok(d)
 }
 
+//for tools which only support default and tsv output
+pub fn parse_and_validate_tsv_output(src: &str) -> Result<Output, String> {
+    let output = Output::from_str(src)?;
+
+    if output == Output::Json {
+        return Err(format!("Unsupported output format specified : {}", src));
+    }
+
+    Ok(output)
+}
+
 pub fn parse_and_validate_crucible_mode(src: &str) -> Result<Mode, String> {
     let mode = Mode::from_str(src)?;
 
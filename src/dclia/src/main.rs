@@ -26,21 +26,31 @@ use std::path::PathBuf;
 
 use dcli::activitystoreinterface::ActivityStoreInterface;
 use dcli::apiinterface::ApiInterface;
-use dcli::crucible::{Member, PlayerName};
+use dcli::crucible::{Member, PlayerSelector};
 use dcli::manifest::definitions::{
     ActivityDefinitionData, DestinationDefinitionData, PlaceDefinitionData,
 };
 //use dcli::error::Error;
+use dcli::config::args_with_config;
 use dcli::enums::mode::Mode;
 use dcli::manifestinterface::ManifestInterface;
 use dcli::output::Output;
 use dcli::response::gpr::CharacterActivitiesData;
-use dcli::utils::{build_tsv, determine_data_dir};
+use dcli::utils::{
+    build_tsv, determine_data_dir, parse_and_validate_tsv_output,
+};
 use dcli::utils::{format_error, EXIT_FAILURE};
 use structopt::StructOpt;
 
 const ORBIT_PLACE_HASH: u32 = 2961497387;
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("name", Some('n')),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving current Destiny 2 activity status for player.
@@ -61,8 +71,12 @@ struct Opt {
     /// Name must be in the format of NAME#CODE. Example: foo#3280
     /// You can find your name in game, or on Bungie's site at:
     /// https://www.bungie.net/7/en/User/Account/IdentitySettings
+    ///
+    /// Can also be a Destiny membership id, an alias set via dclisync
+    /// --alias, or the start of the name of a player in the data store (case
+    /// insensitive). Example: foo
     #[structopt(long = "name", short = "n", required = true)]
-    name: PlayerName,
+    name: PlayerSelector,
 
     ///Print out additional information
 
@@ -86,8 +100,10 @@ struct Opt {
     #[structopt(
         short = "O",
         long = "output-format",
+        parse(try_from_str = parse_and_validate_tsv_output),
         default_value = "default"
     )]
+    //json is rejected by parse_and_validate_tsv_output
     output: Output,
 
     /// API key from Bungie required for some actions.
@@ -97,11 +113,18 @@ struct Opt {
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
-}
 
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
+}
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dclia", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -110,6 +133,10 @@ async fn main() {
     };
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     tell::verbose!("{:#?}", opt);
 
     let data_dir = match determine_data_dir(opt.data_dir) {
@@ -137,7 +164,7 @@ async fn main() {
             }
         };
 
-    let member: Member = match store.find_member(&opt.name, true).await {
+    let member: Member = match store.resolve_member(&opt.name, true).await {
         Ok(e) => e,
         Err(e) => {
             tell::error!("{}", format_error(
@@ -181,8 +208,10 @@ async fn main() {
                     tell::update!("Not currently in an activity");
                 }
                 Output::Tsv => {
-                    print_tsv_no_activity();
+                    print_name_values_no_activity();
                 }
+                //rejected by parse_and_validate_tsv_output
+                Output::Json => unreachable!(),
             };
             return;
         }
@@ -227,8 +256,10 @@ async fn main() {
                 tell::update!("{}", get_in_orbit_human());
             }
             Output::Tsv => {
-                print_tsv_orbit();
+                print_name_values_orbit();
             }
+            //rejected by parse_and_validate_tsv_output
+            Output::Json => unreachable!(),
         };
 
         return;
@@ -347,7 +378,7 @@ async fn main() {
             );
         }
         Output::Tsv => {
-            print_tsv(
+            print_name_values(
                 mode,
                 &activity_type_name,
                 &activity_name,
@@ -357,18 +388,20 @@ async fn main() {
                 true,
             );
         }
+        //rejected by parse_and_validate_tsv_output
+        Output::Json => unreachable!(),
     };
 }
 
-fn print_tsv_orbit() {
-    print_tsv(Mode::None, "", "", "Orbit", "", "", true);
+fn print_name_values_orbit() {
+    print_name_values(Mode::None, "", "", "Orbit", "", "", true);
 }
 
-fn print_tsv_no_activity() {
-    print_tsv(Mode::None, "", "", "", "", "", false);
+fn print_name_values_no_activity() {
+    print_name_values(Mode::None, "", "", "", "", "", false);
 }
 
-fn print_tsv(
+fn print_name_values(
     mode: Mode,
     activity_type_name: &str,
     activity_name: &str,
@@ -25,13 +25,12 @@ use tell::{Tell, TellLevel};
 use std::str::FromStr;
 use std::{collections::HashMap, path::PathBuf};
 
-use dcli::crucible::{Member, PlayerName};
+use dcli::crucible::{Member, PlayerSelector};
 use dcli::utils::{format_error, truncate_ascii_string};
 use dcli::{
-    apiinterface::ApiInterface,
     crucible::{
         AggregateCruciblePerformances, CrucibleActivity,
-        CruciblePlayerPerformance, Player,
+        CruciblePlayerPerformance,
     },
     enums::completionreason::CompletionReason,
     utils::{calculate_avg, f32_are_equal},
@@ -40,16 +39,21 @@ use dcli::{
 use dcli::enums::mode::Mode;
 use dcli::manifestinterface::ManifestInterface;
 
+use dcli::enums::averageweighting::AverageWeighting;
 use dcli::enums::character::CharacterClassSelection;
 use dcli::error::Error;
 
-use dcli::activitystoreinterface::ActivityStoreInterface;
+use dcli::activitystoreinterface::{
+    ActivityStoreInterface, CombatRating, SkillRatingEntry,
+};
+use dcli::skillrating::PROVISIONAL_DEVIATION;
 
 use dcli::utils::{
     determine_data_dir, format_f32, human_date_format, human_duration,
     repeat_str,
 };
 
+use dcli::config::args_with_config;
 use dcli::utils::EXIT_FAILURE;
 use structopt::StructOpt;
 
@@ -78,43 +82,70 @@ fn generate_score(data: &CrucibleActivity) -> String {
     tokens.join("")
 }
 
-async fn get_combat_ratings(data: &CrucibleActivity) -> HashMap<u64, f32> {
-    let mut players: Vec<&Player> = Vec::new();
-
-    for t in data.teams.values() {
-        for p in &t.player_performances {
-            players.push(&p.player);
+//returns combat ratings for the players in the activity, keyed by character id
+async fn get_combat_ratings(
+    store: &mut ActivityStoreInterface,
+    data: &CrucibleActivity,
+    ttl_hours: u32,
+) -> HashMap<i64, CombatRating> {
+    let ttl = chrono::Duration::hours(ttl_hours as i64);
+    match store.retrieve_combat_ratings(data, &ttl).await {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not retrieve combat ratings.", e)
+            );
+            HashMap::new()
         }
     }
+}
 
-    let elo_hash: HashMap<u64, f32> = match ApiInterface::new() {
-        Ok(e) => {
-            let mut player_refs: Vec<&Player> = Vec::new();
-            for t in data.teams.values() {
-                for p in &t.player_performances {
-                    player_refs.push(&p.player);
-                }
-            }
-
-            match e
-                .retrieve_combat_ratings(&player_refs, &data.details.mode)
-                .await
-            {
-                Ok(e) => e,
-                Err(_e) => HashMap::new(),
-            }
+//returns the skill ratings for the players in the activity, keyed by member
+//id. Ratings are calculated by dclisync
+async fn get_skill_ratings(
+    store: &mut ActivityStoreInterface,
+    activity_id: i64,
+) -> HashMap<i64, SkillRatingEntry> {
+    match store.retrieve_activity_skill_ratings(activity_id).await {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not retrieve skill ratings.", e)
+            );
+            HashMap::new()
         }
-        Err(_e) => HashMap::new(),
+    }
+}
+
+//formats a skill rating, marking provisional ratings with a ?
+fn format_skill_rating(rating: f64, deviation: f64) -> String {
+    let provisional = if deviation > PROVISIONAL_DEVIATION {
+        "?"
+    } else {
+        ""
     };
-    elo_hash
+
+    format!("{:.0}{}", rating, provisional)
+}
+
+fn format_avg_skill_rating(total: f64, count: u32) -> String {
+    if count == 0 {
+        return "".to_string();
+    }
+
+    format!("{:.0}", total / count as f64)
 }
 
 fn print_default(
     data: &CrucibleActivity,
-    elo_hash: &HashMap<u64, f32>,
+    ratings: &HashMap<i64, CombatRating>,
+    skill_ratings: &HashMap<i64, SkillRatingEntry>,
     member: &Member,
     details: bool,
     weapon_count: u32,
+    weighting: &AverageWeighting,
 ) {
     let member_id = &member.id;
 
@@ -154,10 +185,14 @@ fn print_default(
 
     tell::verbose!("Activity ID : {}", data.details.id);
 
+    if data.details.detail_pending {
+        tell::update!("Activity details are still being synced. Only synced players are included, without their supers, grenades, melees or medals.");
+    }
+
     tell::update!("{}", standing_str);
     tell::update!("{} {}\n", generate_score(data), completion_reason);
 
-    let header = format!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+    let header = format!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
     "PLAYER",
     "KILLS",
     "ASTS",
@@ -171,6 +206,7 @@ fn print_default(
     "MEL",
     "MED",
     "RATING",
+    "SKILL",
     "STATUS",
     col_w=col_w,
     name_col_w = name_col_w,
@@ -184,9 +220,13 @@ fn print_default(
     let mut all_performances: Vec<&CruciblePlayerPerformance> = Vec::new();
     let mut elo_total_count = 0;
     let mut elo_total_total = 0.0;
+    let mut skill_total_count = 0;
+    let mut skill_total_total = 0.0;
     for v in data.teams.values() {
         let mut elo_team_count = 0;
         let mut elo_team_total = 0.0;
+        let mut skill_team_count = 0;
+        let mut skill_team_total = 0.0;
 
         tell::update!("[{}] {} Team {}!", v.score, v.display_name, v.standing);
         tell::update!("{}", team_title_border);
@@ -201,7 +241,10 @@ fn print_default(
         });
 
         for p in &player_performances {
-            let elo = *elo_hash.get(&p.player.calculate_hash()).unwrap_or(&0.0)
+            let elo = ratings
+                .get(&p.player.character_id)
+                .map(|e| e.rating)
+                .unwrap_or(0.0)
                 * ELO_SCALE;
 
             let mut elo_str = "".to_string();
@@ -215,8 +258,19 @@ fn print_default(
                 elo_str = format_f32(elo, 0);
             }
 
+            let mut skill_str = "".to_string();
+            if let Some(e) = skill_ratings.get(&p.player.member_id) {
+                skill_team_count += 1;
+                skill_team_total += e.pre_rating;
+
+                skill_total_count += 1;
+                skill_total_total += e.pre_rating;
+
+                skill_str = format_skill_rating(e.pre_rating, e.pre_deviation);
+            }
+
             let extended = p.stats.extended.as_ref().unwrap();
-            tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+            tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
                 truncate_ascii_string(&p.player.name.get_bungie_name(), name_col_w),
                 p.stats.kills.to_string(),
                 p.stats.assists.to_string(),
@@ -230,6 +284,7 @@ fn print_default(
                 extended.weapon_kills_melee.to_string(),
                 extended.all_medals_earned.to_string(),
                 elo_str,
+                skill_str,
                 p.stats.generate_status(),
                 col_w=col_w,
                 name_col_w = name_col_w,
@@ -325,7 +380,7 @@ fn print_default(
             format_f32(team_elo, 0)
         };
 
-        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
             "TOTAL",
             aggregate.kills.to_string(),
             aggregate.assists.to_string(),
@@ -340,24 +395,66 @@ fn print_default(
             aggregate.extended.as_ref().unwrap().all_medals_earned.to_string(),
             "",
             "",
+            "",
             col_w=col_w,
             name_col_w = name_col_w,
         );
 
-        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}\n",
+        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+            "PER MIN",
+            format_f32(aggregate.per_minute(aggregate.kills), 2),
+            format_f32(aggregate.per_minute(aggregate.assists), 2),
+            format_f32(aggregate.per_minute(aggregate.opponents_defeated), 2),
+            format_f32(aggregate.per_minute(aggregate.deaths), 2),
+            "",
+            "",
+            "",
+            format_f32(aggregate.per_minute(agg_supers), 2),
+            format_f32(aggregate.per_minute(agg_grenades), 2),
+            format_f32(aggregate.per_minute(agg_melees), 2),
+            "",
+            "",
+            "",
+            "",
+            col_w=col_w,
+            name_col_w = name_col_w,
+        );
+
+        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+            "PER 10 MIN",
+            format_f32(aggregate.per_ten_minutes(aggregate.kills), 2),
+            format_f32(aggregate.per_ten_minutes(aggregate.assists), 2),
+            format_f32(aggregate.per_ten_minutes(aggregate.opponents_defeated), 2),
+            format_f32(aggregate.per_ten_minutes(aggregate.deaths), 2),
+            "",
+            "",
+            "",
+            format_f32(aggregate.per_ten_minutes(agg_supers), 2),
+            format_f32(aggregate.per_ten_minutes(agg_grenades), 2),
+            format_f32(aggregate.per_ten_minutes(agg_melees), 2),
+            "",
+            "",
+            "",
+            "",
+            col_w=col_w,
+            name_col_w = name_col_w,
+        );
+
+        tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}\n",
             "AVG",
-            format_f32(aggregate.kills as f32 / player_performances.len() as f32, 2),
-            format_f32(aggregate.assists as f32 / player_performances.len() as f32,2),
-            format_f32(aggregate.opponents_defeated as f32 / player_performances.len() as f32,2),
-            format_f32(aggregate.deaths as f32 / player_performances.len() as f32,2),
+            format_f32(aggregate.average(aggregate.kills, weighting), 2),
+            format_f32(aggregate.average(aggregate.assists, weighting), 2),
+            format_f32(aggregate.average(aggregate.opponents_defeated, weighting), 2),
+            format_f32(aggregate.average(aggregate.deaths, weighting), 2),
             "",
             "",
             "",
-            format_f32(agg_supers as f32 / player_performances.len() as f32,2),
-            format_f32(agg_grenades as f32 / player_performances.len() as f32,2),
-            format_f32(agg_melees as f32 / player_performances.len() as f32,2),
-            format_f32(aggregate.extended.as_ref().unwrap().all_medals_earned as f32 / player_performances.len() as f32,2),
+            format_f32(aggregate.average(agg_supers, weighting), 2),
+            format_f32(aggregate.average(agg_grenades, weighting), 2),
+            format_f32(aggregate.average(agg_melees, weighting), 2),
+            format_f32(aggregate.average(aggregate.extended.as_ref().unwrap().all_medals_earned, weighting), 2),
             team_elo_str,
+            format_avg_skill_rating(skill_team_total, skill_team_count),
             "", //MAKE THIS REASON FOR COMPLETEION
             col_w=col_w,
             name_col_w = name_col_w,
@@ -380,7 +477,7 @@ fn print_default(
 
     tell::update!("{}", header);
     tell::update!("{}", header_border);
-    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
         "TOTAL",
         aggregate.kills.to_string(),
         aggregate.assists.to_string(),
@@ -394,6 +491,7 @@ fn print_default(
         agg_melees.to_string(),
         aggregate.extended.as_ref().unwrap().all_medals_earned.to_string(),
         "",
+        "",
         "", //MAKE THIS REASON FOR COMPLETEION
         col_w=col_w,
         name_col_w = name_col_w,
@@ -406,20 +504,61 @@ fn print_default(
         format_f32(total_elo, 0)
     };
 
-    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+    "PER MIN",
+    format_f32(aggregate.per_minute(aggregate.kills), 2),
+    format_f32(aggregate.per_minute(aggregate.assists), 2),
+    format_f32(aggregate.per_minute(aggregate.opponents_defeated), 2),
+    format_f32(aggregate.per_minute(aggregate.deaths), 2),
+    "",
+    "",
+    "",
+    format_f32(aggregate.per_minute(agg_supers), 2),
+    format_f32(aggregate.per_minute(agg_grenades), 2),
+    format_f32(aggregate.per_minute(agg_melees), 2),
+    "",
+    "",
+    "",
+    "",
+    col_w=col_w,
+    name_col_w = name_col_w,
+    );
+
+    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+    "PER 10 MIN",
+    format_f32(aggregate.per_ten_minutes(aggregate.kills), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.assists), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.opponents_defeated), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.deaths), 2),
+    "",
+    "",
+    "",
+    format_f32(aggregate.per_ten_minutes(agg_supers), 2),
+    format_f32(aggregate.per_ten_minutes(agg_grenades), 2),
+    format_f32(aggregate.per_ten_minutes(agg_melees), 2),
+    "",
+    "",
+    "",
+    "",
+    col_w=col_w,
+    name_col_w = name_col_w,
+    );
+
+    tell::update!("{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
     "AVG",
-    format_f32(aggregate.kills as f32 / all_performances.len() as f32, 2),
-    format_f32(aggregate.assists as f32 / all_performances.len() as f32,2),
-    format_f32(aggregate.opponents_defeated as f32 / all_performances.len() as f32,2),
-    format_f32(aggregate.deaths as f32 / all_performances.len() as f32,2),
+    format_f32(aggregate.average(aggregate.kills, weighting), 2),
+    format_f32(aggregate.average(aggregate.assists, weighting), 2),
+    format_f32(aggregate.average(aggregate.opponents_defeated, weighting), 2),
+    format_f32(aggregate.average(aggregate.deaths, weighting), 2),
     "",
     "",
     "",
-    format_f32(agg_supers as f32 / all_performances.len() as f32,2),
-    format_f32(agg_grenades as f32 / all_performances.len() as f32,2),
-    format_f32(agg_melees as f32 / all_performances.len() as f32,2),
-    format_f32(aggregate.extended.as_ref().unwrap().all_medals_earned as f32 / all_performances.len() as f32,2),
+    format_f32(aggregate.average(agg_supers, weighting), 2),
+    format_f32(aggregate.average(agg_grenades, weighting), 2),
+    format_f32(aggregate.average(agg_melees, weighting), 2),
+    format_f32(aggregate.average(aggregate.extended.as_ref().unwrap().all_medals_earned, weighting), 2),
     total_elo_str,
+    format_avg_skill_rating(skill_total_total, skill_total_count),
     "", //MAKE THIS REASON FOR COMPLETEION
     col_w=col_w,
     name_col_w = name_col_w,
@@ -466,9 +605,47 @@ fn print_default(
 
     tell::update!();
     tell::update!("STATUS : L - Joined late, E - Left early");
+
+    //ratings are cached, so may have been retrieved at different times
+    let oldest = ratings.values().map(|e| e.fetched).min();
+    let newest = ratings.values().map(|e| e.fetched).max();
+    if let (Some(oldest), Some(newest)) = (oldest, newest) {
+        let oldest = human_date_format(&oldest);
+        let newest = human_date_format(&newest);
+        if oldest == newest {
+            tell::update!("RATING : Combat rating as of {}", oldest);
+        } else {
+            tell::update!(
+                "RATING : Combat rating as of {} to {}",
+                oldest,
+                newest
+            );
+        }
+    }
+
+    if let Some(e) = skill_ratings.values().next() {
+        tell::update!(
+            "SKILL  : {} skill rating going into the activity, calculated from synced activities (? - Provisional)",
+            e.family
+        );
+    }
     tell::update!();
 }
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("name", Some('n')),
+    ("mode", Some('M')),
+    ("class", Some('C')),
+    ("weapon-count", Some('w')),
+    ("combat-rating-ttl", None),
+    ("weighting", None),
+    ("details", Some('d')),
+    ("sync", Some('s')),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving and viewing Destiny 2 Crucible activity details.
@@ -494,8 +671,12 @@ struct Opt {
     /// Name must be in the format of NAME#CODE. Example: foo#3280
     /// You can find your name in game, or on Bungie's site at:
     /// https://www.bungie.net/7/en/User/Account/IdentitySettings
+    ///
+    /// Can also be a Destiny membership id, an alias set via dclisync
+    /// --alias, or the start of the name of a player in the data store (case
+    /// insensitive). Example: foo
     #[structopt(long = "name", short = "n", required = true)]
-    name: PlayerName,
+    name: PlayerSelector,
 
     /// Activity mode from which to return last activity
     ///
@@ -539,6 +720,24 @@ struct Opt {
     #[structopt(long = "weapon-count", short = "w", default_value = "5")]
     weapon_count: u32,
 
+    /// Number of hours players' combat ratings are cached for
+    ///
+    /// Combat ratings are cached in the data store, and the first rating
+    /// retrieved after the activity was played is displayed. If there is none,
+    /// ratings which have not been retrieved within the specified number of
+    /// hours are retrieved again from the Bungie API.
+    #[structopt(long = "combat-rating-ttl", default_value = "24")]
+    combat_rating_ttl: u32,
+
+    /// How per player averages are calculated
+    ///
+    /// Valid values are game (default), which divides by the number of
+    /// players, and time_played, which weights each player by the portion of
+    /// the activity they were in (so late joins and early exits count as
+    /// partial players).
+    #[structopt(long = "weighting", default_value = "game")]
+    weighting: AverageWeighting,
+
     /// The activity id of the activity to display data about
     ///
     /// By default, the last activity will be displayed. The index can be retrieved
@@ -560,10 +759,18 @@ struct Opt {
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dcliad", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -572,6 +779,10 @@ async fn main() {
     };
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     tell::verbose!("{:#?}", opt);
 
     let data_dir = match determine_data_dir(opt.data_dir) {
@@ -613,7 +824,7 @@ async fn main() {
         }
     };
 
-    let member = match store.find_member(&opt.name, true).await {
+    let member = match store.resolve_member(&opt.name, true).await {
         Ok(e) => e,
         Err(e) => {
             tell::error!(
@@ -664,7 +875,17 @@ async fn main() {
         }
     };
 
-    let elo_hash = get_combat_ratings(&data).await;
-
-    print_default(&data, &elo_hash, &member, opt.details, opt.weapon_count);
+    let ratings =
+        get_combat_ratings(&mut store, &data, opt.combat_rating_ttl).await;
+    let skill_ratings = get_skill_ratings(&mut store, data.details.id).await;
+
+    print_default(
+        &data,
+        &ratings,
+        &skill_ratings,
+        &member,
+        opt.details,
+        opt.weapon_count,
+        &opt.weighting,
+    );
 }
@@ -25,7 +25,7 @@ use std::cmp::Ordering;
 use std::path::PathBuf;
 use tell::{Tell, TellLevel};
 
-use dcli::crucible::{Member, PlayerName};
+use dcli::crucible::{Member, PlayerSelector};
 use dcli::enums::standing::Standing;
 use dcli::enums::{
     completionreason::CompletionReason,
@@ -33,18 +33,20 @@ use dcli::enums::{
 };
 use dcli::manifestinterface::ManifestInterface;
 use dcli::utils::{
-    calculate_average, calculate_percent, format_error,
-    parse_and_validate_crucible_mode, parse_rfc3339, truncate_ascii_string,
+    calculate_percent, format_error, parse_and_validate_crucible_mode,
+    parse_rfc3339, truncate_ascii_string,
 };
 use dcli::{
     crucible::{
         AggregateCruciblePerformances, CruciblePlayerActivityPerformance,
-        CruciblePlayerPerformance,
+        CruciblePlayerPerformance, ExtendedCruciblePlayerActivityPerformances,
     },
     enums::mode::Mode,
     utils::{calculate_ratio, human_duration},
 };
 
+use dcli::activityfilter::{ActivityFilterOpt, ActivityWindow};
+use dcli::enums::averageweighting::AverageWeighting;
 use dcli::enums::character::CharacterClassSelection;
 use dcli::enums::weaponsort::WeaponSort;
 
@@ -55,6 +57,7 @@ use dcli::utils::{
     uppercase_first_char,
 };
 
+use dcli::config::args_with_config;
 use dcli::utils::EXIT_FAILURE;
 use num_format::{Locale, ToFormattedString};
 use structopt::StructOpt;
@@ -72,6 +75,8 @@ fn print_default(
     weapon_sort: &WeaponSort,
     medal_count: &u32,
     character_class_selection: &CharacterClassSelection,
+    weighting: &AverageWeighting,
+    window: &ActivityWindow,
 ) {
     let player_name = member.name.get_bungie_name();
 
@@ -84,6 +89,15 @@ fn print_default(
         performances.iter().map(|x| &x.performance).collect();
     let aggregate = AggregateCruciblePerformances::with_performances(&cpp);
 
+    //supers, grenades, melees, weapons and medals arent known until the
+    //activity details have been synced, so they are aggregated separately
+    let cpp: Vec<&CruciblePlayerPerformance> = performances
+        .iter()
+        .filter(|x| !x.activity_detail.detail_pending)
+        .map(|x| &x.performance)
+        .collect();
+    let detailed = AggregateCruciblePerformances::with_performances(&cpp);
+
     let activity_count = performances.len();
 
     let display_count = std::cmp::min(activity_count, *activity_limit as usize);
@@ -134,6 +148,10 @@ fn print_default(
         "Total time played is {}",
         human_duration(aggregate.time_played_seconds)
     );
+
+    if window.count.is_some() {
+        tell::update!("Limited to {}", window);
+    }
     tell::update!();
 
     if is_limited {
@@ -155,6 +173,18 @@ fn print_default(
             },
         );
     }
+
+    let pending_count = performances
+        .iter()
+        .filter(|e| e.activity_detail.detail_pending)
+        .count();
+    if pending_count > 0 {
+        tell::update!(
+            "Details for {} activit{} marked with * are still being synced. Their supers, grenades, melees, weapons and medals are not included.",
+            pending_count,
+            if pending_count == 1 { "y" } else { "ies" }
+        );
+    }
     tell::update!();
 
     let col_w = 8;
@@ -247,9 +277,24 @@ fn print_default(
         map_name = truncate_ascii_string(&map_name, map_col_w);
 
         let extended = activity.performance.stats.extended.as_ref().unwrap();
-        let supers = extended.weapon_kills_super;
-        let grenades = extended.weapon_kills_grenade;
-        let melees = extended.weapon_kills_melee;
+
+        //these arent known until the activity details have been synced
+        let (supers, grenades, melees, id) =
+            if activity.activity_detail.detail_pending {
+                (
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    format!("{}*", activity.activity_detail.id),
+                )
+            } else {
+                (
+                    extended.weapon_kills_super.to_string(),
+                    extended.weapon_kills_grenade.to_string(),
+                    extended.weapon_kills_melee.to_string(),
+                    activity.activity_detail.id.to_string(),
+                )
+            };
 
         let mercy_str = if activity.performance.stats.completion_reason
             == CompletionReason::Mercy
@@ -271,11 +316,11 @@ fn print_default(
             format_f32(activity.performance.stats.kills_deaths_ratio, 2),
             format_f32(activity.performance.stats.kills_deaths_assists, 2),
             format_f32(activity.performance.stats.efficiency, 2),
-            supers.to_string(),
-            grenades.to_string(),
-            melees.to_string(),
+            supers,
+            grenades,
+            melees,
             mercy_str,
-            activity.activity_detail.id.to_string(),
+            id,
             col_w = col_w,
             map_col_w=map_col_w,
             str_col_w=str_col_w,
@@ -284,7 +329,8 @@ fn print_default(
         );
     }
 
-    let extended = aggregate.extended.as_ref().unwrap();
+    let no_extended = ExtendedCruciblePlayerActivityPerformances::default();
+    let extended = detailed.extended.as_ref().unwrap_or(&no_extended);
     tell::update!("{}", repeat_str("-", header.chars().count()));
 
     tell::update!("{:<0map_col_w$}{:<0wl_col_w$}{:>0str_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0id_col_w$}",
@@ -339,16 +385,16 @@ fn print_default(
     "PER GAME",
     format!("{}%", format_f32(aggregate.win_rate, 2)),
     "",
-    format_f32(calculate_average(aggregate.kills, aggregate.total_activities), 2),
-    format_f32(calculate_average(aggregate.assists, aggregate.total_activities), 2),
-    format_f32(calculate_average(aggregate.opponents_defeated, aggregate.total_activities), 2),
-    format_f32(calculate_average(aggregate.deaths, aggregate.total_activities), 2),
+    format_f32(aggregate.average(aggregate.kills, weighting), 2),
+    format_f32(aggregate.average(aggregate.assists, weighting), 2),
+    format_f32(aggregate.average(aggregate.opponents_defeated, weighting), 2),
+    format_f32(aggregate.average(aggregate.deaths, weighting), 2),
     format_f32(aggregate.kills_deaths_ratio, 2),
     format_f32(aggregate.kills_deaths_assists, 2),
     format_f32(aggregate.efficiency, 2),
-    format_f32(calculate_average(extended.weapon_kills_super, aggregate.total_activities), 2),
-    format_f32(calculate_average(extended.weapon_kills_grenade, aggregate.total_activities), 2),
-    format_f32(calculate_average(extended.weapon_kills_melee, aggregate.total_activities), 2),
+    format_f32(detailed.average(extended.weapon_kills_super, weighting), 2),
+    format_f32(detailed.average(extended.weapon_kills_grenade, weighting), 2),
+    format_f32(detailed.average(extended.weapon_kills_melee, weighting), 2),
     format!("{}%",format_f32(calculate_percent(aggregate.total_mercy, aggregate.total_activities), 2)),
     "",
     col_w = col_w,
@@ -358,6 +404,52 @@ fn print_default(
     id_col_w=id_col_w,
     );
 
+    tell::update!("{:<0map_col_w$}{:<0wl_col_w$}{:>0str_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0id_col_w$}",
+    "PER MIN",
+    "",
+    "",
+    format_f32(aggregate.per_minute(aggregate.kills), 2),
+    format_f32(aggregate.per_minute(aggregate.assists), 2),
+    format_f32(aggregate.per_minute(aggregate.opponents_defeated), 2),
+    format_f32(aggregate.per_minute(aggregate.deaths), 2),
+    "",
+    "",
+    "",
+    format_f32(detailed.per_minute(extended.weapon_kills_super), 2),
+    format_f32(detailed.per_minute(extended.weapon_kills_grenade), 2),
+    format_f32(detailed.per_minute(extended.weapon_kills_melee), 2),
+    "",
+    "",
+    col_w = col_w,
+    map_col_w=map_col_w,
+    str_col_w=str_col_w,
+    wl_col_w=wl_col_w,
+    id_col_w=id_col_w,
+    );
+
+    tell::update!("{:<0map_col_w$}{:<0wl_col_w$}{:>0str_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}{:>0id_col_w$}",
+    "PER 10 MIN",
+    "",
+    "",
+    format_f32(aggregate.per_ten_minutes(aggregate.kills), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.assists), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.opponents_defeated), 2),
+    format_f32(aggregate.per_ten_minutes(aggregate.deaths), 2),
+    "",
+    "",
+    "",
+    format_f32(detailed.per_ten_minutes(extended.weapon_kills_super), 2),
+    format_f32(detailed.per_ten_minutes(extended.weapon_kills_grenade), 2),
+    format_f32(detailed.per_ten_minutes(extended.weapon_kills_melee), 2),
+    "",
+    "",
+    col_w = col_w,
+    map_col_w=map_col_w,
+    str_col_w=str_col_w,
+    wl_col_w=wl_col_w,
+    id_col_w=id_col_w,
+    );
+
     tell::update!("{}", header_divider);
     tell::update!("{}", header);
 
@@ -554,6 +646,135 @@ fn print_default(
     }
 }
 
+fn print_comparison(
+    data: &[CruciblePlayerActivityPerformance],
+    previous_data: &[CruciblePlayerActivityPerformance],
+    window: &ActivityWindow,
+    previous_window: &ActivityWindow,
+    weighting: &AverageWeighting,
+) {
+    let cpp: Vec<&CruciblePlayerPerformance> =
+        data.iter().map(|x| &x.performance).collect();
+    let current = AggregateCruciblePerformances::with_performances(&cpp);
+
+    let cpp: Vec<&CruciblePlayerPerformance> =
+        previous_data.iter().map(|x| &x.performance).collect();
+    let previous = AggregateCruciblePerformances::with_performances(&cpp);
+
+    let rows: Vec<(&str, f32, f32, usize)> = vec![
+        (
+            "Games",
+            current.total_activities as f32,
+            previous.total_activities as f32,
+            0,
+        ),
+        ("Win %", current.win_rate, previous.win_rate, 2),
+        (
+            "KD",
+            current.kills_deaths_ratio,
+            previous.kills_deaths_ratio,
+            2,
+        ),
+        (
+            "KDA",
+            current.kills_deaths_assists,
+            previous.kills_deaths_assists,
+            2,
+        ),
+        ("Efficiency", current.efficiency, previous.efficiency, 2),
+        (
+            "Kills / game",
+            current.average(current.kills, weighting),
+            previous.average(previous.kills, weighting),
+            2,
+        ),
+        (
+            "Deaths / game",
+            current.average(current.deaths, weighting),
+            previous.average(previous.deaths, weighting),
+            2,
+        ),
+        (
+            "Assists / game",
+            current.average(current.assists, weighting),
+            previous.average(previous.assists, weighting),
+            2,
+        ),
+        (
+            "Kills / min",
+            current.per_minute(current.kills),
+            previous.per_minute(previous.kills),
+            2,
+        ),
+        (
+            "Deaths / min",
+            current.per_minute(current.deaths),
+            previous.per_minute(previous.deaths),
+            2,
+        ),
+    ];
+
+    let name_col_w = 18;
+    let col_w = 14;
+
+    tell::update!("COMPARISON");
+    tell::update!("==================");
+    tell::update!("Comparing {} against {}", window, previous_window);
+    tell::update!();
+
+    let header = format!(
+        "{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+        "",
+        "CURRENT",
+        "PREVIOUS",
+        "CHANGE",
+        name_col_w = name_col_w,
+        col_w = col_w,
+    );
+    let divider = repeat_str("=", header.chars().count());
+
+    tell::update!("{}", header);
+    tell::update!("{}", divider);
+
+    for (name, c, p, precision) in rows {
+        tell::update!(
+            "{:<0name_col_w$}{:>0col_w$}{:>0col_w$}{:>0col_w$}",
+            name,
+            format_f32(c, precision),
+            format_f32(p, precision),
+            format!("{:+.p$}", c - p, p = precision),
+            name_col_w = name_col_w,
+            col_w = col_w,
+        );
+    }
+
+    tell::update!("{}", divider);
+    tell::update!();
+}
+
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("name", Some('n')),
+    ("mode", Some('M')),
+    ("class", Some('C')),
+    ("moment", Some('T')),
+    ("end-moment", Some('E')),
+    ("activity-limit", Some('L')),
+    ("weapon-count", Some('w')),
+    ("medal-count", Some('m')),
+    ("weapon-sort", Some('W')),
+    ("weighting", None),
+    ("exclude-late-joins", None),
+    ("late-join-seconds", None),
+    ("exclude-left-early", None),
+    ("exclude-mercy", None),
+    ("last", None),
+    ("skip", None),
+    ("sync", Some('s')),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving and viewing Destiny 2 Crucible activity history.
@@ -577,8 +798,12 @@ struct Opt {
     /// Name must be in the format of NAME#CODE. Example: foo#3280
     /// You can find your name in game, or on Bungie's site at:
     /// https://www.bungie.net/7/en/User/Account/IdentitySettings
+    ///
+    /// Can also be a Destiny membership id, an alias set via dclisync
+    /// --alias, or the start of the name of a player in the data store (case
+    /// insensitive). Example: foo
     #[structopt(long = "name", short = "n", required = true)]
-    name: PlayerName,
+    name: PlayerSelector,
 
     /// Custom start time in RFC 3339 date / time format
     ///
@@ -617,6 +842,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --custom-time argument.
     ///
@@ -644,6 +872,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --end-custom-time argument.
     ///
@@ -695,6 +926,35 @@ struct Opt {
     #[structopt(short = "W", long = "weapon-sort", default_value = "kills")]
     weapon_sort: WeaponSort,
 
+    /// How per game averages are calculated
+    ///
+    /// Valid values are game (default), which divides by the number of games,
+    /// and time_played, which weights each game by the portion of the activity
+    /// the player was in (so late joins and early exits count as partial games).
+    #[structopt(long = "weighting", default_value = "game")]
+    weighting: AverageWeighting,
+
+    #[structopt(flatten)]
+    activity_filter: ActivityFilterOpt,
+
+    /// Only include the most recent N games within the moment range
+    ///
+    /// Combine with --skip to include games further back. For example,
+    /// --skip 50 --last 50 includes the games 50 to 100 games ago.
+    #[structopt(long = "last")]
+    last: Option<u32>,
+
+    /// Number of most recent games to skip before including games
+    #[structopt(long = "skip", default_value = "0")]
+    skip: u32,
+
+    /// Compare the --last N games against the N games before them
+    ///
+    /// A summary comparing the two sets of games is displayed after the activity
+    /// details.
+    #[structopt(long = "compare", requires = "last")]
+    compare: bool,
+
     ///Print out additional information
 
     #[structopt(short = "v", long = "verbose")]
@@ -718,10 +978,18 @@ struct Opt {
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dcliah", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -730,6 +998,10 @@ async fn main() {
     };
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     tell::verbose!("{:#?}", opt);
     log::info!("{:#?}", opt);
 
@@ -744,18 +1016,37 @@ async fn main() {
         }
     };
 
-    let start_time = match opt.moment {
-        Moment::Custom => {
-            opt.custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let start_time = match opt
+        .moment
+        .resolve_date_time(opt.custom_time, false, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not determine --moment date / time.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.moment.get_date_time(),
     };
 
-    let end_time = match opt.end_moment {
-        Moment::Custom => {
-            opt.end_custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let end_time = match opt
+        .end_moment
+        .resolve_date_time(opt.end_custom_time, true, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error(
+                    "Could not determine --end-moment date / time.",
+                    e,
+                )
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.end_moment.get_date_time(),
     };
 
     let time_period =
@@ -795,7 +1086,7 @@ async fn main() {
         }
     };
 
-    let member: Member = match store.find_member(&opt.name, true).await {
+    let member: Member = match store.resolve_member(&opt.name, true).await {
         Ok(e) => e,
         Err(e) => {
             tell::error!(
@@ -816,12 +1107,21 @@ async fn main() {
         };
     }
 
+    let filter = opt.activity_filter.filter();
+
+    let window = ActivityWindow {
+        skip: opt.skip,
+        count: opt.last,
+    };
+
     let data = match store
         .retrieve_activities_since(
             &member,
             &opt.character_class_selection,
             &opt.mode,
             &time_period,
+            &filter,
+            &window,
             &mut manifest,
         )
         .await
@@ -860,5 +1160,43 @@ async fn main() {
         &opt.weapon_sort,
         &opt.medal_count,
         &opt.character_class_selection,
+        &opt.weighting,
+        &window,
+    );
+
+    if !opt.compare {
+        return;
+    }
+
+    //requires = "last" ensures window has a count
+    let previous_window = window.previous().unwrap();
+    let previous_data = match store
+        .retrieve_activities_since(
+            &member,
+            &opt.character_class_selection,
+            &opt.mode,
+            &time_period,
+            &filter,
+            &previous_window,
+            &mut manifest,
+        )
+        .await
+    {
+        Ok(e) => e.unwrap_or_default(),
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not retrieve data from activity store.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
+
+    print_comparison(
+        &data,
+        &previous_data,
+        &window,
+        &previous_window,
+        &opt.weighting,
     );
 }
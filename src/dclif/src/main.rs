@@ -25,7 +25,7 @@ use chrono::{DateTime, Utc};
 mod classascii;
 use classascii::ClassAscii;
 
-use dcli::crucible::{Member, PlayerName};
+use dcli::crucible::{Member, PlayerSelector};
 use dcli::enums::mode::Mode;
 use dcli::enums::moment::{DateTimePeriod, Moment};
 use dcli::playeractivitiessummary::PlayerActivitiesSummary;
@@ -37,12 +37,14 @@ use std::path::PathBuf;
 
 use dcli::enums::character::CharacterClassSelection;
 
+use dcli::activityfilter::{ActivityFilter, ActivityWindow};
 use dcli::activitystoreinterface::ActivityStoreInterface;
 
 use dcli::utils::{
     determine_data_dir, format_f32, parse_and_validate_crucible_mode,
 };
 
+use dcli::config::args_with_config;
 use dcli::utils::EXIT_FAILURE;
 use structopt::StructOpt;
 
@@ -98,6 +100,18 @@ fn print_mode_data(
     );
 }
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("name", Some('n')),
+    ("mode", Some('M')),
+    ("class", Some('C')),
+    ("moment", Some('T')),
+    ("end-moment", Some('E')),
+    ("sync", Some('s')),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving and viewing Destiny 2 Crucible activity history.
@@ -121,8 +135,12 @@ struct Opt {
     /// Name must be in the format of NAME#CODE. Example: foo#3280
     /// You can find your name in game, or on Bungie's site at:
     /// https://www.bungie.net/7/en/User/Account/IdentitySettings
+    ///
+    /// Can also be a Destiny membership id, an alias set via dclisync
+    /// --alias, or the start of the name of a player in the data store (case
+    /// insensitive). Example: foo
     #[structopt(long = "name", short = "n", required = true)]
-    name: PlayerName,
+    name: PlayerSelector,
 
     /// Custom start time in RFC 3339 date / time format
     ///
@@ -161,6 +179,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --custom-time argument.
     ///
@@ -188,6 +209,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --end-custom-time argument.
     ///
@@ -241,10 +265,22 @@ struct Opt {
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dclif", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
+
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
     tell::error!("{:#?}", opt);
 
     let data_dir = match determine_data_dir(opt.data_dir) {
@@ -258,18 +294,37 @@ async fn main() {
         }
     };
 
-    let start_time = match opt.moment {
-        Moment::Custom => {
-            opt.custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let start_time = match opt
+        .moment
+        .resolve_date_time(opt.custom_time, false, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not determine --moment date / time.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.moment.get_date_time(),
     };
 
-    let end_time = match opt.end_moment {
-        Moment::Custom => {
-            opt.end_custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let end_time = match opt
+        .end_moment
+        .resolve_date_time(opt.end_custom_time, true, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error(
+                    "Could not determine --end-moment date / time.",
+                    e,
+                )
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.end_moment.get_date_time(),
     };
 
     let time_period =
@@ -295,7 +350,7 @@ async fn main() {
             }
         };
 
-    let member: Member = match store.find_member(&opt.name, true).await {
+    let member: Member = match store.resolve_member(&opt.name, true).await {
         Ok(e) => e,
         Err(e) => {
             tell::error!(
@@ -322,6 +377,8 @@ async fn main() {
             &opt.character_class_selection,
             &opt.mode,
             &time_period,
+            &ActivityFilter::default(),
+            &ActivityWindow::default(),
         )
         .await
     {
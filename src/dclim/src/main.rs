@@ -26,11 +26,14 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use dcli::apiclient::ApiClient;
+use dcli::config::args_with_config;
 use dcli::error::Error;
 use dcli::manifestinterface::MANIFEST_FILE_NAME;
 use dcli::output::Output;
 use dcli::response::manifest::ManifestResponse;
-use dcli::utils::{build_tsv, determine_data_dir};
+use dcli::utils::{
+    build_tsv, determine_data_dir, parse_and_validate_tsv_output,
+};
 use dcli::utils::{format_error, EXIT_FAILURE};
 use manifest_info::ManifestInfo;
 use structopt::StructOpt;
@@ -108,6 +111,9 @@ async fn download_manifest(url: &str, path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[("data-dir", Some('D'))];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving and managing the Destiny 2 manifest database.
@@ -157,13 +163,23 @@ struct Opt {
     #[structopt(
         short = "O",
         long = "output-format",
+        parse(try_from_str = parse_and_validate_tsv_output),
         default_value = "default"
     )]
+    //json is rejected by parse_and_validate_tsv_output
     output: Output,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dclim", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -172,6 +188,10 @@ async fn main() {
     };
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     tell::verbose!("{:#?}", opt);
     log::info!("{:#?}", opt);
 
@@ -280,6 +300,8 @@ async fn main() {
 
                 tell::update!("{}", build_tsv(name_values));
             }
+            //rejected by parse_and_validate_tsv_output
+            Output::Json => unreachable!(),
         }
         return;
     }
@@ -330,5 +352,7 @@ async fn main() {
 
             tell::update!("{}", build_tsv(name_values));
         }
+        //rejected by parse_and_validate_tsv_output
+        Output::Json => unreachable!(),
     }
 }
@@ -21,13 +21,15 @@
 */
 
 use chrono::{DateTime, Utc};
-use dcli::crucible::{Member, PlayerName};
+use dcli::activityfilter::{ActivityFilterOpt, ActivityWindow};
+use dcli::crucible::{Member, PlayerSelector};
+use dcli::enums::averageweighting::AverageWeighting;
 use dcli::enums::mode::Mode;
 use dcli::enums::moment::{DateTimePeriod, Moment};
 use dcli::enums::stat::Stat;
 use dcli::playeractivitiessummary::PlayerActivitiesSummary;
 use dcli::utils::{
-    calculate_average, calculate_efficiency, calculate_kills_deaths_assists,
+    calculate_efficiency, calculate_kills_deaths_assists,
     calculate_kills_deaths_ratio, determine_data_dir, format_error, format_f32,
     parse_and_validate_crucible_mode, parse_rfc3339,
 };
@@ -38,43 +40,67 @@ use dcli::enums::character::CharacterClassSelection;
 
 use dcli::activitystoreinterface::ActivityStoreInterface;
 
+use dcli::config::args_with_config;
 use dcli::utils::EXIT_FAILURE;
 use structopt::StructOpt;
 
 #[allow(clippy::too_many_arguments)]
-fn print_default(data: &PlayerActivitiesSummary, stats: &[Stat]) {
+fn print_default(
+    data: &PlayerActivitiesSummary,
+    stats: &[Stat],
+    weighting: &AverageWeighting,
+    label: Option<String>,
+) {
     let mut out = Vec::<String>::new();
+    out.extend(label);
     for m in stats.iter() {
         let o: String = match m {
             Stat::Assists => data.assists.to_string(),
-            Stat::AssistsAvg => format_f32(
-                calculate_average(data.assists, data.total_activities),
-                2,
-            ),
+            Stat::AssistsAvg => {
+                format_f32(data.average(data.assists, weighting), 2)
+            }
             Stat::AssistsMax => data.highest_assists.to_string(),
             Stat::Deaths => data.deaths.to_string(),
-            Stat::DeathsAvg => format_f32(
-                calculate_average(data.deaths, data.total_activities),
-                2,
-            ),
+            Stat::DeathsAvg => {
+                format_f32(data.average(data.deaths, weighting), 2)
+            }
             Stat::DeathsMax => data.highest_deaths.to_string(),
             Stat::Kills => data.kills.to_string(),
-            Stat::KillsAvg => format_f32(
-                calculate_average(data.kills, data.total_activities),
-                2,
-            ),
+            Stat::KillsAvg => {
+                format_f32(data.average(data.kills, weighting), 2)
+            }
             Stat::KillsMax => data.highest_kills.to_string(),
             Stat::OpponentsDefeated => data.opponents_defeated.to_string(),
-            Stat::OpponentsDefeatedAvg => format_f32(
-                calculate_average(
-                    data.opponents_defeated,
-                    data.total_activities,
-                ),
-                2,
-            ),
+            Stat::OpponentsDefeatedAvg => {
+                format_f32(data.average(data.opponents_defeated, weighting), 2)
+            }
             Stat::OpponentsDefeatedMax => {
                 data.highest_opponents_defeated.to_string()
             }
+            Stat::KillsPerMin => format_f32(data.per_minute(data.kills), 2),
+            Stat::DeathsPerMin => format_f32(data.per_minute(data.deaths), 2),
+            Stat::AssistsPerMin => format_f32(data.per_minute(data.assists), 2),
+            Stat::AbilityKillsPerMin => {
+                format_f32(data.detailed_per_minute(data.ability_kills), 2)
+            }
+            Stat::SuperKillsPerMin => {
+                format_f32(data.detailed_per_minute(data.super_kills), 2)
+            }
+            Stat::KillsPer10Min => {
+                format_f32(data.per_ten_minutes(data.kills), 2)
+            }
+            Stat::DeathsPer10Min => {
+                format_f32(data.per_ten_minutes(data.deaths), 2)
+            }
+            Stat::AssistsPer10Min => {
+                format_f32(data.per_ten_minutes(data.assists), 2)
+            }
+            Stat::AbilityKillsPer10Min => {
+                format_f32(data.detailed_per_ten_minutes(data.ability_kills), 2)
+            }
+            Stat::SuperKillsPer10Min => {
+                format_f32(data.detailed_per_ten_minutes(data.super_kills), 2)
+            }
             Stat::Efficiency => format_f32(
                 calculate_efficiency(data.kills, data.deaths, data.assists),
                 2,
@@ -100,6 +126,7 @@ fn print_default(data: &PlayerActivitiesSummary, stats: &[Stat]) {
             Stat::Wins => data.wins.to_string(),
             Stat::Losses => (data.total_activities - data.wins).to_string(),
             Stat::Mercies => data.completion_reason_mercy.to_string(),
+            Stat::Pending => data.pending_activities.to_string(),
         };
 
         out.push(o);
@@ -108,6 +135,26 @@ fn print_default(data: &PlayerActivitiesSummary, stats: &[Stat]) {
     tell::update!("{}", out.join(","));
 }
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("name", Some('n')),
+    ("mode", Some('M')),
+    ("class", Some('C')),
+    ("moment", Some('T')),
+    ("end-moment", Some('E')),
+    ("stat", Some('x')),
+    ("weighting", None),
+    ("exclude-late-joins", None),
+    ("late-join-seconds", None),
+    ("exclude-left-early", None),
+    ("exclude-mercy", None),
+    ("last", None),
+    ("skip", None),
+    ("sync", Some('s')),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving and viewing Destiny 2 Crucible activity history.
@@ -131,8 +178,12 @@ struct Opt {
     /// Name must be in the format of NAME#CODE. Example: foo#3280
     /// You can find your name in game, or on Bungie's site at:
     /// https://www.bungie.net/7/en/User/Account/IdentitySettings
+    ///
+    /// Can also be a Destiny membership id, an alias set via dclisync
+    /// --alias, or the start of the name of a player in the data store (case
+    /// insensitive). Example: foo
     #[structopt(long = "name", short = "n", required = true)]
-    name: PlayerName,
+    name: PlayerSelector,
 
     /// Custom start time in RFC 3339 date / time format
     ///
@@ -171,6 +222,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --custom-time argument.
     ///
@@ -198,6 +252,9 @@ struct Opt {
     /// season_of_the_chosen, season_of_the_splicer, season_of_the_lost, season_of_the_risen,
     /// witch_queen, season_of_the_haunted, season_of_plunder, season_of_the_seraph, lightfall, season_of_defiance, season_of_the_deep, season_of_the_witch, season_of_the_wish.
     ///
+    /// Relative, date and season moments (i.e. 30d, 2023-05-01 or
+    /// season:current) are also supported. See dclitime --help for details.
+    ///
     /// When custom is specified, the custom start date in RFC3339 format must
     /// be specified with the --end-custom-time argument.
     ///
@@ -233,10 +290,49 @@ struct Opt {
     /// Valid values include kd, kda, efficiency, kills, opponents_defeated, deaths,
     /// assists, kills_avg, opponents_defeated_avg, deaths_avg, assists_avg,
     /// kd_max, kda_max, efficiency_max, kills_max, opponents_defeated_max,
-    /// deaths_max, games, wins, losses, mercies.
+    /// deaths_max, games, wins, losses, mercies, pending.
+    ///
+    /// Rates based on time played are available via kills_per_min,
+    /// deaths_per_min, assists_per_min, ability_kills_per_min,
+    /// super_kills_per_min, kills_per_10min, deaths_per_10min,
+    /// assists_per_10min, ability_kills_per_10min and super_kills_per_10min.
+    ///
+    /// pending is the number of games whose details are still being synced.
+    /// Ability and super kills rates only include games whose details have
+    /// been synced.
     #[structopt(short = "x", long = "stat", required = true)]
     stat: Vec<Stat>,
 
+    /// How per game averages (*_avg stats) are calculated
+    ///
+    /// Valid values are game (default), which divides by the number of games,
+    /// and time_played, which weights each game by the portion of the activity
+    /// the player was in (so late joins and early exits count as partial games).
+    #[structopt(long = "weighting", default_value = "game")]
+    weighting: AverageWeighting,
+
+    #[structopt(flatten)]
+    activity_filter: ActivityFilterOpt,
+
+    /// Only include the most recent N games within the moment range
+    ///
+    /// Combine with --skip to include games further back. For example,
+    /// --skip 50 --last 50 includes the games 50 to 100 games ago.
+    #[structopt(long = "last")]
+    last: Option<u32>,
+
+    /// Number of most recent games to skip before including games
+    #[structopt(long = "skip", default_value = "0")]
+    skip: u32,
+
+    /// Compare the --last N games against the N games before them
+    ///
+    /// Stats for the previous games are output on a second line. Each line
+    /// starts with a label for the games it contains (i.e. the last 50 games
+    /// and games 50 to 100 back).
+    #[structopt(long = "compare", requires = "last")]
+    compare: bool,
+
     ///Print out additional information
     #[structopt(short = "v", long = "verbose")]
     verbose: bool,
@@ -259,10 +355,18 @@ struct Opt {
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dclistat", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -271,6 +375,10 @@ async fn main() {
     };
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     tell::verbose!("{:#?}", opt);
     log::info!("{:#?}", opt);
 
@@ -285,18 +393,37 @@ async fn main() {
         }
     };
 
-    let start_time = match opt.moment {
-        Moment::Custom => {
-            opt.custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let start_time = match opt
+        .moment
+        .resolve_date_time(opt.custom_time, false, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not determine --moment date / time.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.moment.get_date_time(),
     };
 
-    let end_time = match opt.end_moment {
-        Moment::Custom => {
-            opt.end_custom_time.unwrap() //note, this should be ok, because struct opt should ensure valid value
+    let end_time = match opt
+        .end_moment
+        .resolve_date_time(opt.end_custom_time, true, &data_dir)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error(
+                    "Could not determine --end-moment date / time.",
+                    e,
+                )
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        _ => opt.end_moment.get_date_time(),
     };
 
     let time_period =
@@ -322,7 +449,7 @@ async fn main() {
             }
         };
 
-    let member: Member = match store.find_member(&opt.name, true).await {
+    let member: Member = match store.resolve_member(&opt.name, true).await {
         Ok(e) => e,
         Err(e) => {
             tell::error!(
@@ -343,31 +470,57 @@ async fn main() {
         };
     }
 
-    let data = match store
-        .retrieve_activities_summary(
-            &member,
-            &opt.character_class_selection,
-            &opt.mode,
-            &time_period,
-        )
-        .await
-    {
-        Ok(e) => e,
-        Err(e) => {
-            tell::error!(
-                "{}",
-                format_error("Could not retrieve data from activity store.", e)
-            );
-            std::process::exit(EXIT_FAILURE);
-        }
+    let filter = opt.activity_filter.filter();
+
+    let window = ActivityWindow {
+        skip: opt.skip,
+        count: opt.last,
     };
 
-    if data.is_none() {
-        tell::update!("No data found");
-        return;
+    let mut windows = vec![window];
+    if opt.compare {
+        windows.extend(window.previous());
     }
 
-    let data: PlayerActivitiesSummary = data.unwrap();
+    for window in windows.iter() {
+        let data = match store
+            .retrieve_activities_summary(
+                &member,
+                &opt.character_class_selection,
+                &opt.mode,
+                &time_period,
+                &filter,
+                window,
+            )
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error(
+                        "Could not retrieve data from activity store.",
+                        e
+                    )
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        if data.is_none() {
+            tell::update!("No data found");
+            return;
+        }
 
-    print_default(&data, &opt.stat);
+        let data: PlayerActivitiesSummary = data.unwrap();
+
+        //label lines when comparing, so they can be told apart
+        let label = if opt.compare {
+            Some(window.to_string())
+        } else {
+            None
+        };
+
+        print_default(&data, &opt.stat, &opt.weighting, label);
+    }
 }
@@ -20,23 +20,95 @@
 * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+mod scheduler;
+
+use chrono::{DateTime, Local, Utc};
 use log::{error, info};
-use std::path::PathBuf;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tell::{Tell, TellLevel};
 
-use dcli::activitystoreinterface::ActivityStoreInterface;
+use dcli::activitystoreinterface::{
+    ActivityStoreInterface, FailedActivity, GroupSubscription, IntegrityIssue,
+    MemberAlias, MemberSyncResult, NameHistoryEntry, PruneOptions, PruneResult,
+    StoredMember, SyncStatus, TransferResult,
+};
+use dcli::apiclient::RequestBudget;
 use dcli::apiinterface::ApiInterface;
-use dcli::crucible::{Member, PlayerName};
-use dcli::utils::{determine_data_dir, format_error, EXIT_FAILURE};
+use dcli::config::args_with_config;
+use dcli::crucible::{
+    HistoryLimit, Member, PlayerName, PlayerSearchResult, PlayerSelector,
+};
+use dcli::enums::moment::{DateTimePeriod, Moment};
+use dcli::error::Error;
+use dcli::manifestinterface::ManifestInterface;
+use dcli::metrics::{self, METRICS_PATH};
+use dcli::output::Output;
+use dcli::utils::{
+    build_json, determine_data_dir, format_error, format_file_size,
+    human_date_format, human_duration, parse_rfc3339, EXIT_FAILURE, TSV_DELIM,
+    TSV_EOL,
+};
+use scheduler::Scheduler;
 use structopt::StructOpt;
 
 const DEFAULT_REFRESH_INTERVAL: u32 = 60;
 const SHOULD_CONTINUE_CODE: i32 = -1;
 
+//max number of players from the data store listed by --search
+const MAX_STORED_SEARCH_RESULTS: u32 = 10;
+
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("interval", Some('I')),
+    ("adaptive", None),
+    ("min-interval", None),
+    ("idle-check-interval", None),
+    ("max-interval", None),
+    ("metrics-address", None),
+    ("group-interval", None),
+    ("data-dir", Some('D')),
+    ("api-key", Some('k')),
+    ("max-attempts", None),
+    ("max-concurrent-syncs", None),
+    ("max-requests-per-second", None),
+    ("prefetch-combat-ratings", None),
+    ("combat-rating-ttl", None),
+    ("prune-detail-days", None),
+    ("prune-activities-moment", None),
+    ("verify-cycles", None),
+    ("verify-start-moment", None),
+];
+
+//alias definition specified as ALIAS=PLAYER
+#[derive(Debug)]
+struct AliasDefinition {
+    alias: String,
+    player: PlayerSelector,
+}
+
+impl FromStr for AliasDefinition {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (alias, player) = match s.split_once('=') {
+            Some(e) => e,
+            None => return Err("Invalid alias. Format: ALIAS=PLAYER"),
+        };
+
+        Ok(AliasDefinition {
+            alias: alias.trim().to_string(),
+            player: PlayerSelector::from_str(player)?,
+        })
+    }
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for downloading and syncing Destiny 2 Crucible activity
@@ -46,7 +118,9 @@ const SHOULD_CONTINUE_CODE: i32 = -1;
 /// all clan members via the --import flag.
 ///
 /// If multiple flags are specified, they will be run in the following order:
-/// import, add, remove, sync, list
+/// import, subscribe-group, unsubscribe-group, search, add, remove, alias,
+/// remove-alias, merge, restore, export, check, prune, retry-failed, verify,
+/// sync, list, name-history, aliases, groups, failed
 ///
 /// Some options require that a Bungie API key is specified via the --api-key KEY flag,
 /// or DESTINY_API_KEY environment variable.
@@ -95,11 +169,27 @@ struct Opt {
         short = "s",
         //conflicts_with_all = &["add", "remove", "list"],
         //required_unless_one=&["list", "add", "remove"],`
-        required_unless_one = &["list", "add", "remove", "import-group"],
+        required_unless_one = &["list", "search", "add", "remove", "import-group", "subscribe-group", "unsubscribe-group", "name-history", "alias", "remove-alias", "aliases", "groups", "merge", "restore", "export", "check", "prune", "failed", "retry-failed", "verify"],
         requires="api-key"
     )]
     sync: Option<Vec<PlayerName>>,
 
+    /// Search for players whose Bungie name starts with the specified text.
+    ///
+    /// Lists matching players already in the data store, as well as players
+    /// found by searching Bungie (which requires an API key). Bungie results
+    /// include the platforms and when each player last played.
+    ///
+    /// When run from a terminal, you will be prompted to pick one of the
+    /// players found to add to sync. Example: --search foo
+    #[structopt(long = "search")]
+    search: Option<String>,
+
+    /// Maximum number of pages of results to retrieve from Bungie when
+    /// searching for players via --search.
+    #[structopt(long = "search-pages", default_value = "1")]
+    search_pages: u32,
+
     /// Add specified player(s) to have their activities synced the next time
     /// the database is synced.
     ///
@@ -118,6 +208,32 @@ struct Opt {
     )]
     add: Option<Vec<PlayerName>>,
 
+    /// Only sync activities played after the specified moment for players
+    /// added via --add, --import-group or --search.
+    ///
+    /// Accepts the same moments as dclitime --moment (i.e. weekly, 30d or
+    /// season:current). Newer activities are always synced.
+    ///
+    /// Adding a player who is already being synced replaces their limit.
+    /// Specifying an earlier moment (or all_time) syncs their older
+    /// activities the next time they are synced.
+    #[structopt(long = "sync-since")]
+    sync_since: Option<Moment>,
+
+    /// Start date / time for activities to sync if --sync-since is custom
+    ///
+    /// RFC 3339 date / time formatted string. Example: 2020-12-08T17:00:00.774187+00:00
+    #[structopt(long = "sync-since-time", parse(try_from_str = parse_rfc3339), required_if("sync-since", "custom"))]
+    sync_since_time: Option<DateTime<Utc>>,
+
+    /// Only sync the specified number of most recent activities (public and
+    /// private combined) for each character of players added via --add,
+    /// --import-group or --search.
+    ///
+    /// Newer activities are always synced. Set to 0 for no limit.
+    #[structopt(long = "max-activities")]
+    max_activities: Option<u32>,
+
     /// Remove specified player(s) from having their activities synced.
     ///
     /// Note, player data will still be contained in the database, but no new
@@ -132,13 +248,46 @@ struct Opt {
     )]
     remove: Option<Vec<PlayerName>>,
 
-    ///List all Bungie names which are flagged to be synced.
+    ///List all Bungie names which are flagged to be synced, along with the
+    /// status of their synced data.
+    ///
+    /// Shows the number of characters and stored activities for each player,
+    /// when their most recent stored activity was played, when they were last
+    /// synced, how many activities are queued to be synced or have failed
+    /// to sync, and how much of their history is synced.
     #[structopt(short = "l", long = "list", 
         //required_unless_one = &["sync", "add", "remove"], 
         //conflicts_with_all = &["sync"]
     )]
     list: bool,
 
+    /// Display the Bungie names the specified player has been seen with in
+    /// synced activities.
+    ///
+    /// Shows when each name was first and last seen, based on the activities
+    /// it was seen in. The player can be specified by their current or a
+    /// previous Bungie name (NAME#CODE), membership id, alias or the start of
+    /// their name. Example: foo#3280
+    #[structopt(long = "name-history")]
+    name_history: Option<PlayerSelector>,
+
+    /// Set a local alias which can be used in place of a player's Bungie
+    /// name by --name in the other dcli apps.
+    ///
+    /// Format is ALIAS=PLAYER. Example: me=foo#3280
+    ///
+    /// Aliases are not case sensitive, and cannot contain # or be a number.
+    #[structopt(long = "alias")]
+    alias: Option<Vec<AliasDefinition>>,
+
+    /// Remove the specified local alias(es).
+    #[structopt(long = "remove-alias")]
+    remove_alias: Option<Vec<String>>,
+
+    /// List all local aliases, and the players they refer to.
+    #[structopt(long = "aliases")]
+    aliases: bool,
+
     ///Run dclisync in daemon mode. dclisync will run continuously with a
     /// pause (specified by --interval) between syncs
     #[structopt(short = "d", long = "daemon")]
@@ -148,6 +297,42 @@ struct Opt {
     #[structopt(short = "I", long = "interval")]
     interval: Option<u32>,
 
+    /// Adaptively schedule syncs in daemon mode, based on whether players are
+    /// playing Crucible.
+    ///
+    /// Players who are in, or have just left a Crucible activity are checked
+    /// every --min-interval seconds, and synced as soon as their activity
+    /// ends. Players who are not playing are checked less often, up to every
+    /// --idle-check-interval seconds, and synced at least every
+    /// --max-interval seconds. --interval is ignored.
+    #[structopt(long = "adaptive", requires = "daemon")]
+    adaptive: bool,
+
+    /// Interval in seconds between checking players who are playing Crucible
+    /// when running in adaptive daemon mode.
+    #[structopt(long = "min-interval", default_value = "30")]
+    min_interval: u32,
+
+    /// Maximum interval in seconds between checking players who are not
+    /// playing when running in adaptive daemon mode.
+    #[structopt(long = "idle-check-interval", default_value = "300")]
+    idle_check_interval: u32,
+
+    /// Maximum interval in seconds between syncing players when running in
+    /// adaptive daemon mode.
+    #[structopt(long = "max-interval", default_value = "1800")]
+    max_interval: u32,
+
+    /// Address to serve sync metrics on while syncing, in the Prometheus
+    /// text format. Example: 127.0.0.1:9184
+    ///
+    /// Metrics are served at /metrics, and include sync cycles, activities
+    /// synced per player, API request counts and latency, errors, queue depth,
+    /// the time of the last successful sync and the data store size. Usually
+    /// used with --daemon.
+    #[structopt(long = "metrics-address")]
+    metrics_address: Option<SocketAddr>,
+
     /// Import all players for specified Destiny 2 Group / clan.
     ///
     /// You can get your groupid for your clan from the Bungie clan page:
@@ -161,165 +346,1831 @@ struct Opt {
     #[structopt(short = "i", long = "import-group", requires = "api-key")]
     import_group: Option<u32>,
 
+    /// Subscribe to a Destiny 2 Group / clan, so players who join the clan
+    /// are automatically synced.
+    ///
+    /// All current players in the clan are added to sync. When syncing all
+    /// players, the clan roster is checked every --group-interval hours,
+    /// players who joined are added, and players who left are logged (and
+    /// removed from sync if --remove-departed is specified).
+    ///
+    /// Requires that a Bungie API key is specified via the --api-key KEY flag,
+    /// or DESTINY_API_KEY environment variable.
+    #[structopt(long = "subscribe-group", requires = "api-key")]
+    subscribe_group: Option<u32>,
+
+    /// When subscribing to a group, stop syncing players who leave the group.
+    #[structopt(long = "remove-departed", requires = "subscribe-group")]
+    remove_departed: bool,
+
+    /// Remove the subscription to a Destiny 2 Group / clan. Players from the
+    /// clan will still be synced.
+    #[structopt(long = "unsubscribe-group")]
+    unsubscribe_group: Option<u32>,
+
+    /// List subscribed Destiny 2 Groups / clans.
+    #[structopt(long = "groups")]
+    groups: bool,
+
+    /// Interval in hours between checking the rosters of subscribed groups
+    /// for changes.
+    #[structopt(long = "group-interval", default_value = "24")]
+    group_interval: u32,
+
+    /// Merge activity data from another dcli.sqlite3 data store.
+    ///
+    /// Members, characters, activities and stats which are not already in
+    /// the local data store are copied over, along with the players flagged
+    /// to be synced. Activities which exist in both stores are not duplicated.
+    /// If both stores contain different data for the same item, the local
+    /// data is kept and the conflict is reported.
+    ///
+    /// The data store being merged must have been synced with the same
+    /// version of dclisync.
+    #[structopt(short = "m", long = "merge", parse(from_os_str))]
+    merge: Option<PathBuf>,
+
+    /// Restore activity data from an archive created with --export.
+    ///
+    /// Data which already exists in the local data store is not duplicated
+    /// or overwritten. Archives can be restored into data stores created with
+    /// other versions of dclisync.
+    #[structopt(short = "R", long = "restore", parse(from_os_str))]
+    restore: Option<PathBuf>,
+
+    /// Export activity data to a compressed archive file.
+    ///
+    /// Archives are gzip compressed JSON lines files, which can be restored
+    /// via --restore, and are independent of the data store version. It is
+    /// safe to export while dclisync is syncing in daemon mode.
+    #[structopt(short = "e", long = "export", parse(from_os_str))]
+    export: Option<PathBuf>,
+
+    /// Only export activities for the specified player.
+    ///
+    /// Name must be in the format of NAME#CODE. Example: foo#3280
+    ///
+    /// Can also be a membership id, alias or the start of a player's name.
+    #[structopt(long = "export-name", requires = "export")]
+    export_name: Option<PlayerSelector>,
+
+    /// Start moment for activities to export.
+    ///
+    /// Accepts the same moments as dclitime --moment (i.e. weekly, 30d or
+    /// season:previous).
+    #[structopt(long = "export-start-moment", default_value = "all_time")]
+    export_start_moment: Moment,
+
+    /// Start date / time for activities to export if --export-start-moment
+    /// is custom
+    ///
+    /// RFC 3339 date / time formatted string. Example: 2020-12-08T17:00:00.774187+00:00
+    #[structopt(long = "export-start-time", parse(try_from_str = parse_rfc3339), required_if("export-start-moment", "custom"))]
+    export_start_time: Option<DateTime<Utc>>,
+
+    /// End moment for activities to export.
+    #[structopt(long = "export-end-moment", default_value = "now")]
+    export_end_moment: Moment,
+
+    /// End date / time for activities to export if --export-end-moment is
+    /// custom
+    ///
+    /// RFC 3339 date / time formatted string. Example: 2020-12-08T17:00:00.774187+00:00
+    #[structopt(long = "export-end-time", parse(try_from_str = parse_rfc3339), required_if("export-end-moment", "custom"))]
+    export_end_time: Option<DateTime<Utc>>,
+
+    /// Number of times retrieving an activity's details can fail before it
+    /// is moved to the failed list, and no longer retried when syncing.
+    ///
+    /// Network errors and API maintenance are not counted as failures. Set
+    /// to 0 to always retry failed activities.
+    #[structopt(long = "max-attempts", default_value = "5")]
+    max_attempts: u32,
+
+    /// Maximum number of players which are synced at the same time.
+    #[structopt(long = "max-concurrent-syncs", default_value = "4")]
+    max_concurrent_syncs: u32,
+
+    /// Maximum number of requests per second made to the Bungie API while
+    /// syncing or searching for players.
+    ///
+    /// The limit is shared by all of the players being synced at the same
+    /// time. Set to 0 for no limit.
+    #[structopt(long = "max-requests-per-second", default_value = "20")]
+    max_requests_per_second: u32,
+
+    /// Retrieve and cache the combat ratings of the players in synced
+    /// activities.
+    ///
+    /// Ratings are only retrieved for activities played within
+    /// --combat-rating-ttl hours, and for players whose ratings have not been
+    /// retrieved within that time. Cached ratings are displayed by dcliad.
+    #[structopt(long = "prefetch-combat-ratings")]
+    prefetch_combat_ratings: bool,
+
+    /// Number of hours prefetched combat ratings are cached for.
+    #[structopt(long = "combat-rating-ttl", default_value = "24")]
+    combat_rating_ttl: u32,
+
+    /// List activities which have been moved to the failed list.
+    #[structopt(short = "f", long = "failed")]
+    failed: bool,
+
+    /// Move activities on the failed list back into the sync queue, so they
+    /// are retried the next time their players are synced.
+    #[structopt(long = "retry-failed")]
+    retry_failed: bool,
+
+    /// Verify players' stored activities against their activity history, and
+    /// queue any missing activities to be synced.
+    ///
+    /// Syncing only retrieves activities newer than the most recent stored
+    /// activity, so activities missed by an interrupted sync are otherwise
+    /// never retrieved. Only activities between --verify-start-moment and
+    /// --verify-end-moment are verified.
+    ///
+    /// If no arguments are provided, all synced players will be verified.
+    /// Optionally, you can pass in one or more space separated Bungie names
+    /// and codes. Missing activities are synced the next time the player is
+    /// synced.
+    #[structopt(long = "verify", requires = "api-key")]
+    verify: Option<Vec<PlayerName>>,
+
+    /// Start moment for activities to verify.
+    ///
+    /// Accepts the same moments as dclitime --moment (i.e. weekly, 30d or
+    /// season:previous).
+    #[structopt(long = "verify-start-moment", default_value = "30d")]
+    verify_start_moment: Moment,
+
+    /// Start date / time for activities to verify if --verify-start-moment
+    /// is custom
+    ///
+    /// RFC 3339 date / time formatted string. Example: 2020-12-08T17:00:00.774187+00:00
+    #[structopt(long = "verify-start-time", parse(try_from_str = parse_rfc3339), required_if("verify-start-moment", "custom"))]
+    verify_start_time: Option<DateTime<Utc>>,
+
+    /// End moment for activities to verify.
+    #[structopt(long = "verify-end-moment", default_value = "now")]
+    verify_end_moment: Moment,
+
+    /// End date / time for activities to verify if --verify-end-moment is
+    /// custom
+    ///
+    /// RFC 3339 date / time formatted string. Example: 2020-12-08T17:00:00.774187+00:00
+    #[structopt(long = "verify-end-time", parse(try_from_str = parse_rfc3339), required_if("verify-end-moment", "custom"))]
+    verify_end_time: Option<DateTime<Utc>>,
+
+    /// Verify the players being synced every specified number of syncs when
+    /// running in daemon mode.
+    ///
+    /// Uses the --verify-start-moment and --verify-end-moment range. Set to
+    /// 0 to never verify.
+    #[structopt(long = "verify-cycles", default_value = "0")]
+    verify_cycles: u32,
+
+    /// Check the data store for integrity issues.
+    ///
+    /// Checks for orphaned rows, activities with missing modes or teams,
+    /// activities which have been stuck in the sync queue, and weapons and
+    /// medals which are not in the manifest (if the manifest has been
+    /// downloaded with dclim).
+    #[structopt(short = "c", long = "check")]
+    check: bool,
+
+    /// Fix issues found by --check which can be fixed.
+    ///
+    /// Orphaned rows are removed, missing modes are restored, and activities
+    /// pending their details are queued to be synced. Team mode activities
+    /// without team results are only reported.
+    #[structopt(long = "fix", requires = "check")]
+    fix: bool,
+
+    /// Prune data from the data store, and compact the database file.
+    ///
+    /// Data is removed based on the --prune-detail-days and
+    /// --prune-activities-moment retention rules. Only data for players who
+    /// are not being synced (including players removed with --remove) is
+    /// removed. Players and characters who no longer have any activities in
+    /// the data store are also removed, unless they have an alias or are in a
+    /// subscribed group. If no rules are specified, only these are removed
+    /// and the database file is compacted.
+    #[structopt(short = "P", long = "prune")]
+    prune: bool,
+
+    /// When pruning, remove weapon and medal details for players who are not
+    /// synced from activities older than the specified number of days.
+    #[structopt(long = "prune-detail-days")]
+    prune_detail_days: Option<u32>,
+
+    /// When pruning, remove activities before the specified moment which
+    /// none of the synced players played in.
+    ///
+    /// This includes activities of players who have been removed from
+    /// syncing, as well as those of other players stored in the data store
+    /// (i.e. from --merge).
+    ///
+    /// Accepts the same moments as dclitime --moment (i.e. 2023-01-01, 52w or
+    /// season:previous).
+    #[structopt(long = "prune-activities-moment")]
+    prune_activities_moment: Option<Moment>,
+
+    /// Format for command output.
+    ///
+    /// Valid values are default (Default), tsv and json. Applies to --list
+    /// and --name-history.
+    ///
+    /// tsv outputs a header line, followed by a tab (\t) separated line for
+    /// each row, with lines ending in a new line character (\n). json
+    /// outputs a JSON object of name / value pairs for each row, one per
+    /// line.
+    #[structopt(
+        short = "O",
+        long = "output-format",
+        default_value = "default"
+    )]
+    output: Output,
+
     /// API key from Bungie required for some actions.
     ///
     /// You can obtain a key from https://www.bungie.net/en/Application
     #[structopt(short = "k", long = "api-key", env = "DESTINY_API_KEY")]
     api_key: Option<String>,
+
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
 }
 
-#[tokio::main]
-async fn main() {
-    let opt = Opt::from_args();
-    env_logger::init();
+fn print_transfer_result(title: &str, result: &TransferResult) {
+    let rows = [
+        ("Members", result.members),
+        ("Member Links", result.member_links),
+        ("Member Names", result.member_names),
+        ("Characters", result.characters),
+        ("Activities", result.activities),
+        ("Modes", result.modes),
+        ("Team Results", result.team_results),
+        ("Character Activity Stats", result.character_activity_stats),
+        ("Weapon Results", result.weapon_results),
+        ("Medal Results", result.medal_results),
+        ("Sync Entries", result.sync_entries),
+    ];
 
-    let level = if opt.verbose {
-        TellLevel::Verbose
-    } else if opt.daemon {
-        TellLevel::Update
+    tell::update!("{}", title);
+    tell::update!("-------------");
+    for (label, count) in rows.iter() {
+        tell::update!("{:<26}{}", label, count);
+    }
+    tell::update!("");
+
+    if result.conflicts.is_empty() {
+        return;
+    }
+
+    tell::update!("Conflicts (local data kept)");
+    tell::update!("-------------");
+    for c in result.conflicts.iter() {
+        tell::update!("{}", c);
+    }
+    tell::update!("");
+}
+
+fn print_integrity_issues(issues: &[IntegrityIssue]) {
+    let col_w = 52;
+
+    tell::update!("Check");
+    tell::update!("-------------");
+    for issue in issues.iter() {
+        let status = if issue.count == 0 {
+            ""
+        } else if issue.fixed {
+            " (fixed)"
+        } else if issue.fixable {
+            " (fix with --fix)"
+        } else {
+            ""
+        };
+
+        tell::update!(
+            "{:<col_w$}{}{}",
+            issue.description,
+            issue.count,
+            status,
+            col_w = col_w
+        );
+
+        if !issue.examples.is_empty() {
+            tell::update!("    Examples : {}", issue.examples.join(", "));
+        }
+    }
+    tell::update!("");
+
+    let total = issues.iter().filter(|e| e.count > 0 && !e.fixed).count();
+    if total == 0 {
+        tell::update!("No issues found.");
     } else {
-        TellLevel::Progress
+        tell::update!("{} issue(s) found.", total);
+    }
+    tell::update!("");
+}
+
+//reconciles the rosters of subscribed groups which havent been checked
+//within the interval
+async fn reconcile_due_groups(
+    store: &mut ActivityStoreInterface,
+    interval_hours: u32,
+) {
+    let subscriptions = match store.retrieve_group_subscriptions().await {
+        Ok(e) => e,
+        Err(e) => {
+            metrics::record_error(&e);
+            tell::error!(
+                "{}",
+                format_error("Error retrieving group subscriptions.", e)
+            );
+            return;
+        }
     };
 
-    Tell::init(level);
-    info!("Using {} Output Level", level);
+    let interval = chrono::Duration::hours(interval_hours as i64);
+    for s in subscriptions.iter() {
+        let is_due = match s.last_checked {
+            Some(e) => Utc::now() - e >= interval,
+            None => true,
+        };
 
-    info!("Arguments : {:#?}", opt);
-    tell::verbose!("{:#?}", opt);
+        if is_due {
+            reconcile_group(store, s.group_id, true).await;
+        }
+    }
+}
 
-    let data_dir = match determine_data_dir(opt.data_dir) {
+//report_joined is false when subscribing, so the current roster is seeded
+//without reporting every player as having joined
+async fn reconcile_group(
+    store: &mut ActivityStoreInterface,
+    group_id: u32,
+    report_joined: bool,
+) {
+    tell::update!("Checking roster for Group ID : {}", group_id);
+
+    let changes = match store.reconcile_group(group_id).await {
         Ok(e) => e,
         Err(e) => {
+            metrics::record_error(&e);
             tell::error!(
                 "{}",
-                format_error("Error initializing storage directory store.", e)
+                format_error(
+                    &format!("Error checking roster for group {}.", group_id),
+                    e
+                )
             );
-            std::process::exit(EXIT_FAILURE);
+            return;
+        }
+    };
+
+    let mut sections = Vec::new();
+    if report_joined {
+        sections.push(("Joined group, added to sync", &changes.added));
+    } else {
+        tell::update!(
+            "Group ID {} : {} players added to sync",
+            group_id,
+            changes.added.len()
+        );
+    }
+    sections.push(("Left group", &changes.departed));
+    sections.push(("Left group, removed from sync", &changes.removed));
+
+    for (label, members) in sections.iter() {
+        for m in members.iter() {
+            info!("{} ({}) : {}", label, group_id, m.name.get_bungie_name());
+            tell::update!("{} : {}", label, m.name.get_bungie_name());
+        }
+    }
+}
+
+//syncs the players who are due to be synced, based on their current activity
+async fn sync_adaptive(
+    store: &mut ActivityStoreInterface,
+    api: &ApiInterface,
+    scheduler: &mut Scheduler,
+    players: &[PlayerName],
+) {
+    let members: Vec<Member> = if players.is_empty() {
+        match store.get_sync_members().await {
+            Ok(e) => e,
+            Err(e) => {
+                metrics::record_error(&e);
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving synced players.", e)
+                );
+                return;
+            }
+        }
+    } else {
+        let mut out = Vec::new();
+        for player in players.iter() {
+            match store.find_member(player, false).await {
+                Ok(e) => out.push(e),
+                Err(e) => {
+                    metrics::record_error(&e);
+                    tell::error!(
+                        "{}",
+                        format_error(
+                            &format!(
+                                "Error finding player: {}",
+                                player.get_bungie_name()
+                            ),
+                            e
+                        )
+                    );
+                }
+            }
+        }
+        out
+    };
+
+    let due = scheduler.find_due_members(api, &members).await;
+
+    let mut to_sync: Vec<Member> = Vec::with_capacity(due.len());
+    for (member, reason) in due {
+        tell::update!("Syncing {} ({})", member.name.get_bungie_name(), reason);
+        to_sync.push(member.clone());
+    }
+
+    //errors are logged when syncing
+    for result in store.sync_members(&to_sync).await {
+        if result.result.is_ok() {
+            scheduler.record_sync(&result.member);
+        } else {
+            scheduler.record_failed_sync(&result.member);
+        }
+    }
+}
+
+fn print_sync_summary(results: &[MemberSyncResult], elapsed: Duration) {
+    if results.is_empty() {
+        return;
+    }
+
+    tell::update!("");
+    tell::update!("Sync Summary");
+    tell::update!("-------------");
+
+    let col_w = 12;
+    let name_col_w = 32;
+
+    tell::update!(
+        "{:<name_col_w$}{:<col_w$}{:<col_w$}{:<col_w$}{}",
+        "PLAYER",
+        "SYNCED",
+        "QUEUED",
+        "TIME",
+        "STATUS",
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    let mut total_synced = 0;
+    let mut total_queued = 0;
+    let mut failed = 0;
+
+    for r in results.iter() {
+        let (synced, queued, status) = match &r.result {
+            Ok(e) => {
+                total_synced += e.total_synced;
+                total_queued += e.total_available;
+                (
+                    e.total_synced.to_string(),
+                    e.total_available.to_string(),
+                    "Synced".to_string(),
+                )
+            }
+            Err(e) => {
+                failed += 1;
+                (
+                    "-".to_string(),
+                    "-".to_string(),
+                    format!("Failed ({})", e.name()),
+                )
+            }
+        };
+
+        tell::update!(
+            "{:<name_col_w$}{:<col_w$}{:<col_w$}{:<col_w$}{}",
+            r.member.name.get_bungie_name(),
+            synced,
+            queued,
+            format!("{}s", r.duration.as_secs()),
+            status,
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+
+    let elapsed = match human_duration(elapsed.as_secs() as u32) {
+        e if e.is_empty() => "less than a second".to_string(),
+        e => e,
+    };
+
+    tell::update!("");
+    tell::update!(
+        "Synced {} activities for {} of {} players in {}. {} activities remain queued.",
+        total_synced,
+        results.len() - failed,
+        results.len(),
+        elapsed,
+        total_queued,
+    );
+
+    if failed > 0 {
+        tell::update!("{} players could not be synced.", failed);
+    }
+    tell::update!("");
+}
+
+//range of activities verified by --verify and --verify-cycles
+struct VerifyRange {
+    start_moment: Moment,
+    start_time: Option<DateTime<Utc>>,
+    end_moment: Moment,
+    end_time: Option<DateTime<Utc>>,
+}
+
+impl VerifyRange {
+    //moments are resolved each time, so relative moments move with the
+    //daemon
+    async fn resolve(&self, data_dir: &Path) -> Result<DateTimePeriod, Error> {
+        let start = self
+            .start_moment
+            .resolve_date_time(self.start_time, false, data_dir)
+            .await?;
+        let end = self
+            .end_moment
+            .resolve_date_time(self.end_time, true, data_dir)
+            .await?;
+
+        DateTimePeriod::with_start_end_time(start, end)
+    }
+}
+
+//verifies the stored activities of the players (or all synced players if
+//none are specified) against their activity history, queuing any which are
+//missing, and prints a summary
+async fn verify_players(
+    store: &mut ActivityStoreInterface,
+    players: &[PlayerName],
+    range: &VerifyRange,
+    data_dir: &Path,
+) -> Result<(), Error> {
+    let period = range.resolve(data_dir).await?;
+
+    let members = if players.is_empty() {
+        store.get_sync_members().await?
+    } else {
+        let mut out = Vec::with_capacity(players.len());
+        for player in players.iter() {
+            match store.find_member(player, false).await {
+                Ok(e) => out.push(e),
+                Err(e) => tell::error!(
+                    "{}",
+                    format_error(
+                        &format!(
+                            "Could not verify player: {}",
+                            player.get_bungie_name()
+                        ),
+                        e
+                    )
+                ),
+            }
         }
+        out
     };
 
-    info!("Using data directory : {:#?}", &data_dir);
+    if members.is_empty() {
+        return Ok(());
+    }
+
+    tell::update!(
+        "Verifying activities from {} to {} for {} players.",
+        human_date_format(&period.get_start()),
+        human_date_format(&period.get_end()),
+        members.len()
+    );
+
+    let mut results = Vec::with_capacity(members.len());
+    for member in members.iter() {
+        let result = match store.verify_member_history(member, &period).await {
+            Ok(e) => Ok(e),
+            Err(e) => {
+                let name = e.name();
+                tell::error!(
+                    "{}",
+                    format_error(
+                        &format!(
+                            "Error verifying player: {}",
+                            member.name.get_bungie_name()
+                        ),
+                        e
+                    )
+                );
+                Err(name)
+            }
+        };
+        results.push((member, result));
+    }
+
+    tell::update!("");
+    tell::update!("Verify Summary");
+    tell::update!("-------------");
+
+    let col_w = 12;
+    let name_col_w = 32;
+
+    tell::update!(
+        "{:<name_col_w$}{:<col_w$}{:<col_w$}{}",
+        "PLAYER",
+        "CHECKED",
+        "MISSING",
+        "STATUS",
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    let mut total_missing = 0;
+    for (member, result) in results.iter() {
+        let (checked, missing, status) = match result {
+            Ok(e) => {
+                total_missing += e.missing;
+                let status = if e.failed > 0 {
+                    format!("Incomplete ({} histories failed)", e.failed)
+                } else {
+                    "Verified".to_string()
+                };
+                (e.checked.to_string(), e.missing.to_string(), status)
+            }
+            Err(e) => {
+                ("-".to_string(), "-".to_string(), format!("Failed ({})", e))
+            }
+        };
+
+        tell::update!(
+            "{:<name_col_w$}{:<col_w$}{:<col_w$}{}",
+            member.name.get_bungie_name(),
+            checked,
+            missing,
+            status,
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+
+    tell::update!("");
+    tell::update!("{} missing activities queued to be synced.", total_missing);
+    tell::update!("");
+
+    Ok(())
+}
+
+async fn update_store_metrics(
+    store: &mut ActivityStoreInterface,
+    cycle_duration: Duration,
+) {
+    metrics::record_sync_cycle(cycle_duration);
+    metrics::set_database_size(store.get_storage_size());
+
+    match store.get_activity_queue_depth().await {
+        Ok(e) => metrics::set_queue_depth(e.pending, e.failed),
+        Err(e) => {
+            metrics::record_error(&e);
+            tell::error!(
+                "{}",
+                format_error("Error retrieving activity queue depth.", e)
+            );
+        }
+    }
+}
+
+//adds the member to sync, and sets their history limit if one was specified
+async fn add_member_to_sync(
+    store: &mut ActivityStoreInterface,
+    member: &Member,
+    history_limit: &Option<HistoryLimit>,
+) -> Result<(), Error> {
+    store.add_member_to_sync(member).await?;
+
+    if let Some(limit) = history_limit {
+        store.set_history_limit(&member.id, limit).await?;
+    }
+
+    Ok(())
+}
+
+//describes how much of a player's activity history is synced
+fn format_history_limit(limit: &HistoryLimit) -> String {
+    let since = limit.since.map(|e| {
+        format!("Since {}", e.with_timezone(&Local).format("%Y-%m-%d"))
+    });
+    let max_activities = limit.max_activities.map(|e| format!("Last {}", e));
+
+    match (since, max_activities) {
+        (None, None) => "All".to_string(),
+        (Some(a), None) | (None, Some(a)) => a,
+        (Some(a), Some(b)) => format!("{}, {}", a, b),
+    }
+}
+
+async fn search_players(
+    store: &mut ActivityStoreInterface,
+    key: &str,
+    prefix: &str,
+    max_pages: u32,
+    history_limit: &Option<HistoryLimit>,
+    budget: &Option<RequestBudget>,
+) {
+    let stored = match store
+        .search_stored_members(prefix, MAX_STORED_SEARCH_RESULTS)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Error searching data store for players.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
+
+    print_stored_members(&stored);
+
+    let mut candidates: Vec<Member> =
+        stored.iter().map(|e| e.member.clone()).collect();
+
+    if key.is_empty() {
+        tell::update!(
+            "Specify an API key via --api-key to also search Bungie for players."
+        );
+        tell::update!("");
+    } else {
+        let api = match ApiInterface::new_with_key(key) {
+            Ok(mut e) => {
+                if let Some(budget) = budget {
+                    e.set_request_budget(budget.clone());
+                }
+                e
+            }
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error creating interface.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        tell::progress!("Searching Bungie for players");
+        let results =
+            match api.search_players_by_prefix(prefix, max_pages).await {
+                Ok(e) => e,
+                Err(e) => {
+                    tell::error!(
+                        "{}",
+                        format_error("Error searching Bungie for players.", e)
+                    );
+                    std::process::exit(EXIT_FAILURE);
+                }
+            };
+
+        print_player_search_results(&results.players, candidates.len());
+
+        if results.has_more {
+            tell::update!(
+                "Additional players were found. Use --search-pages to retrieve more results."
+            );
+            tell::update!("");
+        }
+
+        candidates.extend(results.players.into_iter().map(|e| e.member));
+    }
+
+    if candidates.is_empty() {
+        return;
+    }
+
+    //only prompt if someone is there to answer
+    if key.is_empty()
+        || !atty::is(atty::Stream::Stdin)
+        || !atty::is(atty::Stream::Stdout)
+    {
+        tell::update!("To sync a player, use --add NAME#CODE");
+        return;
+    }
+
+    print!("Enter the number of a player to add to sync (or press enter to skip) : ");
+    let _ = std::io::stdout().flush();
+
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input).is_err() {
+        return;
+    }
+
+    let input = input.trim();
+    if input.is_empty() {
+        return;
+    }
+
+    let member = match input.parse::<usize>() {
+        Ok(e) if e > 0 && e <= candidates.len() => &candidates[e - 1],
+        _ => {
+            tell::error!("Invalid selection : {}", input);
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
+
+    match add_member_to_sync(store, member, history_limit).await {
+        Ok(_) => tell::update!("Added {}", member.name.get_bungie_name()),
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error(
+                    &format!("Error adding {}.", member.name.get_bungie_name()),
+                    e
+                )
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    }
+    tell::update!("");
+}
+
+fn print_stored_members(members: &[StoredMember]) {
+    tell::update!("Players in Data Store");
+    tell::update!("-------------");
+
+    if members.is_empty() {
+        tell::update!("No matching players found.");
+        tell::update!("");
+        return;
+    }
+
+    let index_col_w = 6;
+    let col_w = 12;
+    let name_col_w = 32;
+
+    tell::update!(
+        "{:<index_col_w$}{:<name_col_w$}{:<col_w$}{}",
+        "#",
+        "PLAYER",
+        "SYNCED",
+        "LAST ACTIVITY",
+        index_col_w = index_col_w,
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    for (i, m) in members.iter().enumerate() {
+        let last_activity = match m.last_activity {
+            Some(e) => human_date_format(&e),
+            None => "Never".to_string(),
+        };
+
+        tell::update!(
+            "{:<index_col_w$}{:<name_col_w$}{:<col_w$}{}",
+            i + 1,
+            m.member.name.get_bungie_name(),
+            m.synced,
+            last_activity,
+            index_col_w = index_col_w,
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+//offset is the number of items listed before these, so numbering continues
+//from the stored players
+fn print_player_search_results(players: &[PlayerSearchResult], offset: usize) {
+    tell::update!("Bungie Players");
+    tell::update!("-------------");
+
+    if players.is_empty() {
+        tell::update!("No matching players found.");
+        tell::update!("");
+        return;
+    }
+
+    let index_col_w = 6;
+    let name_col_w = 32;
+
+    tell::update!(
+        "{:<index_col_w$}{:<name_col_w$}{:<name_col_w$}{}",
+        "#",
+        "PLAYER",
+        "PLATFORMS",
+        "LAST PLAYED",
+        index_col_w = index_col_w,
+        name_col_w = name_col_w,
+    );
+
+    for (i, p) in players.iter().enumerate() {
+        let platforms: Vec<String> =
+            p.platforms.iter().map(|e| e.to_string()).collect();
+
+        let last_played = match p.last_played {
+            Some(e) => human_date_format(&e),
+            None => "Unknown".to_string(),
+        };
+
+        tell::update!(
+            "{:<index_col_w$}{:<name_col_w$}{:<name_col_w$}{}",
+            offset + i + 1,
+            p.member.name.get_bungie_name(),
+            platforms.join(", "),
+            last_played,
+            index_col_w = index_col_w,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_group_subscriptions(subscriptions: &[GroupSubscription]) {
+    tell::update!("Subscribed Groups");
+    tell::update!("-------------");
+
+    if subscriptions.is_empty() {
+        tell::update!("No subscribed groups.");
+        tell::update!("");
+        return;
+    }
+
+    let col_w = 12;
+    let name_col_w = 26;
+
+    tell::update!(
+        "{:<col_w$}{:<col_w$}{:<name_col_w$}{}",
+        "GROUP ID",
+        "PLAYERS",
+        "LAST CHECKED",
+        "REMOVE DEPARTED",
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    for s in subscriptions.iter() {
+        let last_checked = match s.last_checked {
+            Some(e) => human_date_format(&e),
+            None => "Never".to_string(),
+        };
+
+        tell::update!(
+            "{:<col_w$}{:<col_w$}{:<name_col_w$}{}",
+            s.group_id,
+            s.members,
+            last_checked,
+            s.remove_departed,
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_sync_status(statuses: &[SyncStatus]) {
+    tell::update!("Synced Players");
+    tell::update!("-------------");
+
+    if statuses.is_empty() {
+        tell::update!("No players are being synced.");
+        tell::update!("");
+        return;
+    }
+
+    let col_w = 12;
+    let name_col_w = 26;
+
+    tell::update!(
+        "{:<name_col_w$}{:<col_w$}{:<col_w$}{:<col_w$}{:<name_col_w$}{:<name_col_w$}{:<col_w$}{:<col_w$}{}",
+        "PLAYER",
+        "PLATFORM",
+        "CHARACTERS",
+        "ACTIVITIES",
+        "LAST ACTIVITY",
+        "LAST SYNC",
+        "QUEUED",
+        "FAILED",
+        "HISTORY",
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    let format_time = |e: &Option<DateTime<Utc>>| match e {
+        Some(e) => human_date_format(e),
+        None => "Never".to_string(),
+    };
+
+    for s in statuses.iter() {
+        tell::update!(
+            "{:<name_col_w$}{:<col_w$}{:<col_w$}{:<col_w$}{:<name_col_w$}{:<name_col_w$}{:<col_w$}{:<col_w$}{}",
+            s.member.name.get_bungie_name(),
+            s.member.platform.to_string(),
+            s.characters,
+            s.activities,
+            format_time(&s.last_activity),
+            format_time(&s.last_sync),
+            s.queued,
+            s.failed,
+            format_history_limit(&s.history_limit),
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_sync_status_data(output: Output, statuses: &[SyncStatus]) {
+    let format_time = |e: &Option<DateTime<Utc>>| match e {
+        Some(e) => e.to_rfc3339(),
+        None => "".to_string(),
+    };
+
+    let rows: Vec<Vec<(&str, String)>> = statuses
+        .iter()
+        .map(|s| {
+            vec![
+                ("name", s.member.name.get_bungie_name()),
+                ("member_id", s.member.id.to_string()),
+                ("platform", s.member.platform.to_string()),
+                ("characters", s.characters.to_string()),
+                ("activities", s.activities.to_string()),
+                ("last_activity", format_time(&s.last_activity)),
+                ("last_sync", format_time(&s.last_sync)),
+                ("queued", s.queued.to_string()),
+                ("failed", s.failed.to_string()),
+                ("sync_since", format_time(&s.history_limit.since)),
+                (
+                    "max_activities",
+                    s.history_limit
+                        .max_activities
+                        .map(|e| e.to_string())
+                        .unwrap_or_default(),
+                ),
+            ]
+        })
+        .collect();
+
+    print_data_rows(output, rows);
+}
+
+fn print_name_history(member: &Member, history: &[NameHistoryEntry]) {
+    tell::update!("Name History for {}", member.name.get_bungie_name());
+    tell::update!("-------------");
+
+    if history.is_empty() {
+        tell::update!("No names have been recorded for the player.");
+        tell::update!("");
+        return;
+    }
+
+    let name_col_w = 26;
+
+    tell::update!(
+        "{:<name_col_w$}{:<name_col_w$}{}",
+        "NAME",
+        "FIRST SEEN",
+        "LAST SEEN",
+        name_col_w = name_col_w,
+    );
+
+    for h in history.iter() {
+        tell::update!(
+            "{:<name_col_w$}{:<name_col_w$}{}",
+            h.name.get_bungie_name(),
+            human_date_format(&h.first_seen),
+            human_date_format(&h.last_seen),
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_aliases(aliases: &[MemberAlias]) {
+    tell::update!("Aliases");
+    tell::update!("-------------");
+
+    if aliases.is_empty() {
+        tell::update!("No aliases have been set.");
+        tell::update!("");
+        return;
+    }
+
+    let name_col_w = 26;
+
+    tell::update!(
+        "{:<name_col_w$}{:<name_col_w$}{}",
+        "ALIAS",
+        "PLAYER",
+        "MEMBER ID",
+        name_col_w = name_col_w,
+    );
+
+    for a in aliases.iter() {
+        tell::update!(
+            "{:<name_col_w$}{:<name_col_w$}{}",
+            a.alias,
+            a.member.name.get_bungie_name(),
+            a.member.id,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_name_history_data(output: Output, history: &[NameHistoryEntry]) {
+    let rows: Vec<Vec<(&str, String)>> = history
+        .iter()
+        .map(|h| {
+            vec![
+                ("name", h.name.get_bungie_name()),
+                ("first_seen", h.first_seen.to_rfc3339()),
+                ("last_seen", h.last_seen.to_rfc3339()),
+            ]
+        })
+        .collect();
+
+    print_data_rows(output, rows);
+}
+
+//prints rows of name / value pairs as tsv or json
+fn print_data_rows(output: Output, rows: Vec<Vec<(&str, String)>>) {
+    if output == Output::Json {
+        for row in rows {
+            tell::update!("{}", build_json(row));
+        }
+        return;
+    }
+
+    let mut out = String::new();
+    if let Some(first) = rows.first() {
+        let header: Vec<&str> = first.iter().map(|e| e.0).collect();
+        out.push_str(&header.join(TSV_DELIM));
+        out.push_str(TSV_EOL);
+    }
+
+    for row in rows.iter() {
+        let values: Vec<&str> = row.iter().map(|e| e.1.as_str()).collect();
+        out.push_str(&values.join(TSV_DELIM));
+        out.push_str(TSV_EOL);
+    }
+
+    tell::update!("{}", out);
+}
+
+fn print_failed_activities(activities: &[FailedActivity]) {
+    tell::update!("Failed Activities");
+    tell::update!("-------------");
+
+    if activities.is_empty() {
+        tell::update!("No failed activities.");
+        tell::update!("");
+        return;
+    }
+
+    let col_w = 14;
+    let name_col_w = 26;
+
+    tell::update!(
+        "{:<col_w$}{:<name_col_w$}{:<col_w$}{:<col_w$}{:<name_col_w$}{}",
+        "ACTIVITY",
+        "PLAYER",
+        "CHARACTER",
+        "ATTEMPTS",
+        "LAST ATTEMPT",
+        "LAST ERROR",
+        col_w = col_w,
+        name_col_w = name_col_w,
+    );
+
+    for a in activities.iter() {
+        let last_attempt = match a.last_attempt {
+            Some(e) => human_date_format(&e),
+            None => "".to_string(),
+        };
+
+        tell::update!(
+            "{:<col_w$}{:<name_col_w$}{:<col_w$}{:<col_w$}{:<name_col_w$}{}",
+            a.activity_id,
+            a.member.name.get_bungie_name(),
+            a.character_class.to_string(),
+            a.attempts,
+            last_attempt,
+            a.last_error.as_deref().unwrap_or(""),
+            col_w = col_w,
+            name_col_w = name_col_w,
+        );
+    }
+    tell::update!("");
+}
+
+fn print_prune_result(result: &PruneResult) {
+    let rows = [
+        ("Weapon Results", result.weapon_results),
+        ("Medal Results", result.medal_results),
+        ("Activities", result.activities),
+        ("Characters", result.characters),
+        ("Members", result.members),
+    ];
+
+    tell::update!("Pruned");
+    tell::update!("-------------");
+    for (label, count) in rows.iter() {
+        tell::update!("{:<26}{}", label, count);
+    }
+    tell::update!("");
+
+    tell::update!(
+        "{:<26}{} -> {}",
+        "Data Store Size",
+        format_file_size(result.size_before),
+        format_file_size(result.size_after)
+    );
+    tell::update!("");
+}
+
+#[tokio::main]
+async fn main() {
+    let args = args_with_config::<Opt>("dclisync", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
+    env_logger::init();
+
+    let level = if opt.verbose {
+        TellLevel::Verbose
+    } else if opt.daemon {
+        TellLevel::Update
+    } else {
+        TellLevel::Progress
+    };
+
+    Tell::init(level);
+
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+    info!("Using {} Output Level", level);
+
+    info!("Arguments : {:#?}", opt);
+    tell::verbose!("{:#?}", opt);
+
+    let data_dir = match determine_data_dir(opt.data_dir) {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Error initializing storage directory store.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
+
+    info!("Using data directory : {:#?}", &data_dir);
+
+    let key = match opt.api_key {
+        Some(e) => e,
+        None => "".to_string(),
+    };
+
+    //NOTE: if a key is not provided StructOpt should reject input
+    let mut store: ActivityStoreInterface =
+        match ActivityStoreInterface::init_with_path(
+            &data_dir,
+            Some(key.to_string()),
+        )
+        .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error initializing activity store.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+    let budget = if opt.max_requests_per_second > 0 {
+        let budget = RequestBudget::new(opt.max_requests_per_second);
+        store.set_request_budget(budget.clone());
+        Some(budget)
+    } else {
+        None
+    };
+
+    let history_limit =
+        if opt.sync_since.is_some() || opt.max_activities.is_some() {
+            let since = match &opt.sync_since {
+                None | Some(Moment::AllTime) => None,
+                Some(e) => match e
+                    .resolve_date_time(opt.sync_since_time, false, &data_dir)
+                    .await
+                {
+                    Ok(e) => Some(e),
+                    Err(e) => {
+                        tell::error!(
+                            "{}",
+                            format_error(
+                                "Could not determine --sync-since date / time.",
+                                e,
+                            )
+                        );
+                        std::process::exit(EXIT_FAILURE);
+                    }
+                },
+            };
+
+            Some(HistoryLimit {
+                since,
+                max_activities: opt.max_activities.filter(|e| *e > 0),
+            })
+        } else {
+            None
+        };
+
+    if opt.import_group.is_some() {
+        let group_id = opt.import_group.unwrap();
+
+        tell::update!("Import Group ID : {}", group_id);
+
+        let api = match ApiInterface::new_with_key(&key) {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error creating interface.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        let members: Vec<Member> =
+            match api.retrieve_group_members(group_id).await {
+                Ok(e) => e,
+                Err(e) => {
+                    tell::error!(
+                        "{}",
+                        format_error("Error creating interface.", e)
+                    );
+                    std::process::exit(EXIT_FAILURE);
+                }
+            };
+
+        for m in members.iter() {
+            //if they dont have a valid Bungie ID / id, then skip
+            if !m.name.is_valid_bungie_name() {
+                tell::update!(
+                    "No valid Bungie ID and code. Skipping. : {}",
+                    m.name.get_short_name()
+                );
+
+                continue;
+            }
+
+            match add_member_to_sync(&mut store, m, &history_limit).await {
+                Ok(_) => tell::update!("{}", m.name.get_bungie_name()),
+                Err(e) => {
+                    tell::update!(
+                        "Error adding {}. Skipping. {}",
+                        m.name.get_bungie_name(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(group_id) = opt.subscribe_group {
+        if let Err(e) =
+            store.subscribe_group(group_id, opt.remove_departed).await
+        {
+            tell::error!("{}", format_error("Error subscribing to group.", e));
+            std::process::exit(EXIT_FAILURE);
+        }
+
+        tell::update!("Subscribed to Group ID : {}", group_id);
+        reconcile_group(&mut store, group_id, false).await;
+    }
+
+    if let Some(group_id) = opt.unsubscribe_group {
+        match store.unsubscribe_group(group_id).await {
+            Ok(true) => {
+                tell::update!("Unsubscribed from Group ID : {}", group_id)
+            }
+            Ok(false) => {
+                tell::update!("Not subscribed to Group ID : {}", group_id)
+            }
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error unsubscribing from group.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        }
+    }
+
+    if let Some(prefix) = opt.search {
+        search_players(
+            &mut store,
+            &key,
+            &prefix,
+            opt.search_pages,
+            &history_limit,
+            &budget,
+        )
+        .await;
+    }
+
+    if opt.add.is_some() {
+        let players = opt.add.unwrap();
+
+        tell::update!("Added");
+        tell::update!("-------------");
+        for player in players.iter() {
+            let result = match store.find_member(player, false).await {
+                Ok(m) => {
+                    add_member_to_sync(&mut store, &m, &history_limit).await
+                }
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(_) => tell::update!("{}", player.get_bungie_name()),
+                Err(e) => {
+                    tell::update!(
+                        "Error adding {}. Skipping. {}",
+                        player.get_bungie_name(),
+                        e
+                    );
+                }
+            }
+        }
+        tell::update!("");
+    }
+
+    if opt.remove.is_some() {
+        let players = opt.remove.unwrap();
+
+        tell::update!("Removed");
+        tell::update!("-------------");
+
+        for player in players.iter() {
+            match store.remove_player_from_sync(player).await {
+                Ok(_) => tell::update!("{}", player.get_bungie_name()),
+                Err(e) => {
+                    tell::update!(
+                        "Error removing {}. {}",
+                        player.get_bungie_name(),
+                        e
+                    );
+                }
+            }
+        }
+        tell::update!("");
+    }
+
+    if let Some(aliases) = opt.alias {
+        tell::update!("Aliases Set");
+        tell::update!("-------------");
+
+        for a in aliases.iter() {
+            let member = match store.resolve_member(&a.player, true).await {
+                Ok(e) => e,
+                Err(e) => {
+                    tell::update!(
+                        "Error finding player for {}. Skipping. {}",
+                        a.alias,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            match store.set_alias(&a.alias, &member).await {
+                Ok(_) => tell::update!(
+                    "{} : {}",
+                    a.alias,
+                    member.name.get_bungie_name()
+                ),
+                Err(e) => {
+                    tell::update!(
+                        "Error setting alias {}. Skipping. {}",
+                        a.alias,
+                        e
+                    );
+                }
+            }
+        }
+        tell::update!("");
+    }
+
+    if let Some(aliases) = opt.remove_alias {
+        tell::update!("Aliases Removed");
+        tell::update!("-------------");
+
+        for alias in aliases.iter() {
+            match store.remove_alias(alias).await {
+                Ok(true) => tell::update!("{}", alias),
+                Ok(false) => tell::update!("{} : Alias not found", alias),
+                Err(e) => {
+                    tell::update!("Error removing alias {}. {}", alias, e);
+                }
+            }
+        }
+        tell::update!("");
+    }
+
+    if let Some(path) = opt.merge {
+        tell::update!("Merging {}", path.display());
+
+        let result = match store.merge_store(&path).await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error merging data store.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_transfer_result("Merged", &result);
+    }
 
-    let key = match opt.api_key {
-        Some(e) => e,
-        None => "".to_string(),
-    };
+    if let Some(path) = opt.restore {
+        tell::update!("Restoring {}", path.display());
 
-    //NOTE: if a key is not provided StructOpt should reject input
-    let mut store: ActivityStoreInterface =
-        match ActivityStoreInterface::init_with_path(
-            &data_dir,
-            Some(key.to_string()),
-        )
-        .await
+        let result = match store.import_archive(&path).await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!("{}", format_error("Error restoring archive.", e));
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_transfer_result("Restored", &result);
+    }
+
+    if let Some(path) = opt.export {
+        let member = match opt.export_name {
+            Some(e) => match store.resolve_member(&e, false).await {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    tell::error!(
+                        "{}",
+                        format_error("Error retrieving member.", e)
+                    );
+                    std::process::exit(EXIT_FAILURE);
+                }
+            },
+            None => None,
+        };
+
+        let start_time = match opt
+            .export_start_moment
+            .resolve_date_time(opt.export_start_time, false, &data_dir)
+            .await
         {
             Ok(e) => e,
             Err(e) => {
                 tell::error!(
                     "{}",
-                    format_error("Error initializing activity store.", e)
+                    format_error(
+                        "Could not determine --export-start-moment date / time.",
+                        e,
+                    )
                 );
                 std::process::exit(EXIT_FAILURE);
             }
         };
 
-    if opt.import_group.is_some() {
-        let group_id = opt.import_group.unwrap();
-
-        tell::update!("Import Group ID : {}", group_id);
-
-        let api = match ApiInterface::new_with_key(&key) {
+        let end_time = match opt
+            .export_end_moment
+            .resolve_date_time(opt.export_end_time, true, &data_dir)
+            .await
+        {
             Ok(e) => e,
             Err(e) => {
                 tell::error!(
                     "{}",
-                    format_error("Error creating interface.", e)
+                    format_error(
+                        "Could not determine --export-end-moment date / time.",
+                        e,
+                    )
                 );
                 std::process::exit(EXIT_FAILURE);
             }
         };
 
-        let members: Vec<Member> =
-            match api.retrieve_group_members(group_id).await {
+        let period = match DateTimePeriod::with_start_end_time(
+            start_time, end_time,
+        ) {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                        "{}",
+                        format_error(
+                            "--export-end-moment must be after --export-start-moment.",
+                            e,
+                        )
+                    );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        tell::update!("Exporting {}", path.display());
+
+        let result = match store
+            .export_archive(&path, member.as_ref(), &period)
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!("{}", format_error("Error exporting archive.", e));
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_transfer_result("Exported", &result);
+    }
+
+    if opt.check {
+        let mut manifest = match ManifestInterface::new(&data_dir, false).await
+        {
+            Ok(e) => Some(e),
+            Err(_e) => {
+                tell::update!(
+                    "Manifest not found. Skipping manifest checks. Run dclim to download the manifest."
+                );
+                None
+            }
+        };
+
+        tell::update!("Checking {}", store.get_storage_path());
+
+        let issues =
+            match store.check_integrity(manifest.as_mut(), opt.fix).await {
                 Ok(e) => e,
                 Err(e) => {
                     tell::error!(
                         "{}",
-                        format_error("Error creating interface.", e)
+                        format_error("Error checking data store.", e)
                     );
                     std::process::exit(EXIT_FAILURE);
                 }
             };
 
-        for m in members.iter() {
-            //if they dont have a valid Bungie ID / id, then skip
-            if !m.name.is_valid_bungie_name() {
-                tell::update!(
-                    "No valid Bungie ID and code. Skipping. : {}",
-                    m.name.get_short_name()
-                );
-
-                continue;
-            }
+        print_integrity_issues(&issues);
+    }
 
-            match store.add_member_to_sync(m).await {
-                Ok(_) => tell::update!("{}", m.name.get_bungie_name()),
-                Err(e) => {
-                    tell::update!(
-                        "Error adding {}. Skipping. {}",
-                        m.name.get_bungie_name(),
-                        e
+    if opt.prune {
+        let activities_before = match opt.prune_activities_moment {
+            Some(e) => {
+                match e.resolve_date_time(None, false, &data_dir).await {
+                    Ok(e) => Some(e),
+                    Err(e) => {
+                        tell::error!(
+                        "{}",
+                        format_error(
+                            "Could not determine --prune-activities-moment date / time.",
+                            e,
+                        )
                     );
+                        std::process::exit(EXIT_FAILURE);
+                    }
                 }
             }
+            None => None,
+        };
+
+        let options = PruneOptions {
+            detail_before: opt
+                .prune_detail_days
+                .map(|e| Utc::now() - chrono::Duration::days(e as i64)),
+            activities_before,
+        };
+
+        tell::update!("Pruning {}", store.get_storage_path());
+
+        let result = match store.prune(&options).await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error pruning data store.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_prune_result(&result);
+    }
+
+    if opt.retry_failed {
+        match store.retry_failed_activities().await {
+            Ok(e) => {
+                tell::update!("{} failed activities queued to retry.", e);
+                tell::update!("");
+            }
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error retrying failed activities.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
         }
     }
 
-    if opt.add.is_some() {
-        let players = opt.add.unwrap();
+    store.set_max_activity_attempts(opt.max_attempts);
+    store.set_max_concurrent_syncs(opt.max_concurrent_syncs);
 
-        tell::update!("Added");
-        tell::update!("-------------");
-        for player in players.iter() {
-            match store.add_player_to_sync(player).await {
-                Ok(_) => tell::update!("{}", player.get_bungie_name()),
-                Err(e) => {
-                    tell::update!(
-                        "Error adding {}. Skipping. {}",
-                        player.get_bungie_name(),
-                        e
-                    );
-                }
-            }
+    if opt.prefetch_combat_ratings {
+        store.set_combat_rating_prefetch(Some(chrono::Duration::hours(
+            opt.combat_rating_ttl as i64,
+        )));
+    }
+
+    let verify_range = VerifyRange {
+        start_moment: opt.verify_start_moment,
+        start_time: opt.verify_start_time,
+        end_moment: opt.verify_end_moment,
+        end_time: opt.verify_end_time,
+    };
+
+    if let Some(players) = &opt.verify {
+        if let Err(e) =
+            verify_players(&mut store, players, &verify_range, &data_dir).await
+        {
+            tell::error!(
+                "{}",
+                format_error("Error verifying activity history.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
         }
-        tell::update!("");
     }
 
-    if opt.remove.is_some() {
-        let players = opt.remove.unwrap();
+    if opt.sync.is_some() {
+        let mut scheduler: Option<Scheduler> = None;
+        let mut api: Option<ApiInterface> = None;
 
-        tell::update!("Removed");
-        tell::update!("-------------");
+        let refresh_interval = if opt.adaptive {
+            if opt.min_interval == 0
+                || opt.min_interval > opt.idle_check_interval
+                || opt.idle_check_interval > opt.max_interval
+            {
+                tell::error!(
+                    "--min-interval must be greater than 0, and less than or equal to --idle-check-interval, which must be less than or equal to --max-interval."
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
 
-        for player in players.iter() {
-            match store.remove_player_from_sync(player).await {
-                Ok(_) => tell::update!("{}", player.get_bungie_name()),
+            api = match ApiInterface::new_with_key(&key) {
+                Ok(mut e) => {
+                    //checking if players are playing counts against the
+                    //same budget as syncing them
+                    if let Some(budget) = &budget {
+                        e.set_request_budget(budget.clone());
+                    }
+                    Some(e)
+                }
                 Err(e) => {
-                    tell::update!(
-                        "Error removing {}. {}",
-                        player.get_bungie_name(),
-                        e
+                    tell::error!(
+                        "{}",
+                        format_error("Error creating interface.", e)
                     );
+                    std::process::exit(EXIT_FAILURE);
                 }
-            }
-        }
-        tell::update!("");
-    }
+            };
 
-    if opt.sync.is_some() {
-        let refresh_interval = match opt.interval {
-            Some(e) => e,
-            None => DEFAULT_REFRESH_INTERVAL,
+            scheduler = Some(Scheduler::new(
+                Duration::from_secs(opt.min_interval as u64),
+                Duration::from_secs(opt.idle_check_interval as u64),
+                Duration::from_secs(opt.max_interval as u64),
+            ));
+
+            opt.min_interval
+        } else {
+            match opt.interval {
+                Some(e) => e,
+                None => DEFAULT_REFRESH_INTERVAL,
+            }
         };
 
         let sleep_duration = Duration::from_secs(refresh_interval as u64);
@@ -420,27 +2271,85 @@ async fn main() {
             });
         }
 
-        if opt.daemon {
+        if opt.adaptive {
+            tell::update!(
+                "Beginning Sync in Adaptive Daemon Mode with {} to {} second interval (quiet mode)",
+                opt.min_interval,
+                opt.max_interval
+            );
+        } else if opt.daemon {
             tell::update!(
                 "Beginning Sync in Daemon Mode with {} second interval (quiet mode)",
                 refresh_interval
             );
         }
 
+        if let Some(address) = opt.metrics_address {
+            if let Err(e) = metrics::serve(address) {
+                tell::error!(
+                    "{}",
+                    format_error(
+                        &format!("Could not serve metrics on {}.", address),
+                        e
+                    )
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+
+            tell::update!(
+                "Serving metrics at http://{}{}",
+                address,
+                METRICS_PATH
+            );
+        }
+
         let players = opt.sync.unwrap();
+        //number of syncs since players were last verified in daemon mode
+        let mut unverified_cycles: u32 = 0;
 
         loop {
+            let cycle_start = Instant::now();
+
             if players.is_empty() {
-                match store.sync_all().await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        tell::error!("{}", format_error("Error syncing.", e));
-                        std::process::exit(EXIT_FAILURE);
-                    }
+                reconcile_due_groups(&mut store, opt.group_interval).await;
+            }
+
+            if opt.daemon && opt.verify_cycles > 0 {
+                unverified_cycles += 1;
+            }
+
+            if opt.verify_cycles > 0 && unverified_cycles >= opt.verify_cycles {
+                unverified_cycles = 0;
+                if let Err(e) = verify_players(
+                    &mut store,
+                    &players,
+                    &verify_range,
+                    &data_dir,
+                )
+                .await
+                {
+                    metrics::record_error(&e);
+                    tell::error!(
+                        "{}",
+                        format_error("Error verifying activity history.", e)
+                    );
                 }
+            }
+
+            if let (Some(scheduler), Some(api)) =
+                (scheduler.as_mut(), api.as_ref())
+            {
+                sync_adaptive(&mut store, api, scheduler, &players).await;
             } else {
-                match store.sync_players(&players).await {
-                    Ok(_) => {}
+                let start = Instant::now();
+                let results = if players.is_empty() {
+                    store.sync_all().await
+                } else {
+                    store.sync_players(&players).await
+                };
+
+                match results {
+                    Ok(e) => print_sync_summary(&e, start.elapsed()),
                     Err(e) => {
                         tell::error!("{}", format_error("Error syncing.", e));
                         std::process::exit(EXIT_FAILURE);
@@ -448,6 +2357,10 @@ async fn main() {
                 }
             }
 
+            if opt.metrics_address.is_some() {
+                update_store_metrics(&mut store, cycle_start.elapsed()).await;
+            }
+
             let s = *exit_code.lock().unwrap();
             if s != SHOULD_CONTINUE_CODE {
                 info!("Exiting loop. Exit code : {}", s);
@@ -468,19 +2381,95 @@ async fn main() {
     }
 
     if opt.list {
-        let members = match store.get_sync_members().await {
-            Ok(m) => m,
+        let statuses = match store.retrieve_sync_status().await {
+            Ok(e) => e,
             Err(e) => {
-                tell::error!("{}", format_error("Error syncing.", e));
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving synced players.", e)
+                );
                 std::process::exit(EXIT_FAILURE);
             }
         };
 
-        tell::update!("Synced Players");
-        tell::update!("-------------");
-        for member in members.iter() {
-            tell::update!("{}", member.name.get_bungie_name());
+        match opt.output {
+            Output::Default => print_sync_status(&statuses),
+            Output::Tsv | Output::Json => {
+                print_sync_status_data(opt.output, &statuses)
+            }
         }
-        tell::update!("");
+    }
+
+    if let Some(name) = opt.name_history {
+        let member = match store.resolve_member(&name, false).await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!("{}", format_error("Error retrieving member.", e));
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        let history = match store.retrieve_name_history(&member).await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving name history.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        match opt.output {
+            Output::Default => print_name_history(&member, &history),
+            Output::Tsv | Output::Json => {
+                print_name_history_data(opt.output, &history)
+            }
+        }
+    }
+
+    if opt.aliases {
+        let aliases = match store.retrieve_aliases().await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving aliases.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_aliases(&aliases);
+    }
+
+    if opt.groups {
+        let subscriptions = match store.retrieve_group_subscriptions().await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving group subscriptions.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_group_subscriptions(&subscriptions);
+    }
+
+    if opt.failed {
+        let activities = match store.retrieve_failed_activities().await {
+            Ok(e) => e,
+            Err(e) => {
+                tell::error!(
+                    "{}",
+                    format_error("Error retrieving failed activities.", e)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        };
+
+        print_failed_activities(&activities);
     }
 }
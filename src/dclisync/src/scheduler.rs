@@ -0,0 +1,442 @@
+/*
+* Copyright 2023 Mike Chambers
+* https://github.com/mikechambers/dcli
+*
+* Permission is hereby granted, free of charge, to any person obtaining a copy of
+* this software and associated documentation files (the "Software"), to deal in
+* the Software without restriction, including without limitation the rights to
+* use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies
+* of the Software, and to permit persons to whom the Software is furnished to do
+* so, subject to the following conditions:
+*
+* The above copyright notice and this permission notice shall be included in all
+* copies or substantial portions of the Software.
+*
+* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+* IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+* FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+* COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+* IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+* CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//Schedules player syncs when running in adaptive daemon mode.
+//
+//Each player's current activity is checked via the API. Players who are in,
+//or have just left a Crucible activity are checked every min interval, and
+//synced as soon as their activity ends (and again on the next check, in case
+//the activity was not available from the API yet). Players who are not
+//playing are checked less often, backing off to the idle check interval, and
+//are synced at least once every max interval. If a sync fails, it is retried
+//on the player's next check.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use dcli::apiinterface::ApiInterface;
+use dcli::crucible::Member;
+use dcli::metrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncReason {
+    Initial,
+    ActivityEnded,
+    FollowUp,
+    Interval,
+    Retry,
+}
+
+impl fmt::Display for SyncReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let out = match self {
+            SyncReason::Initial => "first sync",
+            SyncReason::ActivityEnded => "activity ended",
+            SyncReason::FollowUp => "follow up after activity",
+            SyncReason::Interval => "max interval",
+            SyncReason::Retry => "retry after failed sync",
+        };
+
+        write!(f, "{}", out)
+    }
+}
+
+//result of checking a player's current activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckResult {
+    //in a crucible activity which started at the specified time
+    Playing(DateTime<Utc>),
+    NotPlaying,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Intervals {
+    min: Duration,
+    idle: Duration,
+    max: Duration,
+}
+
+struct MemberSchedule {
+    next_check: Instant,
+    check_interval: Duration,
+    last_sync: Option<Instant>,
+
+    //start time of the crucible activity the player was last seen in
+    activity_started: Option<DateTime<Utc>>,
+    follow_up: bool,
+
+    //whether the last sync failed, and should be retried
+    retry: bool,
+}
+
+impl MemberSchedule {
+    fn new(now: Instant, intervals: &Intervals) -> MemberSchedule {
+        MemberSchedule {
+            next_check: now,
+            check_interval: intervals.min,
+            last_sync: None,
+            activity_started: None,
+            follow_up: false,
+            retry: false,
+        }
+    }
+
+    fn is_check_due(&self, now: Instant) -> bool {
+        now >= self.next_check
+    }
+
+    //updates the schedule with the result of a check, and returns whether
+    //the player should be synced because of it
+    fn record_check(
+        &mut self,
+        now: Instant,
+        result: CheckResult,
+        intervals: &Intervals,
+    ) -> Option<SyncReason> {
+        let activity_started = match result {
+            CheckResult::Playing(e) => Some(e),
+            CheckResult::NotPlaying => None,
+            CheckResult::Failed => {
+                self.check_interval =
+                    (self.check_interval * 2).min(intervals.idle);
+                self.next_check = now + self.check_interval;
+                return None;
+            }
+        };
+
+        //if the start time changed, the previous activity ended, even if they
+        //went straight into another one
+        let ended = self.activity_started.is_some()
+            && self.activity_started != activity_started;
+
+        let reason = if ended {
+            self.follow_up = true;
+            Some(SyncReason::ActivityEnded)
+        } else if self.follow_up {
+            self.follow_up = false;
+            Some(SyncReason::FollowUp)
+        } else {
+            None
+        };
+
+        self.check_interval = if activity_started.is_some() || reason.is_some()
+        {
+            intervals.min
+        } else {
+            (self.check_interval * 2).min(intervals.idle)
+        };
+
+        self.activity_started = activity_started;
+        self.next_check = now + self.check_interval;
+
+        //retries dont reset the backoff, so players whose syncs keep failing
+        //are not synced every check
+        reason.or_else(|| self.retry.then_some(SyncReason::Retry))
+    }
+
+    fn record_sync(&mut self, now: Instant, succeeded: bool) {
+        if succeeded {
+            self.last_sync = Some(now);
+        }
+        self.retry = !succeeded;
+    }
+
+    //whether the player should be synced regardless of their activity
+    fn interval_reason(
+        &self,
+        now: Instant,
+        intervals: &Intervals,
+    ) -> Option<SyncReason> {
+        match self.last_sync {
+            None => Some(SyncReason::Initial),
+            Some(e) if now - e >= intervals.max => Some(SyncReason::Interval),
+            _ => None,
+        }
+    }
+}
+
+pub struct Scheduler {
+    intervals: Intervals,
+    members: HashMap<i64, MemberSchedule>,
+}
+
+impl Scheduler {
+    pub fn new(
+        min_interval: Duration,
+        idle_check_interval: Duration,
+        max_interval: Duration,
+    ) -> Scheduler {
+        Scheduler {
+            intervals: Intervals {
+                min: min_interval,
+                idle: idle_check_interval,
+                max: max_interval,
+            },
+            members: HashMap::new(),
+        }
+    }
+
+    /// Checks the current activity of players who are due to be checked, and
+    /// returns the players who should be synced now, along with why.
+    pub async fn find_due_members<'a>(
+        &mut self,
+        api: &ApiInterface,
+        members: &'a [Member],
+    ) -> Vec<(&'a Member, SyncReason)> {
+        let mut out = Vec::new();
+        let now = Instant::now();
+        let intervals = self.intervals;
+
+        for member in members.iter() {
+            let schedule = self
+                .members
+                .entry(member.id)
+                .or_insert_with(|| MemberSchedule::new(now, &intervals));
+
+            let mut reason = None;
+
+            if schedule.is_check_due(now) {
+                let result = check_member(api, member).await;
+                reason =
+                    schedule.record_check(Instant::now(), result, &intervals);
+            }
+
+            if reason.is_none() {
+                reason = schedule.interval_reason(now, &intervals);
+            }
+
+            if let Some(e) = reason {
+                out.push((member, e));
+            }
+        }
+
+        out
+    }
+
+    /// Records that the player was synced successfully.
+    pub fn record_sync(&mut self, member: &Member) {
+        if let Some(e) = self.members.get_mut(&member.id) {
+            e.record_sync(Instant::now(), true);
+        }
+    }
+
+    /// Records that syncing the player failed, so it is retried on the
+    /// player's next check.
+    pub fn record_failed_sync(&mut self, member: &Member) {
+        if let Some(e) = self.members.get_mut(&member.id) {
+            e.record_sync(Instant::now(), false);
+        }
+    }
+}
+
+async fn check_member(api: &ApiInterface, member: &Member) -> CheckResult {
+    let activity = match api
+        .retrieve_current_activity(member.id, member.platform)
+        .await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tell::verbose!(
+                "Could not check current activity for {} : {}",
+                member.name.get_bungie_name(),
+                e
+            );
+            metrics::record_error(&e);
+            return CheckResult::Failed;
+        }
+    };
+
+    match activity.filter(|e| {
+        e.current_activity_mode_type
+            .map(|e| e.is_crucible())
+            .unwrap_or(false)
+    }) {
+        Some(e) => CheckResult::Playing(e.date_activity_started),
+        None => CheckResult::NotPlaying,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn intervals() -> Intervals {
+        Intervals {
+            min: Duration::from_secs(30),
+            idle: Duration::from_secs(300),
+            max: Duration::from_secs(1800),
+        }
+    }
+
+    fn started(minute: u32) -> CheckResult {
+        CheckResult::Playing(
+            Utc.with_ymd_and_hms(2023, 5, 1, 12, minute, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_initial_and_interval_sync() {
+        let intervals = intervals();
+        let now = Instant::now();
+        let mut schedule = MemberSchedule::new(now, &intervals);
+
+        assert!(schedule.is_check_due(now));
+        assert_eq!(
+            schedule.interval_reason(now, &intervals),
+            Some(SyncReason::Initial)
+        );
+
+        schedule.record_sync(now, true);
+        assert_eq!(
+            schedule.interval_reason(now + Duration::from_secs(60), &intervals),
+            None
+        );
+        assert_eq!(
+            schedule.interval_reason(now + intervals.max, &intervals),
+            Some(SyncReason::Interval)
+        );
+    }
+
+    #[test]
+    fn test_activity_ended_and_follow_up() {
+        let intervals = intervals();
+        let now = Instant::now();
+        let mut schedule = MemberSchedule::new(now, &intervals);
+
+        assert_eq!(schedule.record_check(now, started(0), &intervals), None);
+        assert_eq!(schedule.check_interval, intervals.min);
+
+        //same activity
+        assert_eq!(schedule.record_check(now, started(0), &intervals), None);
+
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::ActivityEnded)
+        );
+        assert_eq!(schedule.check_interval, intervals.min);
+
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::FollowUp)
+        );
+        assert_eq!(schedule.check_interval, intervals.min);
+
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            None
+        );
+        assert_eq!(schedule.check_interval, intervals.min * 2);
+    }
+
+    #[test]
+    fn test_new_activity_ends_previous() {
+        let intervals = intervals();
+        let now = Instant::now();
+        let mut schedule = MemberSchedule::new(now, &intervals);
+
+        schedule.record_check(now, started(0), &intervals);
+        assert_eq!(
+            schedule.record_check(now, started(10), &intervals),
+            Some(SyncReason::ActivityEnded)
+        );
+        assert_eq!(schedule.check_interval, intervals.min);
+    }
+
+    #[test]
+    fn test_idle_backoff_capped() {
+        let intervals = intervals();
+        let now = Instant::now();
+        let mut schedule = MemberSchedule::new(now, &intervals);
+
+        for _ in 0..10 {
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals);
+        }
+        assert_eq!(schedule.check_interval, intervals.idle);
+        assert_eq!(schedule.next_check, now + intervals.idle);
+
+        for _ in 0..10 {
+            schedule.record_check(now, CheckResult::Failed, &intervals);
+        }
+        assert_eq!(schedule.check_interval, intervals.idle);
+
+        //failed checks do not lose track of the activity
+        schedule.record_check(now, started(0), &intervals);
+        assert_eq!(schedule.check_interval, intervals.min);
+        schedule.record_check(now, CheckResult::Failed, &intervals);
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::ActivityEnded)
+        );
+    }
+
+    #[test]
+    fn test_failed_sync_retried_on_next_check() {
+        let intervals = intervals();
+        let now = Instant::now();
+        let mut schedule = MemberSchedule::new(now, &intervals);
+
+        schedule.record_sync(now, true);
+        schedule.record_check(now, started(0), &intervals);
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::ActivityEnded)
+        );
+
+        //failed syncs are not treated as synced
+        schedule.record_sync(now + intervals.max, false);
+        assert_eq!(
+            schedule.interval_reason(now + intervals.max, &intervals),
+            Some(SyncReason::Interval)
+        );
+
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::FollowUp)
+        );
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::Retry)
+        );
+        assert_eq!(schedule.check_interval, intervals.min * 2);
+
+        //failed checks keep the retry
+        assert_eq!(
+            schedule.record_check(now, CheckResult::Failed, &intervals),
+            None
+        );
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            Some(SyncReason::Retry)
+        );
+
+        schedule.record_sync(now, true);
+        assert_eq!(
+            schedule.record_check(now, CheckResult::NotPlaying, &intervals),
+            None
+        );
+    }
+}
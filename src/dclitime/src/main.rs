@@ -22,13 +22,26 @@
 
 mod datetimeformat;
 
+use chrono::Local;
 use datetimeformat::DateTimeFormat;
+use dcli::config::args_with_config;
 use dcli::enums::moment::Moment;
 use dcli::output::Output;
-use dcli::utils::build_tsv;
+use dcli::utils::{
+    build_tsv, determine_data_dir, format_error, parse_and_validate_tsv_output,
+    EXIT_FAILURE,
+};
+use std::path::PathBuf;
 use structopt::StructOpt;
 use tell::{Tell, TellLevel};
 
+//options which can be set in the dcli.toml config file
+const CONFIG_OPTIONS: &[(&str, Option<char>)] = &[
+    ("moment", Some('T')),
+    ("time-format", Some('f')),
+    ("data-dir", Some('D')),
+];
+
 #[derive(StructOpt, Debug)]
 #[structopt(verbatim_doc_comment)]
 /// Command line tool for retrieving date / time stamps for Destiny 2 weekly event
@@ -45,15 +58,36 @@ use tell::{Tell, TellLevel};
 ///
 /// Released under an MIT License.
 struct Opt {
-    /// The weekly Destiny 2 moment to retrieve the date / time stamp for
+    /// The Destiny 2 moment to retrieve the date / time stamp for
+    ///
+    /// Valid values include now, daily (last daily reset), next_daily, weekend
+    /// (last weekend reset on Friday), next_weekend, weekly (last weekly reset
+    /// on Tuesday), next_weekly, day, week, month, all_time and season moments
+    /// such as launch or season_of_the_wish.
+    ///
+    /// Relative and calendar moments are also supported. These include a
+    /// number of minutes (m), hours (h), days (d) or weeks (w) before now
+    /// (i.e. 90m, 36h, 3d, 2w), a date in YYYY-MM-DD format, today and
+    /// yesterday (start of the day in the local time zone) and N weekly resets
+    /// ago (i.e. "2 weekly resets ago", 2_weekly_resets_ago or weekly:-2).
     ///
-    /// Valid values are now, current_weekly (previous Tuesday weekly reset),
-    /// next_weekly (upcoming Tuesday weekly reset), current_daily, next_daily,
-    /// current_xur (previous Friday Xur reset), next_xur (upcoming Friday Xur reset),
-    /// current_trials (previous Friday Trials reset), next_trials (upcoming Friday Trials reset)
+    /// Season moments season:current, season:previous and season:<number>
+    /// (i.e. season:22) are read from the manifest, which can be downloaded
+    /// with dclim. When used as an end moment by other tools, they resolve to
+    /// the end of the season.
+    ///
+    /// These moments are accepted by all of the dcli tools which take a
+    /// moment.
     #[structopt(short = "T", long = "moment", default_value = "now")]
     moment: Moment,
 
+    /// Directory where Destiny 2 manifest database file is stored. (optional)
+    ///
+    /// Only required for season moments. This will normally be downloaded
+    /// using the dclim tool, and uses a system appropriate directory by default.
+    #[structopt(short = "D", long = "data-dir", parse(from_os_str))]
+    data_dir: Option<PathBuf>,
+
     /// Date / time format to output moment
     ///
     /// Valid values are rfc3339 (default), rfc2822 and unix (unix timestamp,
@@ -74,14 +108,23 @@ struct Opt {
     #[structopt(
         short = "O",
         long = "output-format",
+        parse(try_from_str = parse_and_validate_tsv_output),
         default_value = "default"
     )]
+    //json is rejected by parse_and_validate_tsv_output
     output: Output,
-}
 
+    /// Name of a profile in the dcli.toml config file to load options from
+    ///
+    /// Profiles can set options such as name, mode, class and moment
+    /// together. Options specified on the command line take precedence.
+    #[structopt(long = "profile")]
+    profile: Option<String>,
+}
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
+    let args = args_with_config::<Opt>("dclitime", CONFIG_OPTIONS);
+    let opt = Opt::from_iter(args);
 
     let level = if opt.verbose {
         TellLevel::Verbose
@@ -91,27 +134,62 @@ async fn main() {
 
     Tell::init(level);
 
+    if let Some(e) = &opt.profile {
+        tell::verbose!("Using config profile : {}", e);
+    }
+
     log::info!("{:#?}", opt.verbose);
     tell::verbose!("{:#?}", opt.verbose);
 
-    let dt = opt.moment.get_date_time();
+    let data_dir = match determine_data_dir(opt.data_dir) {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Error initializing data directory.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
+
+    let dt = match opt.moment.resolve_date_time(None, false, &data_dir).await {
+        Ok(e) => e,
+        Err(e) => {
+            tell::error!(
+                "{}",
+                format_error("Could not determine --moment date / time.", e)
+            );
+            std::process::exit(EXIT_FAILURE);
+        }
+    };
     let date_time_str = match opt.time_format {
         DateTimeFormat::RFC3339 => dt.to_rfc3339(),
         DateTimeFormat::RFC2822 => dt.to_rfc2822(),
         DateTimeFormat::Unix => dt.timestamp().to_string(),
     };
 
+    let local_date_time_str = dt.with_timezone(&Local).to_rfc3339();
+
     match opt.output {
         Output::Default => {
             tell::update!("{}", date_time_str);
+            tell::verbose!(
+                "{} resolves to {} ({} local)",
+                opt.moment,
+                dt.to_rfc3339(),
+                local_date_time_str
+            );
         }
         Output::Tsv => {
             let mut name_values: Vec<(&str, String)> = Vec::new();
             name_values.push(("date_time", date_time_str));
             name_values.push(("format", format!("{}", opt.time_format)));
             name_values.push(("moment", format!("{}", opt.moment)));
+            name_values.push(("local_date_time", local_date_time_str));
 
             tell::update!("{}", build_tsv(name_values));
         }
+        //rejected by parse_and_validate_tsv_output
+        Output::Json => unreachable!(),
     }
 }